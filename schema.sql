@@ -13,6 +13,8 @@ CREATE TABLE IF NOT EXISTS articles (
     author TEXT,
     text TEXT,
     source TEXT,
+    followup_of INTEGER REFERENCES articles(id) ON DELETE SET NULL, -- published article it only restates
+    story_id INTEGER REFERENCES stories(id) ON DELETE SET NULL, -- story thread it belongs to
     FOREIGN KEY(fetch_id) REFERENCES fetches(id) ON DELETE CASCADE
 );
 
@@ -21,6 +23,13 @@ CREATE TABLE IF NOT EXISTS summaries (
     fetch_id INTEGER UNIQUE, -- 1 summary per fetch
     generated_at TEXT DEFAULT CURRENT_TIMESTAMP,
     mood_text TEXT,
+    sentiment REAL, -- -1.0 (grim) to 1.0 (elated)
+    category_sentiment TEXT, -- JSON array of {category, score}
+    match_section TEXT, -- JSON match preview or report, if any
+    kind TEXT NOT NULL DEFAULT 'daily', -- daily | weekly | monthly | superseded
+    period_start TEXT, -- first day covered by a roll-up
+    period_end TEXT, -- last day covered by a roll-up
+    version_of INTEGER, -- for a superseded summary, the fetch it was written for
     sent BOOLEAN NOT NULL DEFAULT 0,
     FOREIGN KEY(fetch_id) REFERENCES fetches(id) ON DELETE CASCADE
 );
@@ -30,12 +39,227 @@ CREATE TABLE IF NOT EXISTS bullets (
     fetch_id INTEGER,
     text TEXT,
     accepted BOOLEAN DEFAULT NULL, -- NULL = not yet filtered, TRUE/FALSE = LLM decision
+    reason TEXT, -- why the deduplicator decided as it did
+    duplicate_of INTEGER REFERENCES bullets(id) ON DELETE SET NULL, -- the bullet a rejected one repeats
+    category TEXT, -- digest section, one of the configured categories
+    importance INTEGER, -- 1 (filler) to 10 (huge news), from the summarizer
+    deferred BOOLEAN NOT NULL DEFAULT 0, -- accepted but cut by the digest caps, carried to the next run
+    carryovers INTEGER NOT NULL DEFAULT 0, -- how many runs this bullet has been carried over
+    grounded BOOLEAN DEFAULT NULL, -- NULL = not checked, TRUE/FALSE = supported by its sources
+    grounding_notes TEXT, -- claims the grounding check could not find in the sources
+    headline TEXT, -- headline-length variant of text
+    detail TEXT, -- paragraph-length variant of text
+    story_id INTEGER REFERENCES stories(id) ON DELETE SET NULL, -- story thread it belongs to
+    update_on TEXT, -- title of the earlier reported story it follows up, shown as "Update on …"
     FOREIGN KEY(fetch_id) REFERENCES fetches(id) ON DELETE CASCADE
 );
 
-CREATE VIEW IF NOT EXISTS latest_rejected_bullets AS
-SELECT b.id, b.text, f.fetched_at
+CREATE TABLE IF NOT EXISTS bullet_sources (
+    bullet_id INTEGER NOT NULL,
+    article_id INTEGER NOT NULL,
+    PRIMARY KEY (bullet_id, article_id),
+    FOREIGN KEY(bullet_id) REFERENCES bullets(id) ON DELETE CASCADE,
+    FOREIGN KEY(article_id) REFERENCES articles(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS bullet_embeddings (
+    bullet_id INTEGER PRIMARY KEY,
+    model TEXT NOT NULL,
+    vector BLOB NOT NULL, -- little-endian f32s
+    FOREIGN KEY(bullet_id) REFERENCES bullets(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS article_embeddings (
+    article_id INTEGER PRIMARY KEY,
+    model TEXT NOT NULL,
+    vector BLOB NOT NULL, -- little-endian f32s
+    FOREIGN KEY(article_id) REFERENCES articles(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS transfer_rumours (
+    id INTEGER PRIMARY KEY,
+    player TEXT NOT NULL,
+    from_club TEXT,
+    to_club TEXT,
+    direction TEXT NOT NULL, -- in | out
+    fee TEXT, -- as reported, e.g. "£45m"
+    status TEXT NOT NULL, -- rumour | talks | agreed | done | collapsed
+    first_seen TEXT DEFAULT CURRENT_TIMESTAMP,
+    last_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS transfer_updates (
+    id INTEGER PRIMARY KEY,
+    rumour_id INTEGER NOT NULL,
+    fetch_id INTEGER,
+    status TEXT NOT NULL,
+    fee TEXT,
+    source TEXT, -- site the report came from
+    url TEXT,
+    reported_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(rumour_id) REFERENCES transfer_rumours(id) ON DELETE CASCADE,
+    FOREIGN KEY(fetch_id) REFERENCES fetches(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS player_availability (
+    id INTEGER PRIMARY KEY,
+    player TEXT NOT NULL,
+    status TEXT NOT NULL, -- available | doubtful | out | suspended
+    issue TEXT, -- injury, illness or suspension
+    expected_return TEXT, -- as reported, e.g. "after the international break"
+    first_reported TEXT DEFAULT CURRENT_TIMESTAMP,
+    last_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS availability_updates (
+    id INTEGER PRIMARY KEY,
+    availability_id INTEGER NOT NULL,
+    fetch_id INTEGER,
+    status TEXT NOT NULL,
+    issue TEXT,
+    expected_return TEXT,
+    source TEXT,
+    url TEXT,
+    reported_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(availability_id) REFERENCES player_availability(id) ON DELETE CASCADE,
+    FOREIGN KEY(fetch_id) REFERENCES fetches(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS llm_calls (
+    id INTEGER PRIMARY KEY,
+    fetch_id INTEGER,
+    step TEXT NOT NULL, -- summarize, dedup, …
+    model TEXT NOT NULL,
+    flex BOOLEAN NOT NULL,
+    attempt INTEGER NOT NULL, -- 1-based across the retry/fallback chain
+    succeeded BOOLEAN NOT NULL,
+    error TEXT,
+    latency_ms INTEGER,
+    prompt_tokens INTEGER,
+    completion_tokens INTEGER, -- includes reasoning tokens
+    reasoning_tokens INTEGER,
+    cost_usd REAL, -- NULL when the model has no configured price
+    called_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(fetch_id) REFERENCES fetches(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS content_cuts (
+    id INTEGER PRIMARY KEY,
+    fetch_id INTEGER,
+    step TEXT NOT NULL, -- the prompt the articles were cut for: summarize, transfers, …
+    article_id INTEGER,
+    class TEXT NOT NULL, -- news | opinion | listicle | duplicate
+    weight REAL NOT NULL,
+    body_tokens INTEGER NOT NULL,
+    kept_tokens INTEGER NOT NULL, -- 0 when dropped
+    dropped BOOLEAN NOT NULL,
+    cut_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(fetch_id) REFERENCES fetches(id) ON DELETE CASCADE,
+    FOREIGN KEY(article_id) REFERENCES articles(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    fetch_id INTEGER, -- set once discovery finds new articles
+    status TEXT NOT NULL DEFAULT 'running', -- running | done | failed | abandoned
+    stage TEXT, -- last stage that finished
+    attempts INTEGER NOT NULL DEFAULT 1,
+    options TEXT NOT NULL, -- JSON RunOptions
+    state TEXT, -- JSON RunState handed on by the last finished stage
+    started_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    finished_at TEXT,
+    updated_at TEXT, -- last sign of life, a 'running' run quiet for long has crashed
+    FOREIGN KEY(fetch_id) REFERENCES fetches(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS run_stages (
+    id INTEGER PRIMARY KEY,
+    run_id INTEGER NOT NULL,
+    stage TEXT NOT NULL, -- discover | scrape | summarize | dedup | persist | deliver
+    status TEXT NOT NULL, -- running | done | skipped | failed
+    error TEXT,
+    duration_ms INTEGER,
+    started_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    finished_at TEXT,
+    FOREIGN KEY(run_id) REFERENCES runs(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS stories (
+    id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL, -- of the article or bullet that started it
+    first_seen TEXT NOT NULL,
+    last_updated TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS entities (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    kind TEXT NOT NULL -- player | club | competition | staff
+);
+
+CREATE TABLE IF NOT EXISTS article_entities (
+    article_id INTEGER NOT NULL,
+    entity_id INTEGER NOT NULL,
+    mentions INTEGER NOT NULL, -- times the title and text name it, 1 when only the LLM found it
+    method TEXT NOT NULL, -- alias | llm
+    PRIMARY KEY (article_id, entity_id),
+    FOREIGN KEY(article_id) REFERENCES articles(id) ON DELETE CASCADE,
+    FOREIGN KEY(entity_id) REFERENCES entities(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS bullet_entities (
+    bullet_id INTEGER NOT NULL,
+    entity_id INTEGER NOT NULL,
+    PRIMARY KEY (bullet_id, entity_id),
+    FOREIGN KEY(bullet_id) REFERENCES bullets(id) ON DELETE CASCADE,
+    FOREIGN KEY(entity_id) REFERENCES entities(id) ON DELETE CASCADE
+);
+
+-- full-text indexes over articles and bullets, kept in sync by the triggers below.
+-- Db::open rebuilds an index the first time it is created, so existing rows are searchable.
+CREATE VIRTUAL TABLE IF NOT EXISTS articles_fts USING fts5(
+    og_title, author, text,
+    content = 'articles', content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER IF NOT EXISTS articles_fts_insert AFTER INSERT ON articles BEGIN
+    INSERT INTO articles_fts(rowid, og_title, author, text) VALUES (new.id, new.og_title, new.author, new.text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS articles_fts_delete AFTER DELETE ON articles BEGIN
+    INSERT INTO articles_fts(articles_fts, rowid, og_title, author, text) VALUES ('delete', old.id, old.og_title, old.author, old.text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS articles_fts_update AFTER UPDATE OF og_title, author, text ON articles BEGIN
+    INSERT INTO articles_fts(articles_fts, rowid, og_title, author, text) VALUES ('delete', old.id, old.og_title, old.author, old.text);
+    INSERT INTO articles_fts(rowid, og_title, author, text) VALUES (new.id, new.og_title, new.author, new.text);
+END;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS bullets_fts USING fts5(
+    text,
+    content = 'bullets', content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER IF NOT EXISTS bullets_fts_insert AFTER INSERT ON bullets BEGIN
+    INSERT INTO bullets_fts(rowid, text) VALUES (new.id, new.text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS bullets_fts_delete AFTER DELETE ON bullets BEGIN
+    INSERT INTO bullets_fts(bullets_fts, rowid, text) VALUES ('delete', old.id, old.text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS bullets_fts_update AFTER UPDATE OF text ON bullets BEGIN
+    INSERT INTO bullets_fts(bullets_fts, rowid, text) VALUES ('delete', old.id, old.text);
+    INSERT INTO bullets_fts(rowid, text) VALUES (new.id, new.text);
+END;
+
+DROP VIEW IF EXISTS latest_rejected_bullets;
+CREATE VIEW latest_rejected_bullets AS
+SELECT b.id, b.text, f.fetched_at, b.reason, b.duplicate_of, m.text AS duplicate_of_text
 FROM bullets b
 JOIN fetches f ON b.fetch_id = f.id
-WHERE f.id = (SELECT MAX(id) FROM fetches)
+LEFT JOIN bullets m ON m.id = b.duplicate_of
+WHERE f.id = (SELECT MAX(fetch_id) FROM summaries WHERE kind = 'daily')
   AND b.accepted = 0;
@@ -1,34 +1,48 @@
-use async_openai::{config::OpenAIConfig, types::ReasoningEffort};
-use anyhow::{Result, Context};
-use async_openai::{
-    types::{
-        ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
-        CreateChatCompletionRequestArgs, ResponseFormat, ResponseFormatJsonSchema,
-    },
-    Client,
-};
+use anyhow::Result;
+use async_openai::types::{ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage};
 use serde::Deserialize;
 use serde_json::json;
+use chrono::Utc;
 
-use crate::config::Config;
-use crate::models::{Bullet, Summary};
+use crate::config::{Config, PromptsConfig};
+use crate::llm::{chat_json, chat_json_validated, JsonCall};
+use crate::models::{Bullet, DuplicateOf, Summary};
+use crate::squad::Squad;
+
+const BASE_COMPLETION_TOKENS: u32 = 2000; // the model's reasoning
+const DECISION_COMPLETION_TOKENS: u32 = 150; // one candidate's or pair's verdict with its reason
+const MAX_COMPLETION_TOKENS: u32 = 32000;
+
+/// Completion tokens for a reply with one verdict per each of `decisions`
+fn completion_budget(decisions: usize) -> u32 {
+    (BASE_COMPLETION_TOKENS + DECISION_COMPLETION_TOKENS * decisions as u32).min(MAX_COMPLETION_TOKENS)
+}
+
+#[derive(Debug, Deserialize)]
+struct DedupDecision {
+    accepted: bool,
+    reason: String,
+    duplicate_of_previous: Option<usize>,
+    duplicate_of_candidate: Option<usize>,
+}
 
 #[derive(Debug, Deserialize)]
 struct DedupResponse {
-    results: Vec<bool>,
+    results: Vec<DedupDecision>,
 }
 
 pub async fn ai_deduplicate(cfg: &Config, previous_bullets: &[Bullet], current_summary: &Summary) -> Result<Summary> {
-    let api_key = &cfg.api_key;
-
-    let openai_config = OpenAIConfig::default().with_api_key(api_key);
-    let client = Client::with_config(openai_config);
-
     // Extract text content
-    let prev_texts: Vec<String> = previous_bullets.iter().map(|b| b.text.clone()).collect();
+    // Previous bullets are labelled with their age so older ones can be weighed less
+    let now = Utc::now().naive_utc();
+    let prev_texts: Vec<String> = previous_bullets
+        .iter()
+        .map(|b| format!("({:.0} days ago) {}", b.age_days(now).floor(), b.text))
+        .collect();
     let curr_texts: Vec<String> = current_summary.items.iter().map(|b| b.text.clone()).collect();
 
-    let system_prompt = r#"
+    let custom_prompt = PromptsConfig::load(&cfg.prompts.dedup)?;
+    let mut system_prompt = custom_prompt.unwrap_or_else(|| r#"
 You are a helpful assistant for summarizing Liverpool FC news.
 
 You are given:
@@ -36,22 +50,47 @@ You are given:
 - A list of new candidate bullet points for today’s summary
 
 Your job is to compare each candidate bullet to all the previous ones and decide:
-  - true  → if this bullet is **meaningfully different** and should be included
-  - false → if it is **too similar or repetitive**, and should be discarded
+  - accepted: true  → if this bullet is **meaningfully different** and should be included
+  - accepted: false → if it is **too similar or repetitive**, and should be discarded
 
-Sometimes, today's bullet points are repetitive as well; please also give false to a bullet point if there is another bullet point from today that is making the same point.
+Sometimes, today's bullet points are repetitive as well; please also reject a bullet point if an earlier candidate from today is making the same point.
 
-The goal is to end up with a list of "true" bullet points that are informative but not repetitive.
+The goal is to end up with a list of accepted bullet points that are informative but not repetitive.
 
-Respond only with a structured JSON array of true/false, in the same order as the candidate bullets.
-"#;
+For every candidate also give:
+  - reason: one short sentence explaining the decision
+  - duplicate_of_previous: the number of the previous bullet it repeats, or null
+  - duplicate_of_candidate: the number of the earlier candidate it repeats, or null
+
+Respond only with structured JSON containing one result per candidate bullet, in the same order as the candidate bullets.
+"#.to_string());
+
+    if cfg.dedup.decay_per_day > 0.0 {
+        system_prompt.push_str(
+            "\nThe older a previous bullet is, the less it should suppress a candidate: reject a candidate because of an older bullet only if it is clearly the same story with nothing new.\n"
+        );
+    }
+
+    if let Some(context) = Squad::load(cfg)?.prompt_context() {
+        system_prompt.push_str(&format!("\nPlayers and staff go by several names; this list tells you who is who.\n\n{context}\n"));
+    }
 
     let schema = json!({
         "type": "object",
         "properties": {
             "results": {
                 "type": "array",
-                "items": { "type": "boolean" }
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "accepted": { "type": "boolean" },
+                        "reason": { "type": "string" },
+                        "duplicate_of_previous": { "type": ["integer", "null"] },
+                        "duplicate_of_candidate": { "type": ["integer", "null"] }
+                    },
+                    "required": ["accepted", "reason", "duplicate_of_previous", "duplicate_of_candidate"],
+                    "additionalProperties": false
+                }
             }
         },
         "required": ["results"],
@@ -60,67 +99,135 @@ Respond only with a structured JSON array of true/false, in the same order as th
 
     let user_prompt = format!(
         "PREVIOUS BULLETS:\n{}\n\nCANDIDATE BULLETS:\n{}",
-        prev_texts.iter().map(|s| format!("- {}", s)).collect::<Vec<_>>().join("\n"),
-        curr_texts.iter().map(|s| format!("- {}", s)).collect::<Vec<_>>().join("\n")
+        prev_texts.iter().enumerate().map(|(i, s)| format!("{}. {}", i, s)).collect::<Vec<_>>().join("\n"),
+        curr_texts.iter().enumerate().map(|(i, s)| format!("{}. {}", i, s)).collect::<Vec<_>>().join("\n")
     );
 
-    let response_format = ResponseFormat::JsonSchema {
-        json_schema: ResponseFormatJsonSchema {
-            description: None,
-            name: "dedup_filter".into(),
-            schema: Some(schema),
-            strict: Some(true),
-        },
-    };
-
-    let request = CreateChatCompletionRequestArgs::default()
-        .model(&cfg.model)
-        .messages([
+    let expected = current_summary.items.len();
+    let parsed: DedupResponse = chat_json_validated(cfg, JsonCall {
+        step: "dedup",
+        schema_name: "dedup_filter",
+        schema,
+        messages: vec![
             ChatCompletionRequestSystemMessage::from(system_prompt).into(),
             ChatCompletionRequestUserMessage::from(user_prompt).into(),
-        ])
-        .response_format(response_format)
-        .max_completion_tokens(2000u32)
-        .reasoning_effort(ReasoningEffort::Low)
-        .service_tier(async_openai::types::ServiceTier::Flex)
-        .build()
-        .context("Failed to build deduplication request")?;
-
-    let response = client.chat().create(request).await.context("Deduplication call failed")?;
-
-    for choice in response.choices {
-        if let Some(content) = choice.message.content {
-            let parsed: DedupResponse = serde_json::from_str(&content)
-                .context("Failed to parse deduplication JSON response")?;
-
-            if parsed.results.len() != current_summary.items.len() {
-                anyhow::bail!(
-                    "LLM returned {} results, expected {}",
-                    parsed.results.len(),
-                    current_summary.items.len()
-                );
+        ],
+        max_completion_tokens: completion_budget(expected),
+    }, |r: &DedupResponse| {
+        if r.results.len() == expected {
+            Ok(())
+        } else {
+            Err(format!("returned {} results, expected one per candidate ({})", r.results.len(), expected))
+        }
+    }).await?;
+
+    // Apply decisions to each bullet
+    let updated_bullets: Vec<Bullet> = current_summary
+        .items
+        .iter()
+        .zip(parsed.results)
+        .enumerate()
+        .map(|(i, (b, decision))| {
+            // only a rejected bullet points at what it repeats
+            let duplicate_of = if decision.accepted {
+                None
+            } else if let Some(p) = decision.duplicate_of_previous {
+                previous_bullets.get(p).and_then(|prev| prev.id).map(DuplicateOf::Previous)
+            } else {
+                decision.duplicate_of_candidate
+                    .filter(|&c| c != i && c < current_summary.items.len())
+                    .map(DuplicateOf::Candidate)
+            };
+            Bullet {
+                accepted: Some(decision.accepted),
+                reason: Some(decision.reason),
+                duplicate_of,
+                ..b.clone()
             }
+        })
+        .collect();
 
-            // Apply decisions to each bullet
-            let updated_bullets: Vec<Bullet> = current_summary
-                .items
-                .iter()
-                .zip(parsed.results.into_iter())
-                .map(|(b, accepted)| Bullet {
-                    text: b.text.clone(),
-                    accepted: Some(accepted),
-                })
-                .collect();
-
-            let updated_summary = Summary {
-                mood: current_summary.mood.clone(),
-                date: current_summary.date,
-                items: updated_bullets,
-            };
+    Ok(Summary {
+        items: updated_bullets,
+        ..current_summary.clone()
+    })
+}
 
-            return Ok(updated_summary);
-        }
+#[derive(Debug, Deserialize)]
+struct PairVerdict {
+    index: usize,
+    duplicate: bool,
+    reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PairResponse {
+    pairs: Vec<PairVerdict>,
+}
+
+/// Ask the model whether each (candidate, previous) pair reports the same story, and why.
+/// Pairs the model leaves out are treated as not duplicates.
+pub async fn ai_confirm_duplicates(cfg: &Config, pairs: &[(String, String)]) -> Result<Vec<(bool, String)>> {
+    if pairs.is_empty() {
+        return Ok(vec![]);
     }
 
-    anyhow::bail!("No valid content in OpenAI response")
+    let system_prompt = r#"
+You are a helpful assistant for summarizing Liverpool FC news.
+
+You are given numbered pairs of bullet points. In each pair, A is a candidate for today's summary and B is a bullet that was already published or already chosen for today.
+
+For each pair, decide whether A is a duplicate of B:
+  - true  → A reports the same story as B and adds nothing meaningful
+  - false → A is a different story, or a genuine new development of B's story
+
+Respond only with a JSON object listing every pair index with its verdict and a one-sentence reason.
+"#;
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "pairs": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "index": { "type": "integer" },
+                        "duplicate": { "type": "boolean" },
+                        "reason": { "type": "string" }
+                    },
+                    "required": ["index", "duplicate", "reason"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["pairs"],
+        "additionalProperties": false
+    });
+
+    let user_prompt = pairs
+        .iter()
+        .enumerate()
+        .map(|(i, (a, b))| format!("{}.\nA: {}\nB: {}", i, a, b))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let parsed: PairResponse = chat_json(cfg, JsonCall {
+        step: "dedup_pairs",
+        schema_name: "dedup_pairs",
+        schema,
+        messages: vec![
+            ChatCompletionRequestSystemMessage::from(system_prompt).into(),
+            ChatCompletionRequestUserMessage::from(user_prompt).into(),
+        ],
+        max_completion_tokens: completion_budget(pairs.len()),
+    }).await?;
+
+    let mut verdicts = vec![(false, "No verdict from the LLM".to_string()); pairs.len()];
+    for v in parsed.pairs {
+        if let Some(slot) = verdicts.get_mut(v.index) {
+            *slot = (v.duplicate, v.reason);
+        }
+    }
+    Ok(verdicts)
 }
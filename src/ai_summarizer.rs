@@ -1,43 +1,60 @@
-use anyhow::{Context, Result};
-use async_openai::{
-    Client, config::OpenAIConfig, types::{
-        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs, ReasoningEffort, ResponseFormat, ResponseFormatJsonSchema
-    }
+use std::collections::HashSet;
+
+use anyhow::Result;
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage
 };
 use serde::{Deserialize};
 use serde_json::json;
-use tokio::time::Duration;
-use tiktoken_rs::{o200k_base, CoreBPE};
-use chrono::Local;
+use tiktoken_rs::CoreBPE;
+use chrono::{Local, NaiveDate};
 use log::{debug, info};
 
-use crate::{calendar::Fixture, config::Config};
-use crate::models::{NewsArticle, Summary, Bullet};
+use crate::{calendar::MatchMode, config::{Config, PromptsConfig}};
+use crate::llm::{chat_json, JsonCall};
+use crate::content_budget::truncate_content;
+use crate::models::{CategorySentiment, NewsArticle, Summary, Bullet};
+use crate::squad::Squad;
+
+const BASE_COMPLETION_TOKENS: u32 = 4000; // mood, sentiments and the model's reasoning
+const ITEM_COMPLETION_TOKENS: u32 = 300; // one item in all three variants, with its category and sources
+const MAX_COMPLETION_TOKENS: u32 = 32000;
+
+/// Completion tokens for a reply of up to `items` items
+fn completion_budget(items: usize) -> u32 {
+    (BASE_COMPLETION_TOKENS + ITEM_COMPLETION_TOKENS * items as u32).min(MAX_COMPLETION_TOKENS)
+}
+
+#[derive(Debug, Deserialize)]
+struct RawAiItem {
+    text: String,
+    headline: String,
+    detail: String,
+    category: String,
+    importance: u8,
+    sources: Vec<usize>,
+}
 
 #[derive(Debug, Deserialize)]
 struct RawAiSummary {
     mood: String,
-    items: Vec<String>,
+    sentiment: f32,
+    category_sentiment: Vec<CategorySentiment>,
+    items: Vec<RawAiItem>,
 }
-const MAX_TOKENS: usize = 350_000;
-const MIN_BODY_TOKENS: usize = 40; // don't over-trim tiny bodies
-const SEP_TOKENS_PER_ARTICLE: usize = 6; // rough buffer for "\n\n" joins
 
-pub async fn summarize_articles(cfg: &Config, articles: &[NewsArticle], fixture: &Option<Fixture>) -> Result<Summary> {
+/// Candidate bullets from `articles`; the `background` ones (by position) only restate published
+/// stories and are there for context
+pub async fn summarize_articles(cfg: &Config, articles: &[NewsArticle], background: &HashSet<usize>, match_mode: &Option<MatchMode>) -> Result<Summary> {
     debug!("Starting summarize_articles with {} articles", articles.len());
-    let api_key = &cfg.api_key;
-
-    let openai_config = OpenAIConfig::default().with_api_key(api_key);
-    let client = Client::with_config(openai_config);
-    debug!("Created OpenAI client");
-
     debug!("Starting content truncation for {} articles", articles.len());
-    let combined_text: String = truncate_content(articles)?;
+    let combined_text: String = truncate_content(cfg, "summarize", articles, background)?;
     debug!("Content truncated, final length: {} characters", combined_text.len());
 
     let mut system_prompt = String::new();
 
-    let prompt = r#"
+    let custom_prompt = PromptsConfig::load(&cfg.prompts.summarize)?;
+    let prompt = custom_prompt.as_deref().unwrap_or(r#"
         You are a Liverpool (LFC) fan and supporter. You have access to some news published about the club from the last 24 hours.
 
         Analyze all the provided articles and create a summary of the key developments and trends from the past 24 hours.
@@ -45,12 +62,22 @@ pub async fn summarize_articles(cfg: &Config, articles: &[NewsArticle], fixture:
         Return only a JSON object with this structure:
         {
           "mood": string,
-          "items": [string, string, ...]
+          "sentiment": number,
+          "category_sentiment": [{ "category": string, "score": number }, ...],
+          "items": [{ "text": string, "headline": string, "detail": string, "category": string, "importance": integer, "sources": [integer] }, ...]
         }
 
         The "mood" string should be a ONE-SENTENCE summary stating whether the news is mostly positive, mostly negative, or mixed, and very briefly why. You can start it with "The mood is …".
 
-        Each item in the "items" array is a bullet point summarizing some news/development about Liverpool's men's team. Feel free to end the bullet point text with an appropriate emoji. Don't repeat the same story across multiple bullet points, even if there are multiple articles talking about it. They should be roughly ordered by importance.
+        "sentiment" scores the same mood from -1.0 (grim: heavy defeats, serious injuries, key players leaving) through 0.0 (neutral or mixed) to 1.0 (elated: big wins, trophies, major signings). "category_sentiment" gives the same score for each category that has at least one item.
+
+        Each item's "text" is a bullet point summarizing some news/development about Liverpool's men's team. Feel free to end the bullet point text with an appropriate emoji. Don't repeat the same story across multiple bullet points, even if there are multiple articles talking about it.
+
+        Each item's "headline" is the same news in at most 10 words, like a headline or a push notification, and its "detail" is a short paragraph (3 to 5 sentences) with the context and specifics a fan reading at length would want. Both follow the same rules as "text".
+
+        Each item's "sources" lists the numbers of the articles (shown as [n] before each title) the bullet point is based on. Only state facts, numbers and names that appear in those articles.
+
+        Each item's "importance" scores how much a fan would care, from 1 (filler) to 10 (huge news such as a major signing, a serious injury to a key player, or a title-deciding result). Use the whole range; most days have only a couple of items above 7.
 
         Feel free to be biased towards our beloved club. Use casual language and emojis.
 
@@ -60,32 +87,16 @@ pub async fn summarize_articles(cfg: &Config, articles: &[NewsArticle], fixture:
         The most important areas that fans would care about are potential transfers, injuries, player/team stats, and match summaries/previews.
 
         Stylistically, go easy on the em dashes.
-        "#;
+        "#);
 
     system_prompt.push_str(prompt);
+    system_prompt.push_str("\nFile each item under exactly one of these categories:\n");
+    for c in &cfg.categories {
+        system_prompt.push_str(&format!("- {}\n", c.prompt_line()));
+    }
     system_prompt.push_str(&format!("Today's date is {}. Even though articles are published either today or yesterday, they may be referencing events and news that happened a long time ago. Don't summarize those, as they have likely been covered by previous summaries.", Local::now().date_naive().format("%Y-%m-%d").to_string()));
 
-    let schema = json!({
-      "type": "object",
-      "properties": {
-        "mood": { "type": "string" },
-        "items": {
-          "type": "array",
-          "items": { "type": "string" }
-        }
-      },
-      "required": ["mood", "items"],
-      "additionalProperties": false
-    });
-
-    let response_format = ResponseFormat::JsonSchema {
-        json_schema: ResponseFormatJsonSchema {
-            description: None,
-            name: "lfc_summary".to_string(),
-            schema: Some(schema),
-            strict: Some(true),
-        },
-    };
+    let schema = summary_schema(cfg);
 
     debug!("Building OpenAI request with model: {}", cfg.model);
     let mut messages: Vec<ChatCompletionRequestMessage> = vec![
@@ -94,190 +105,217 @@ pub async fn summarize_articles(cfg: &Config, articles: &[NewsArticle], fixture:
 
     messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage::from(combined_text)));
 
-    if let Some(f) = fixture {
-        info!("There is a fixture today: {} at {}. Inserting a dynamic prompt about it…", &f.opponent, &f.date);
-        let prompt = format!("We are playing against {} today at {}. The first bullet point you generate should be about that match.", f.opponent, f.date);
-        messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage::from(prompt)));
+    let squad = Squad::load(cfg)?;
+    if let Some(context) = squad.prompt_context() {
+        messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage::from(context)));
     }
 
-    let request = CreateChatCompletionRequestArgs::default()
-        .model(&cfg.model)
-        .messages(messages)
-        .response_format(response_format)
-        .service_tier(async_openai::types::ServiceTier::Flex)
-        .max_completion_tokens(5000u32)
-        .reasoning_effort(ReasoningEffort::Low)
-        .build()
-        .context("Failed to build OpenAI request")?;
-
-    debug!("Making OpenAI API call with 60s timeout...");
-    let start_time = std::time::Instant::now();
-    let response = match tokio::time::timeout(
-        Duration::from_secs(60), // adjust as needed
-        client.chat().create(request)
-    ).await {
-        Ok(api_result) => {
-            let elapsed = start_time.elapsed();
-            debug!("OpenAI API call completed in {:?}", elapsed);
-            match api_result {
-                Ok(response) => response,
-                Err(api_error) => {
-                    debug!("OpenAI API returned error: {:?}", api_error);
-                    return Err(anyhow::anyhow!("OpenAI API error: {}", api_error));
-                }
-            }
-        }
-        Err(_timeout_error) => {
-            let elapsed = start_time.elapsed();
-            debug!("OpenAI API call timed out after {:?}", elapsed);
-            return Err(anyhow::anyhow!("OpenAI API call timed out after 60 seconds"));
-        }
-    };
+    if !background.is_empty() {
+        messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage::from(
+            "Articles marked (background only) restate stories that earlier digests already covered. Use them for context, but don't write bullet points based only on them.".to_string()
+        )));
+    }
 
-    debug!("Processing {} response choices", response.choices.len());
-    debug!("Here is the raw response: {:#?}", response);
-    for choice in response.choices {
-        if let Some(content) = choice.message.content {
-            debug!("Received response content, length: {} chars", content.len());
-            debug!("Response content: {}", content);
-            let raw: RawAiSummary = serde_json::from_str(&content)
-                .context("Failed to parse AI JSON summary")?;
-
-            let items: Vec<Bullet> = raw.items.into_iter()
-                .map(|text| Bullet {
-                    text,
-                    accepted: None,
-                })
-                .collect();
-
-            debug!("Successfully created summary with {} items", items.len());
-            return Ok(Summary {
-                mood: raw.mood,
-                items,
-                date: chrono::Utc::now().date_naive(), // fills in today's date
-            });
-        }
+    if cfg.transfers.enabled {
+        // the transfer tracker reports rumours and deals whose status changed
+        messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage::from(
+            "Transfer rumours, bids, negotiations and completed deals are tracked separately, so leave them out of the bullet points. Other transfer-window news, such as squad planning or contract renewals, is fine.".to_string()
+        )));
     }
 
-    debug!("No valid content found in OpenAI response");
-    anyhow::bail!("No valid content in OpenAI response");
-}
+    if cfg.availability.enabled {
+        messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage::from(
+            "Injury, illness and suspension updates are tracked separately, so leave them out of the bullet points.".to_string()
+        )));
+    }
 
-fn decode_first_n_tokens(bpe: &CoreBPE, s: &str, n: usize) -> String {
-    if n == 0 || s.is_empty() {
-        return String::new();
+    let match_prompt = match match_mode {
+        // the match-day section carries the preview or report, the bullets cover the rest
+        Some(MatchMode::PreMatch(f)) if cfg.match_day.enabled => Some(format!(
+            "We are playing against {} today at {}. A separate preview covers team news, injuries and the opponent's form for that match, so leave those out of the bullet points unless they are about something else.",
+            f.opponent, f.date
+        )),
+        Some(MatchMode::PostMatch(f)) if cfg.match_day.enabled => Some(format!(
+            "We played against {} yesterday. A separate match report covers the score, scorers, player ratings and reactions, so leave those out of the bullet points and cover the rest of the news.",
+            f.opponent
+        )),
+        Some(MatchMode::PreMatch(f)) => Some(format!(
+            "We are playing against {} today at {}. The first bullet point you generate should be about that match.",
+            f.opponent, f.date
+        )),
+        _ => None,
+    };
+    if let Some(prompt) = match_prompt {
+        info!("Match mode {:?}. Inserting a dynamic prompt about it…", match_mode);
+        messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage::from(prompt)));
     }
-    let ids = bpe.encode_with_special_tokens(s);
-    let keep = ids.len().min(n);
-    bpe.decode(ids[..keep].to_vec()).unwrap_or_default()
+
+    let raw: RawAiSummary = chat_json(cfg, JsonCall {
+        step: "summarize",
+        schema_name: "lfc_summary",
+        schema,
+        messages,
+        // a busy day can have about a bullet per article
+        max_completion_tokens: completion_budget(articles.len().max(10)),
+    }).await?;
+
+    let mut summary = into_summary(raw, articles);
+    squad.fix_spellings(&mut summary);
+    debug!("Successfully created summary with {} items", summary.items.len());
+    Ok(summary)
 }
 
-fn truncate_content(articles: &[NewsArticle]) -> Result<String> {
-    debug!("Starting truncate_content with {} articles", articles.len());
-    let bpe = o200k_base().unwrap();
-    debug!("Initialized BPE tokenizer");
-
-    // token counts
-    let mut title_tok = Vec::with_capacity(articles.len());
-    let mut body_tok  = Vec::with_capacity(articles.len());
-    for (i, a) in articles.iter().enumerate() {
-        let title_tokens = bpe.encode_with_special_tokens(&a.og_title).len();
-        let body_tokens = bpe.encode_with_special_tokens(&a.text).len();
-        title_tok.push(title_tokens);
-        body_tok.push(body_tokens);
-        debug!("Article {}: title={} tokens, body={} tokens", i, title_tokens, body_tokens);
-    }
+/// Condense the bullets sent between `start` and `end` into a recap, with the daily mood
+/// sentences and the titles of the articles scraped in that time as context
+pub async fn summarize_period(
+    cfg: &Config,
+    label: &str,
+    (start, end): (NaiveDate, NaiveDate),
+    bullets: &[Bullet],
+    moods: &[(String, String)],
+    articles: &[NewsArticle],
+    max_bullets: usize,
+) -> Result<Summary> {
+    debug!("Starting summarize_period for {} bullets and {} articles", bullets.len(), articles.len());
 
-    // we "keep" full bodies initially, then level them down
-    let mut keep_body = body_tok.clone();
+    let mut system_prompt = format!(r#"
+        You are a Liverpool (LFC) fan and supporter writing a "{label} in review" recap for fans who skipped the daily news.
 
-    // total estimate (titles + kept bodies + separators)
-    let mut total = title_tok.iter().sum::<usize>()
-        + keep_body.iter().sum::<usize>()
-        + SEP_TOKENS_PER_ARTICLE * articles.len();
+        You are given the bullet points sent in the daily summaries from {start} to {end}, the mood of each day, and the numbered titles of the articles they were based on.
 
-    debug!("Initial token count: {} (max allowed: {})", total, MAX_TOKENS);
+        Return only a JSON object with this structure:
+        {{
+          "mood": string,
+          "sentiment": number,
+          "category_sentiment": [{{ "category": string, "score": number }}, ...],
+          "items": [{{ "text": string, "headline": string, "detail": string, "category": string, "importance": integer, "sources": [integer] }}, ...]
+        }}
 
-    if total <= MAX_TOKENS {
-        debug!("Content fits within token limit, no truncation needed");
-        let combined = articles
-            .iter()
-            .map(|a| format!("{}\n\n{}", a.og_title, a.text))
-            .collect::<Vec<_>>()
-            .join("\n\n");
-        return Ok(combined);
-    }
+        "mood" is a ONE-SENTENCE verdict on the {label} as a whole. "sentiment" scores it from -1.0 (grim) to 1.0 (elated), and "category_sentiment" does the same per category.
 
-    // helper to find indices of the (trimmable) max and second max bodies
-    let find_two_largest = |keep: &Vec<usize>| -> Option<(usize, usize)> {
-        // consider only entries strictly above MIN_BODY_TOKENS
-        let mut max_i: Option<usize> = None;
-        let mut second_i: Option<usize> = None;
-
-        for i in 0..keep.len() {
-            if keep[i] <= MIN_BODY_TOKENS { continue; }
-            match max_i {
-                None => max_i = Some(i),
-                Some(mi) => {
-                    if keep[i] > keep[mi] {
-                        second_i = max_i;
-                        max_i = Some(i);
-                    } else if second_i.map_or(true, |si| keep[i] > keep[si]) && i != mi {
-                        second_i = Some(i);
-                    }
-                }
-            }
-        }
-        max_i.map(|mi| (mi, second_i.unwrap_or(mi)))
-    };
+        Write at most {max_bullets} items. Merge the daily bullets that follow the same story into one item that tells where it ended up, and leave out stories that went nowhere. Order them by how much a fan would care. Each item's "importance" is from 1 (filler) to 10 (huge news) and its "sources" lists the numbers of the relevant article titles, if any. "headline" is the item in at most 10 words and "detail" a short paragraph telling the whole story.
 
-    // level the tallest bodies until we fit
-    let mut iteration = 0;
-    while total > MAX_TOKENS {
-        iteration += 1;
-        let needed = total - MAX_TOKENS;
-        debug!("Truncation iteration {}: need to reduce by {} tokens", iteration, needed);
+        Use casual language and emojis, but only state what the bullets say. Stylistically, go easy on the em dashes.
+        "#);
 
-        let some = find_two_largest(&keep_body);
-        if some.is_none() {
-            break; // nothing left we’re allowed to trim
-        }
-        let (imax, isecond) = some.unwrap();
-        let max_len = keep_body[imax];
-        let second_len = keep_body[isecond];
-
-        // target is second longest (or MIN if that’s higher)
-        let target_len = std::cmp::max(second_len, MIN_BODY_TOKENS);
-        let mut diff = max_len.saturating_sub(target_len);
-
-        // if all trimmable bodies are equal length (diff == 0), still shave at least 1
-        // (bounded by MIN and by how much we need)
-        if diff == 0 {
-            diff = (max_len.saturating_sub(MIN_BODY_TOKENS)).min(needed).max(1);
-        }
+    system_prompt.push_str("\nFile each item under exactly one of these categories:\n");
+    for c in &cfg.categories {
+        system_prompt.push_str(&format!("- {}\n", c.prompt_line()));
+    }
 
-        let shave = diff.min(needed);
-        if shave == 0 {
-            debug!("No more tokens can be shaved, breaking");
-            break;
+    let user_prompt = format!(
+        "DAILY MOODS:\n{}\n\nBULLETS:\n{}\n\nARTICLE TITLES:\n{}",
+        moods.iter().map(|(at, mood)| format!("{at}: {mood}")).collect::<Vec<_>>().join("\n"),
+        bullets.iter()
+            .map(|b| format!(
+                "- ({}, {}) {}",
+                b.published_at.map(|t| t.date().to_string()).unwrap_or_default(),
+                b.category.as_deref().unwrap_or("other"),
+                b.text
+            ))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        articles.iter()
+            .enumerate()
+            .map(|(i, a)| format!("[{}] {} ({})", i, a.og_title, a.source))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    );
+
+    let raw: RawAiSummary = chat_json(cfg, JsonCall {
+        step: "rollup",
+        schema_name: "lfc_rollup",
+        schema: summary_schema(cfg),
+        messages: vec![
+            ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage::from(system_prompt)),
+            ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage::from(user_prompt)),
+        ],
+        max_completion_tokens: completion_budget(max_bullets),
+    }).await?;
+
+    let mut summary = into_summary(raw, articles);
+    summary.items.truncate(max_bullets);
+    debug!("Successfully created {} recap with {} items", label, summary.items.len());
+    Ok(summary)
+}
+
+fn summary_schema(cfg: &Config) -> serde_json::Value {
+    let category_names: Vec<&str> = cfg.categories.iter().map(|c| c.name.as_str()).collect();
+    json!({
+      "type": "object",
+      "properties": {
+        "mood": { "type": "string" },
+        "sentiment": { "type": "number" },
+        "category_sentiment": {
+          "type": "array",
+          "items": {
+            "type": "object",
+            "properties": {
+              "category": { "type": "string", "enum": category_names },
+              "score": { "type": "number" }
+            },
+            "required": ["category", "score"],
+            "additionalProperties": false
+          }
+        },
+        "items": {
+          "type": "array",
+          "items": {
+            "type": "object",
+            "properties": {
+              "text": { "type": "string" },
+              "headline": { "type": "string" },
+              "detail": { "type": "string" },
+              "category": { "type": "string", "enum": category_names },
+              "importance": { "type": "integer" },
+              "sources": { "type": "array", "items": { "type": "integer" } }
+            },
+            "required": ["text", "headline", "detail", "category", "importance", "sources"],
+            "additionalProperties": false
+          }
         }
+      },
+      "required": ["mood", "sentiment", "category_sentiment", "items"],
+      "additionalProperties": false
+    })
+}
 
-        debug!("Shaving {} tokens from article {}", shave, imax);
-        keep_body[imax] = keep_body[imax].saturating_sub(shave);
-        total = total.saturating_sub(shave);
-        debug!("New total after shaving: {}", total);
+fn into_summary(raw: RawAiSummary, articles: &[NewsArticle]) -> Summary {
+    let items: Vec<Bullet> = raw.items.into_iter()
+        .map(|item| Bullet {
+            text: item.text,
+            headline: Some(item.headline),
+            detail: Some(item.detail),
+            accepted: None,
+            category: Some(item.category),
+            importance: Some(item.importance.clamp(1, 10)),
+            // article numbers are indices into `articles`, mapped back to their row ids
+            sources: item.sources.iter()
+                .filter_map(|&n| articles.get(n).and_then(|a| a.id))
+                .collect(),
+            ..Default::default()
+        })
+        .collect();
+
+    Summary {
+        mood: raw.mood,
+        sentiment: Some(raw.sentiment.clamp(-1.0, 1.0)),
+        category_sentiment: raw.category_sentiment.into_iter()
+            .map(|c| CategorySentiment { score: c.score.clamp(-1.0, 1.0), ..c })
+            .collect(),
+        match_section: None,
+        transfer_changes: vec![],
+        availability_changes: vec![],
+        items,
+        date: chrono::Utc::now().date_naive(), // fills in today's date
     }
+}
 
-    // rebuild final text
-    debug!("Rebuilding final text with {} articles", articles.len());
-    let mut out = Vec::with_capacity(articles.len());
-    for (i, a) in articles.iter().enumerate() {
-        let title = &a.og_title; // titles intact
-        let body  = decode_first_n_tokens(&bpe, &a.text, keep_body[i]);
-        debug!("Article {}: keeping {} body tokens", i, keep_body[i]);
-        out.push(format!("{title}\n\n{body}"));
+pub fn decode_first_n_tokens(bpe: &CoreBPE, s: &str, n: usize) -> String {
+    if n == 0 || s.is_empty() {
+        return String::new();
     }
-    let final_text = out.join("\n\n");
-    debug!("Final combined text length: {} characters", final_text.len());
-    Ok(final_text)
+    let ids = bpe.encode_with_special_tokens(s);
+    let keep = ids.len().min(n);
+    bpe.decode(ids[..keep].to_vec()).unwrap_or_default()
 }
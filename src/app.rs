@@ -1,22 +1,38 @@
 use std::collections::HashSet;
+use std::time::Instant;
 use url::Url;
 
-use crate::calendar::check_today_fixture;
-use crate::config::{Config, EnsureOutcome};
+use crate::calendar::check_match_mode_on;
+use crate::config::{Config, DedupStrategy, EnsureOutcome, FollowupAction, FollowupMethod, StoryMethod};
 use crate::db::{Db, load_existing_urls_from_db};
 use crate::logger::init_logger;
-use crate::models::NewsArticle;
+use crate::models::{Bullet, NewsArticle, Summary};
 use crate::extractor::{extract_f365_article, discover_all_articles, extract_thisisanfield_article};
 use crate::ai_summarizer::summarize_articles;
+use crate::extractive::{deduplicate_offline, summarize_offline};
+use crate::llm::drain_call_log;
+use crate::content_budget::drain_cut_log;
+use crate::costs::apply_budget;
 use crate::ai_deduplicator::ai_deduplicate;
-use crate::utils::format_summary_plain_text;
+use crate::embedding_deduplicator::{embedding_deduplicate, prefilter_history};
+use crate::followups::find_followups;
+use crate::entities::{tag_articles, tag_articles_llm, tag_bullets};
+use crate::stories::{assign_articles, assign_bullets};
+use crate::utils::render_for_recipients;
+use crate::ranking::apply_digest_caps;
+use crate::grounding::ground_summary;
+use crate::match_day::build_match_section;
+use crate::transfers::{extract_transfers, merge_transfers};
+use crate::availability::{extract_availability, merge_availability};
 use crate::email::send_email;
 use crate::telegram::send_telegram_message;
+use crate::regenerate::RegenerateMode;
+use crate::runs::{MAX_RUN_ATTEMPTS, Run, RunOptions, STALE_RUN_MINUTES, Stage};
 
-use anyhow::Result;
-use log::{debug, info, error};
+use anyhow::{anyhow, Result};
+use log::{debug, info, error, warn};
 
-pub async fn run_scraper(no_ai: bool, no_email: bool, no_telegram: bool) -> Result<()> {
+pub async fn run_scraper(no_ai: bool, offline: bool, no_email: bool, no_telegram: bool) -> Result<()> {
     // 0) Initialize logger
     init_logger()?;
     debug!("Logger initialized");
@@ -31,29 +47,154 @@ pub async fn run_scraper(no_ai: bool, no_email: bool, no_telegram: bool) -> Resu
         return Ok(());
     }
 
-    let cfg = Config::get_user_config()?;
+    let mut cfg = Config::get_user_config()?;
     debug!("User config loaded");
 
     // 1a) Validate config based on enabled features
-    if !no_email && (cfg.email_username.is_none() || cfg.email_app_password.is_none()) {
-        error!("LFC_EMAIL_USERNAME and/or LFC_EMAIL_APP_PASSWORD env vars are not set. Use --no-email to skip email notifications.");
+    if let Some(problem) = missing_delivery_secrets(&cfg, no_email, no_telegram) {
+        error!("{problem}");
+        return Ok(());
+    }
+    if cfg.api_key.is_none() && !no_ai && !offline && !cfg.offline.fallback {
+        error!("LFC_API_KEY environment variable is not set. Use --offline for a digest without the AI summarizer.");
         return Ok(());
     }
 
+    // 2) Finish what a crash or timeout left behind, so no articles or digest are dropped
+    resume_runs(&mut cfg).await?;
+
+    // 3) Then start a new run
+    let run_id = Db::open(&cfg)?.create_run(None, None, &RunOptions::daily(no_ai, offline, no_email, no_telegram))?;
+    debug!("Created run {}", run_id);
+    execute_run(&mut cfg, run_id).await
+}
+
+/// Why the enabled channels can't deliver, if they can't
+pub fn missing_delivery_secrets(cfg: &Config, no_email: bool, no_telegram: bool) -> Option<&'static str> {
+    if !no_email && (cfg.email_username.is_none() || cfg.email_app_password.is_none()) {
+        return Some("LFC_EMAIL_USERNAME and/or LFC_EMAIL_APP_PASSWORD env vars are not set. Use --no-email to skip email notifications.");
+    }
     if !no_telegram && cfg.telegram_bot_token.is_none() {
-        error!("LFC_TELEGRAM_BOT_TOKEN env var is not set. Use --no-telegram to skip telegram notifications.");
-        return Ok(());
+        return Some("LFC_TELEGRAM_BOT_TOKEN env var is not set. Use --no-telegram to skip telegram notifications.");
+    }
+    None
+}
+
+/// Pick up every failed or crashed run from its last finished stage, oldest first. Runs another
+/// process is still working on are left alone.
+async fn resume_runs(cfg: &mut Config) -> Result<()> {
+    let runs = Db::open(cfg)?.incomplete_runs(STALE_RUN_MINUTES)?;
+    for run in runs {
+        if run.attempts >= MAX_RUN_ATTEMPTS {
+            error!("Run {} failed {} times, giving up on it", run.id, run.attempts);
+            Db::open(cfg)?.finish_run(run.id, "abandoned")?;
+            continue;
+        }
+
+        if !Db::open(cfg)?.retry_run(run.id, STALE_RUN_MINUTES)? {
+            info!("Run {} was picked up by another process", run.id);
+            continue;
+        }
+        match run.stage {
+            Some(stage) => info!("Resuming run {} after its {} stage", run.id, stage.as_str()),
+            None => info!("Restarting run {}", run.id),
+        }
+        // a run that fails again is retried next time, it shouldn't hold up today's
+        if let Err(e) = execute_run(cfg, run.id).await {
+            error!("Run {} failed again: {e:?}", run.id);
+        }
+    }
+    Ok(())
+}
+
+/// What a finished stage means for the rest of the run
+enum Flow {
+    /// Go on to the next stage
+    Next,
+    /// The stage did its work and nothing is left to do
+    End,
+    /// The stage didn't run, and neither do the ones after it
+    Skip,
+}
+
+/// Run the stages of a run that haven't finished, recording each one's status, timing and error
+pub async fn execute_run(cfg: &mut Config, run_id: i64) -> Result<()> {
+    let mut db = Db::open(cfg)?;
+    let mut run = db.load_run(run_id)?;
+    let mut budget_checked = false;
+
+    for stage in Stage::after(run.stage) {
+        let stage_id = db.start_stage(run.id, stage)?;
+        let started = Instant::now();
+
+        // the budget check may switch models, so it has to come before any AI stage, resumed or not
+        let ai_stage = matches!(stage, Stage::Summarize | Stage::Dedup | Stage::Track | Stage::Persist) && !run.options.offline;
+        let flow = if ai_stage && !budget_checked {
+            budget_checked = true;
+            match apply_budget(cfg, &db) {
+                Ok(true) => run_stage(cfg, &mut db, &mut run, stage).await,
+                Ok(false) => Ok(Flow::Skip),
+                Err(e) => Err(e),
+            }
+        } else {
+            run_stage(cfg, &mut db, &mut run, stage).await
+        };
+
+        let elapsed = started.elapsed().as_millis();
+        match flow {
+            Ok(Flow::Next) => {
+                db.finish_stage(stage_id, "done", None, elapsed)?;
+                run.stage = Some(stage);
+                db.save_run(&run)?;
+            }
+            Ok(Flow::End) => {
+                db.finish_stage(stage_id, "done", None, elapsed)?;
+                run.stage = Some(stage);
+                db.save_run(&run)?;
+                break;
+            }
+            Ok(Flow::Skip) => {
+                db.finish_stage(stage_id, "skipped", None, elapsed)?;
+                break;
+            }
+            Err(e) => {
+                db.finish_stage(stage_id, "failed", Some(&format!("{e:#}")), elapsed)?;
+                db.finish_run(run.id, "failed")?;
+                return Err(e.context(format!("Run {} failed in its {} stage", run.id, stage.as_str())));
+            }
+        }
     }
 
-    // 2) Open DB
-    let db = Db::open(&cfg)?;
-    debug!("Database opened");
+    db.finish_run(run.id, "done")?;
+    Ok(())
+}
 
-    // 3) Load existing articles
-    let existing_urls: HashSet<Url> = load_existing_urls_from_db(&db)?;
+async fn run_stage(cfg: &Config, db: &mut Db, run: &mut Run, stage: Stage) -> Result<Flow> {
+    match stage {
+        Stage::Discover => discover(db, run).await,
+        Stage::Scrape => scrape(cfg, db, run).await,
+        Stage::Summarize => summarize(cfg, db, run).await,
+        Stage::Dedup => dedup(cfg, db, run).await,
+        Stage::Track => track(cfg, db, run).await,
+        Stage::Persist => persist(cfg, db, run).await,
+        Stage::Deliver => deliver(cfg, db, run).await,
+    }
+}
+
+fn run_fetch(run: &Run) -> Result<i64> {
+    run.fetch_id.ok_or_else(|| anyhow!("Run {} has no fetch", run.id))
+}
+
+fn run_summary(run: &Run) -> Result<Summary> {
+    run.state.summary.clone().ok_or_else(|| anyhow!("Run {} has no summary to go on from", run.id))
+}
+
+async fn discover(db: &Db, run: &mut Run) -> Result<Flow> {
+    // Load existing articles
+    let existing_urls: HashSet<Url> = load_existing_urls_from_db(db)?;
     debug!("Loaded {} existing article URLs from DB", existing_urls.len());
 
-    // 4) Discover new URLs from multiple sources concurrently
+    // Discover new URLs from multiple sources concurrently
     let mut new_urls: HashSet<Url> = discover_all_articles().await?;
 
     new_urls.retain(|url| !existing_urls.contains(url));
@@ -61,21 +202,36 @@ pub async fn run_scraper(no_ai: bool, no_email: bool, no_telegram: bool) -> Resu
 
     if new_urls.is_empty() {
         info!("No new articles found. Everything is up to date.");
-        return Ok(());
+        return Ok(Flow::End);
     } else {
         info!("Found {} new articles, starting scrape…", new_urls.len());
     }
 
-    // 5) Get a new fetch ID
+    // Get a new fetch ID
     let fetch_id = db.create_fetch()?;
     debug!("Created new fetch ID: {}", fetch_id);
 
-    // 6) Create MPSC channel
+    run.fetch_id = Some(fetch_id);
+    run.state.urls = new_urls.iter().map(Url::to_string).collect();
+    Ok(Flow::Next)
+}
+
+async fn scrape(cfg: &Config, db: &mut Db, run: &mut Run) -> Result<Flow> {
+    let fetch_id = run_fetch(run)?;
+
+    // a resumed scrape only fetches what didn't make it into the DB the first time
+    let existing_urls: HashSet<Url> = load_existing_urls_from_db(db)?;
+    let new_urls: HashSet<Url> = run.state.urls.iter()
+        .filter_map(|u| u.parse().ok())
+        .filter(|url| !existing_urls.contains(url))
+        .collect();
+
+    // Create MPSC channel
     let (tx, mut rx) = tokio::sync::mpsc::channel::<NewsArticle>(200);
     debug!("Channel created for article transmission");
 
-    // 7) Spawn DB writer
-    let db_writer = db;
+    // Spawn DB writer
+    let db_writer = Db::open(cfg)?;
     let writer_handle = tokio::spawn(async move {
         while let Some(article) = rx.recv().await {
             if let Err(e) = db_writer.insert_article(fetch_id, &article) {
@@ -87,14 +243,14 @@ pub async fn run_scraper(no_ai: bool, no_email: bool, no_telegram: bool) -> Resu
         info!("All articles inserted for fetch_id {}", fetch_id);
     });
 
-    // 8) Create HTTP client
+    // Create HTTP client
     let client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::limited(10))
         .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 13.5; rv:116.0) Gecko/20100101 Firefox/116.0")
         .build()?;
     debug!("HTTP client created");
 
-    // 9) Scrape each URL
+    // Scrape each URL
     for url in new_urls {
         let tx = tx.clone();
         let client = client.clone();
@@ -129,21 +285,96 @@ pub async fn run_scraper(no_ai: bool, no_email: bool, no_telegram: bool) -> Resu
     writer_handle.await?;
     info!("Writing new articles to the DB finished.");
 
-    if no_ai {
+    // dictionary tags need no model, so --no-ai runs get them too
+    if cfg.entities.enabled {
+        let articles = db.load_articles_for_latest_fetch(&fetch_id)?;
+        tag_articles(cfg, db, &articles)?;
+    }
+    Ok(Flow::Next)
+}
+
+async fn summarize(cfg: &Config, db: &mut Db, run: &mut Run) -> Result<Flow> {
+    if run.options.no_ai {
         info!("--no-ai flag set, skipping AI processing and summary sending");
-        return Ok(());
+        return Ok(Flow::Skip);
+    }
+    let fetch_id = run_fetch(run)?;
+
+    // the match mode of the day the articles were fetched, which is today unless resumed later
+    let fetched_on = db.fetch_time(fetch_id)?.map(|t| t.date()).unwrap_or_else(|| chrono::Utc::now().date_naive());
+    // only the model's summary and match section use it
+    let match_mode = if run.options.offline { None } else { check_match_mode_on(fetched_on).await? };
+
+    let mut previous_articles = db.load_articles_for_latest_fetch(&fetch_id)?;
+    let mut background = HashSet::new();
+    if cfg.followups.enabled {
+        // offline, articles are compared by their word sequences instead of their embeddings
+        let mut followups_cfg = cfg.clone();
+        if run.options.offline {
+            followups_cfg.followups.method = FollowupMethod::Fingerprint;
+        }
+        let followups = find_followups(&followups_cfg, db, fetch_id, &previous_articles).await?;
+        // marking an empty list too clears what an earlier attempt found
+        let marks: Vec<(i64, i64)> = followups.iter().filter_map(|f| Some((previous_articles[f.index].id?, f.of))).collect();
+        db.mark_followups(fetch_id, &marks)?;
+        info!("{} of {} articles are follow-ups of published stories", followups.len(), previous_articles.len());
+
+        let indices: HashSet<usize> = followups.iter().map(|f| f.index).collect();
+        match cfg.followups.action {
+            FollowupAction::Background => background = indices,
+            FollowupAction::Drop => {
+                previous_articles = previous_articles
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| !indices.contains(i))
+                    .map(|(_, a)| a)
+                    .collect();
+                if previous_articles.is_empty() {
+                    info!("Every new article is a follow-up, nothing to summarize");
+                    return Ok(Flow::End);
+                }
+            }
+        }
     }
+    // the extractive digest has no background, a follow-up there would only repeat its story
+    let offline_articles: Vec<NewsArticle> = previous_articles
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !background.contains(i))
+        .map(|(_, a)| a.clone())
+        .collect();
+    let summary = if run.options.offline {
+        Ok(summarize_offline(cfg, &offline_articles))
+    } else {
+        summarize_articles(cfg, &previous_articles, &background, &match_mode).await
+    };
+    db.insert_llm_calls(fetch_id, &drain_call_log())?;
+    db.insert_content_cuts(fetch_id, &drain_cut_log())?;
+    let summary = match summary {
+        // the rest of the run, resumed or not, goes on without the model too
+        Err(e) if cfg.offline.fallback => {
+            warn!("Summarizing failed, sending an offline digest instead: {e:?}");
+            run.options.offline = true;
+            summarize_offline(cfg, &offline_articles)
+        }
+        summary => summary?,
+    };
 
-    // 10) Summarize
-    let mut db = Db::open(&cfg)?;
+    run.state.match_mode = match_mode;
+    run.state.summary = Some(summary);
+    Ok(Flow::Next)
+}
+
+async fn dedup(cfg: &Config, db: &mut Db, run: &mut Run) -> Result<Flow> {
+    let fetch_id = run_fetch(run)?;
+    let summary = run_summary(run)?;
     let previous_articles = db.load_articles_for_latest_fetch(&fetch_id)?;
-    let today_fixture = check_today_fixture().await?;
-    let summary = summarize_articles(&cfg, &previous_articles, &today_fixture).await?;
 
-    // 11) Deduplication sources
-    let published_bullets   = db.fetch_latest_published_bullets()?;          // suppressors
-    info!("These are yesterday's bullet points that will be deduplicated against: {:#?}", published_bullets.iter().map(|b| b.text.clone()).collect::<Vec<String>>());
-    let carryover_bullets   = db.fetch_unpublished_accepted_bullets_since_last_published()?;
+    // Deduplication sources
+    let lookback = cfg.dedup.lookback();
+    let published_bullets   = db.fetch_published_bullets_before(Some(fetch_id), lookback)?; // suppressors
+    info!("These are the published bullet points ({:?}) that will be deduplicated against: {:#?}", lookback, published_bullets.iter().map(|b| b.text.clone()).collect::<Vec<String>>());
+    let carryover_bullets   = if run.options.carryovers { db.fetch_carryover_bullets(fetch_id, cfg.digest.max_carryovers)? } else { Vec::new() };
 
     // merge today's candidates with carryover BEFORE dedup
     let mut merged = summary.clone();
@@ -155,13 +386,23 @@ pub async fn run_scraper(no_ai: bool, no_email: bool, no_telegram: bool) -> Resu
         if !seen.contains(&b.text) {
             seen.insert(b.text.clone());      // keep a copy in the set
             b.accepted = None;                // re-evaluate today
+            b.carryovers += 1;
             merged.items.push(b);             // move the whole Bullet (including text)
         }
     }
     info!("These are today's bullet candidates: {:#?}", merged.items.iter().map(|b| b.text.clone()).collect::<Vec<String>>());
 
+    // with no model to compare meanings, an offline digest is judged on its words alone
+    if run.options.offline {
+        let judged = deduplicate_offline(cfg, &published_bullets, &merged);
+        run.state.summary = Some(apply_digest_caps(&judged, &cfg.digest));
+        return Ok(Flow::Next);
+    }
+
     // run dedup USING published bullets as the "previous" set
-    let processed_summary = ai_deduplicate(&cfg, &published_bullets, &merged).await?;
+    let processed_summary = deduplicate(cfg, db, &published_bullets, &merged).await;
+    db.insert_llm_calls(fetch_id, &drain_call_log())?;
+    let processed_summary = processed_summary?;
 
     info!("The deduplicator accepted {} bullet points.",
         processed_summary
@@ -179,45 +420,210 @@ pub async fn run_scraper(no_ai: bool, no_email: bool, no_telegram: bool) -> Resu
 
     debug!("This is what the deduplicator returned {:#?}", processed_summary.items);
 
+    // check what survived dedup against the articles it came from
+    let processed_summary = if cfg.grounding.enabled {
+        let mut source_articles = previous_articles.clone();
+        let missing: Vec<i64> = processed_summary.items.iter()
+            .flat_map(|b| b.sources.iter().copied())
+            .filter(|id| !source_articles.iter().any(|a| a.id == Some(*id)))
+            .collect::<HashSet<i64>>()
+            .into_iter()
+            .collect();
+        source_articles.extend(db.load_articles_by_ids(&missing)?);
+
+        let grounded = ground_summary(cfg, &source_articles, &processed_summary).await;
+        db.insert_llm_calls(fetch_id, &drain_call_log())?;
+        grounded?
+    } else {
+        processed_summary
+    };
+
+    // keep the digest short, deferring the overflow to the next run
+    run.state.summary = Some(apply_digest_caps(&processed_summary, &cfg.digest));
+    Ok(Flow::Next)
+}
+
+async fn track(cfg: &Config, db: &mut Db, run: &mut Run) -> Result<Flow> {
+    if !run.options.trackers || run.options.offline {
+        return Ok(Flow::Next);
+    }
+    let fetch_id = run_fetch(run)?;
+    let mut summary = run_summary(run)?;
+    let articles = db.load_articles_for_latest_fetch(&fetch_id)?;
+
+    let availability = if cfg.availability.enabled { Some(extract_availability(cfg, &articles).await) } else { None };
+    let transfers = if cfg.transfers.enabled { Some(extract_transfers(cfg, &articles).await) } else { None };
+    db.insert_llm_calls(fetch_id, &drain_call_log())?;
+    db.insert_content_cuts(fetch_id, &drain_cut_log())?;
+
+    // the merges aren't idempotent, so they land together with the run finishing this stage:
+    // a crash either leaves the trackers untouched for the retry or the stage done
+    db.atomically(|db| {
+        // injuries and suspensions go before the preview, which lists who is out
+        if let Some(reports) = availability {
+            match reports.and_then(|r| merge_availability(cfg, db, fetch_id, &articles, &r)) {
+                Ok(changes) => summary.availability_changes = changes,
+                Err(e) => error!("Availability tracking failed, sending the digest without it: {e:?}"),
+            }
+        }
+        // transfer rumours are tracked across days; the digest only shows what moved
+        if let Some(reports) = transfers {
+            match reports.and_then(|r| merge_transfers(cfg, db, fetch_id, &articles, &r)) {
+                Ok(changes) => summary.transfer_changes = changes,
+                Err(e) => error!("Transfer tracking failed, sending the digest without it: {e:?}"),
+            }
+        }
+        run.state.summary = Some(summary);
+        run.stage = Some(Stage::Track);
+        db.save_run(run)
+    })?;
+    Ok(Flow::Next)
+}
+
+async fn persist(cfg: &Config, db: &mut Db, run: &mut Run) -> Result<Flow> {
+    let fetch_id = run_fetch(run)?;
+    let mut processed_summary = run_summary(run)?;
+    let previous_articles = db.load_articles_for_latest_fetch(&fetch_id)?;
+
+    // match-day preview or post-match report, sent above the bullets
+    if let Some(mode) = run.state.match_mode.as_ref().filter(|_| cfg.match_day.enabled && !run.options.offline) {
+        let section = build_match_section(cfg, db, mode, &previous_articles).await;
+        db.insert_llm_calls(fetch_id, &drain_call_log())?;
+        match section {
+            Ok(section) => processed_summary.match_section = Some(section),
+            Err(e) => error!("Match-day section failed, sending the digest without it: {e:?}"),
+        }
+    }
+
+    // a regenerated summary replaces the old one, or moves it aside as a superseded version.
+    // Once that's done the run forgets about it, so a resumed persist only clears its own leftovers
+    match run.options.previous.take() {
+        Some(RegenerateMode::Replace) => db.delete_summary(fetch_id)?,
+        Some(RegenerateMode::Version) => db.supersede_summary(fetch_id)?,
+        None => db.delete_summary(fetch_id)?,
+    }
+    db.save_run(run)?;
+
     // persist summary (do not flip accepted flags)
-    db.insert_summary(fetch_id, &processed_summary)?;
+    let bullet_ids = db.insert_summary(fetch_id, &processed_summary)?;
+    if let Some(generated_at) = run.options.generated_at {
+        db.set_summary_time(fetch_id, generated_at)?;
+    }
+    db.insert_bullet_embeddings(&bullet_ids, &processed_summary.items, &cfg.dedup.embedding_model)?;
+
+    // story threads, labelling bullets on stories readers have seen before
+    if cfg.stories.enabled {
+        // offline, articles are matched on their words instead of their embeddings
+        let mut stories_cfg = cfg.clone();
+        if run.options.offline {
+            stories_cfg.stories.method = StoryMethod::Lexical;
+        }
+        let threaded = assign_articles(&stories_cfg, db, &previous_articles).await;
+        db.insert_llm_calls(fetch_id, &drain_call_log())?;
+        match threaded.and_then(|_| assign_bullets(cfg, db, fetch_id, &bullet_ids, &mut processed_summary)) {
+            Ok(()) => {}
+            Err(e) => error!("Story threading failed, sending the digest without update labels: {e:?}"),
+        }
+    }
 
-    // send notifications…
-    let plain_text = format_summary_plain_text(&processed_summary);
+    if cfg.entities.enabled {
+        tag_bullets(cfg, db, &bullet_ids, &processed_summary.items)?;
+        if cfg.entities.llm && !run.options.offline {
+            let tagged = tag_articles_llm(cfg, db, &previous_articles).await;
+            db.insert_llm_calls(fetch_id, &drain_call_log())?;
+            if let Err(e) = tagged {
+                error!("Entity extraction failed, keeping the dictionary tags: {e:?}");
+            }
+        }
+    }
+
+    run.state.summary = Some(processed_summary);
+    Ok(Flow::Next)
+}
+
+async fn deliver(cfg: &Config, db: &mut Db, run: &mut Run) -> Result<Flow> {
+    let fetch_id = run_fetch(run)?;
+    let processed_summary = run_summary(run)?;
+    let (no_email, no_telegram) = (run.options.no_email, run.options.no_telegram);
+    let email_due = !no_email && !run.state.email_sent;
+    let telegram_due = !no_telegram && !run.state.telegram_sent;
+
+    // the old version already went out, the new one stands in for it
+    if !run.options.deliver {
+        info!("The digest of fetch {} was sent before, not resending it", fetch_id);
+        db.mark_summary_sent(fetch_id)?;
+        return Ok(Flow::Next);
+    }
 
-    let email_task = if no_email {
-        info!("--no-email flag set, skipping email notifications");
+    // send notifications, rendered per recipient so each gets their own sections
+    let email_messages = render_for_recipients(cfg, &processed_summary, cfg.emails.as_deref().unwrap_or_default(), cfg.channels.email);
+    let telegram_messages = render_for_recipients(cfg, &processed_summary, cfg.telegram_chat_ids.as_deref().unwrap_or_default(), cfg.channels.telegram);
+
+    let email_task = if !email_due {
+        if no_email {
+            info!("--no-email flag set, skipping email notifications");
+        }
         tokio::spawn(async { Ok(()) })
     } else {
         let cfg_clone = cfg.clone();
-        let text_clone = plain_text.clone();
-        tokio::spawn(async move { send_email(&cfg_clone, &text_clone).await })
+        tokio::spawn(async move { send_email(&cfg_clone, "LFC news summary", &email_messages).await })
     };
 
-    let telegram_task = if no_telegram {
-        info!("--no-telegram flag set, skipping telegram notifications");
+    let telegram_task = if !telegram_due {
+        if no_telegram {
+            info!("--no-telegram flag set, skipping telegram notifications");
+        }
         tokio::spawn(async { Ok(()) })
     } else {
         let cfg_clone = cfg.clone();
-        let text_clone = plain_text.clone();
-        tokio::spawn(async move { send_telegram_message(&cfg_clone, &text_clone).await })
+        tokio::spawn(async move { send_telegram_message(&cfg_clone, &telegram_messages).await })
     };
 
     let (email_res, telegram_res) = tokio::join!(email_task, telegram_task);
 
+    let mut failed = vec![];
     match email_res.unwrap() {
-        Ok(_) if !no_email => info!("Email(s) sent."),
-        Err(e) if !no_email => error!("Email(s) failed: {e:?}"),
+        Ok(_) if email_due => {
+            info!("Email(s) sent.");
+            run.state.email_sent = true;
+        }
+        Err(e) if email_due => {
+            error!("Email(s) failed: {e:?}");
+            failed.push("email");
+        }
         _ => {}
     }
 
     match telegram_res.unwrap() {
-        Ok(_) if !no_telegram => info!("Telegram(s) sent."),
-        Err(e) if !no_telegram => error!("Telegram(s) failed: {e:?}"),
+        Ok(_) if telegram_due => {
+            info!("Telegram(s) sent.");
+            run.state.telegram_sent = true;
+        }
+        Err(e) if telegram_due => {
+            error!("Telegram(s) failed: {e:?}");
+            failed.push("telegram");
+        }
         _ => {}
     }
 
+    // the digest stays unsent and the stage failed, so the run resumes here and retries
+    if !failed.is_empty() {
+        db.save_run(run)?;
+        return Err(anyhow!("Sending the digest by {} failed", failed.join(" and ")));
+    }
     db.mark_summary_sent(fetch_id)?;
+    Ok(Flow::Next)
+}
 
-    Ok(())
+/// Judge `candidates` against the published `history` with the configured strategy
+pub async fn deduplicate(cfg: &Config, db: &Db, history: &[Bullet], candidates: &Summary) -> Result<Summary> {
+    match cfg.dedup.strategy {
+        DedupStrategy::Llm if history.len() > cfg.dedup.prefilter_min_history => {
+            let (nearest, candidates) = prefilter_history(cfg, db, history, candidates).await?;
+            info!("Pre-filtered {} published bullets down to the {} most similar", history.len(), nearest.len());
+            ai_deduplicate(cfg, &nearest, &candidates).await
+        }
+        DedupStrategy::Llm => ai_deduplicate(cfg, history, candidates).await,
+        DedupStrategy::Embedding => embedding_deduplicate(cfg, db, history, candidates).await,
+    }
 }
@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use async_openai::types::{ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use teloxide::prelude::*;
+use tiktoken_rs::o200k_base;
+use url::Url;
+
+use crate::ai_summarizer::decode_first_n_tokens;
+use crate::config::Config;
+use crate::costs::apply_budget;
+use crate::db::Db;
+use crate::embeddings::{cosine_similarity, embed_texts};
+use crate::llm::{chat_json, drain_call_log, JsonCall};
+use crate::logger::init_logger;
+use crate::models::{Bullet, NewsArticle};
+
+// question words that would match half the archive
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "against", "and", "any", "are", "been", "before", "did", "does", "for",
+    "from", "has", "have", "how", "into", "last", "latest", "liverpool", "lfc", "month", "news",
+    "said", "say", "says", "than", "that", "the", "their", "them", "there", "this", "today",
+    "was", "week", "were", "what", "when", "where", "which", "who", "why", "will", "with",
+    "yesterday", "you",
+];
+
+/// An answer to a question about the archive, with the articles it draws on
+#[derive(Debug)]
+pub struct Answer {
+    pub text: String,
+    pub citations: Vec<Citation>,
+}
+
+#[derive(Debug)]
+pub struct Citation {
+    pub title: String,
+    pub source: String,
+    pub url: Url,
+    pub date: NaiveDate,
+}
+
+/// Lowercase words of the question worth searching for
+fn search_terms(question: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    question
+        .split(|c: char| !c.is_alphanumeric() && c != '-' && c != '\'')
+        .map(|w| w.trim_matches(|c| c == '-' || c == '\'').trim_end_matches("'s").to_lowercase())
+        .filter(|w| w.chars().count() >= 3 && !STOP_WORDS.contains(&w.as_str()))
+        .filter(|w| seen.insert(w.clone()))
+        .collect()
+}
+
+/// The dates a question asks about, "this month" being the 1st to today; the last
+/// `lookback_days` days when it names no period
+fn question_window(question: &str, today: NaiveDate, lookback_days: u32) -> (NaiveDate, NaiveDate) {
+    let q = question.to_lowercase();
+    let start_of_month = today.with_day(1).unwrap();
+    if q.contains("today") {
+        (today, today)
+    } else if q.contains("yesterday") {
+        (today - Duration::days(1), today - Duration::days(1))
+    } else if q.contains("last week") {
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        (monday - Duration::days(7), monday - Duration::days(1))
+    } else if q.contains("this week") {
+        (today - Duration::days(today.weekday().num_days_from_monday() as i64), today)
+    } else if q.contains("last month") {
+        let end = start_of_month - Duration::days(1);
+        (end.with_day(1).unwrap(), end)
+    } else if q.contains("this month") {
+        (start_of_month, today)
+    } else {
+        (today - Duration::days(lookback_days as i64), today)
+    }
+}
+
+/// Articles and sent bullets matching the question's words, plus, when the archive has bullet
+/// embeddings, the bullets closest to the question. The bullets' own sources are added to the
+/// articles so answers drawn from a bullet can still cite a URL.
+async fn retrieve(cfg: &Config, db: &mut Db, question: &str, (start, end): (NaiveDate, NaiveDate)) -> Result<(Vec<NewsArticle>, Vec<Bullet>)> {
+    let terms = search_terms(question);
+    debug!("Searching {} to {} for {:?}", start, end, terms);
+
+    let mut articles = db.search_articles(&terms, start, end, cfg.ask.max_articles)?;
+    let mut bullets = db.search_bullets(&terms, start, end, cfg.ask.max_bullets)?;
+
+    let embedded = db.embedded_bullets_between(&cfg.dedup.embedding_model, start, end)?;
+    if !embedded.is_empty() {
+        match embed_texts(cfg, &[question.to_string()]).await {
+            Ok(vectors) => {
+                let mut scored: Vec<(f32, Bullet)> = embedded
+                    .into_iter()
+                    .filter(|b| !bullets.iter().any(|known| known.id == b.id))
+                    .map(|b| (cosine_similarity(&vectors[0], b.embedding.as_deref().unwrap_or_default()), b))
+                    .collect();
+                scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+                // at least half the slots go to the closest bullets
+                let room = cfg.ask.max_bullets - bullets.len().min(cfg.ask.max_bullets / 2);
+                bullets.truncate(cfg.ask.max_bullets - room);
+                bullets.extend(scored.into_iter().take(room).map(|(_, b)| b));
+            }
+            Err(e) => warn!("Could not embed the question, using text search only: {e:?}"),
+        }
+        db.insert_llm_calls(None, &drain_call_log())?;
+    }
+
+    let missing: Vec<i64> = bullets.iter()
+        .flat_map(|b| b.sources.iter().copied())
+        .filter(|id| !articles.iter().any(|a| a.id == Some(*id)))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    articles.extend(db.load_articles_by_ids(&missing)?);
+
+    info!("Found {} articles and {} bullets for the question", articles.len(), bullets.len());
+    Ok((articles, bullets))
+}
+
+fn context_block(cfg: &Config, articles: &[NewsArticle], bullets: &[Bullet]) -> String {
+    let bpe = o200k_base().unwrap();
+    let mut block = String::from("Digest bullets:\n");
+    for b in bullets {
+        let date = b.published_at.map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default();
+        let refs: Vec<String> = b.sources.iter()
+            .filter_map(|id| articles.iter().position(|a| a.id == Some(*id)))
+            .map(|i| format!("[{i}]"))
+            .collect();
+        block.push_str(&format!("- ({}) {} {}\n", date, b.text, refs.join(" ")));
+    }
+
+    block.push_str("\nArticles:\n");
+    for (i, a) in articles.iter().enumerate() {
+        block.push_str(&format!(
+            "\n[{}] {} ({}, {})\n\n{}\n",
+            i,
+            a.og_title,
+            a.source,
+            a.published_time.format("%Y-%m-%d"),
+            decode_first_n_tokens(&bpe, &a.text, cfg.ask.max_article_tokens)
+        ));
+    }
+    block
+}
+
+#[derive(Debug, Deserialize)]
+struct RawAnswer {
+    answer: String,
+    sources: Vec<usize>,
+}
+
+/// Answer a question about past news from the stored articles and digests
+pub async fn answer_question(cfg: &Config, db: &mut Db, question: &str) -> Result<Answer> {
+    let (start, end) = question_window(question, Local::now().date_naive(), cfg.ask.lookback_days);
+    let (articles, bullets) = retrieve(cfg, db, question, (start, end)).await?;
+    if articles.is_empty() && bullets.is_empty() {
+        return Ok(Answer {
+            text: format!("I couldn't find anything about that between {} and {}.", start, end),
+            citations: vec![],
+        });
+    }
+
+    let system_prompt = format!(r#"
+You answer a Liverpool FC fan's question about the news from {start} to {end}, using only the digest bullets and articles provided. Articles are numbered [n]; a bullet's [n] are the articles it was written from.
+
+  - answer: a short, direct answer. Say who said what and when, quoting briefly where it helps. If the material doesn't answer the question, say so rather than guess.
+  - sources: the numbers of the articles the answer relies on, most important first
+
+Use casual language and go easy on the em dashes.
+"#);
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "answer": { "type": "string" },
+            "sources": { "type": "array", "items": { "type": "integer" } }
+        },
+        "required": ["answer", "sources"],
+        "additionalProperties": false
+    });
+
+    let raw: Result<RawAnswer> = chat_json(cfg, JsonCall {
+        step: "ask",
+        schema_name: "answer",
+        schema,
+        messages: vec![
+            ChatCompletionRequestSystemMessage::from(system_prompt).into(),
+            ChatCompletionRequestUserMessage::from(format!(
+                "Question: {}\n\n{}",
+                question,
+                context_block(cfg, &articles, &bullets)
+            )).into(),
+        ],
+        max_completion_tokens: 2000,
+    }).await;
+    db.insert_llm_calls(None, &drain_call_log())?;
+    let raw = raw?;
+    debug!("Answer: {:#?}", raw);
+
+    let mut seen = HashSet::new();
+    let citations = raw.sources.iter()
+        .filter(|&&i| seen.insert(i))
+        .filter_map(|&i| articles.get(i))
+        .map(|a| Citation {
+            title: a.og_title.clone(),
+            source: a.source.clone(),
+            url: a.url.clone(),
+            date: a.published_time.date_naive(),
+        })
+        .collect();
+
+    Ok(Answer { text: raw.answer, citations })
+}
+
+pub fn format_answer(answer: &Answer) -> String {
+    let mut output = answer.text.clone();
+    if !answer.citations.is_empty() {
+        output.push_str("\n\nSources:");
+        for c in &answer.citations {
+            output.push_str(&format!("\n- {} ({}, {})\n  {}", c.title, c.source, c.date, c.url));
+        }
+    }
+    output
+}
+
+/// `lfc ask`: answer one question and print it
+pub async fn run_ask(question: &str) -> Result<()> {
+    init_logger()?;
+    let mut cfg = Config::get_user_config()?;
+    let mut db = Db::open(&cfg)?;
+
+    if !apply_budget(&mut cfg, &db)? {
+        return Err(anyhow!("The monthly budget is spent, not asking the model"));
+    }
+    let answer = answer_question(&cfg, &mut db, question).await?;
+    println!("{}", format_answer(&answer));
+    Ok(())
+}
+
+/// `lfc bot`: answer `/ask <question>` messages from the configured Telegram chats until stopped
+pub async fn run_bot() -> Result<()> {
+    init_logger()?;
+    let cfg = Config::get_user_config()?;
+    let token = cfg.telegram_bot_token.clone()
+        .ok_or_else(|| anyhow!("LFC_TELEGRAM_BOT_TOKEN is not set"))?;
+    // anyone can message a bot, only the digest's own chats get answers
+    let allowed: HashSet<i64> = cfg.telegram_chat_ids.iter().flatten()
+        .filter_map(|id| id.parse().ok())
+        .collect();
+    if allowed.is_empty() {
+        return Err(anyhow!("LFC_TELEGRAM_CHAT_IDS is not set, the bot would answer nobody"));
+    }
+
+    info!("Bot listening for /ask in {} chats", allowed.len());
+    let bot = Bot::new(token);
+    teloxide::repl(bot, move |bot: Bot, msg: Message| {
+        let cfg = cfg.clone();
+        let allowed = allowed.clone();
+        async move {
+            let Some(question) = msg.text().and_then(ask_question) else { return Ok(()) };
+            if !allowed.contains(&msg.chat.id.0) {
+                warn!("Ignoring /ask from chat {}", msg.chat.id);
+                return Ok(());
+            }
+            if question.is_empty() {
+                bot.send_message(msg.chat.id, "Usage: /ask what did Slot say about Gravenberch this month?").await?;
+                return Ok(());
+            }
+
+            let reply = match ask_once(cfg, question).await {
+                Ok(answer) => format_answer(&answer),
+                Err(e) => {
+                    error!("Could not answer {:?}: {e:?}", question);
+                    "Sorry, I couldn't answer that one right now.".to_string()
+                }
+            };
+            bot.send_message(msg.chat.id, reply).await?;
+            Ok(())
+        }
+    }).await;
+    Ok(())
+}
+
+/// The question of an "/ask …" message, or "/ask@lfc_bot …" in groups; None for other messages
+fn ask_question(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix("/ask")?;
+    let rest = match rest.strip_prefix('@') {
+        Some(mention) => mention.trim_start_matches(|c: char| !c.is_whitespace()),
+        None if rest.is_empty() || rest.starts_with(char::is_whitespace) => rest,
+        None => return None,
+    };
+    Some(rest.trim())
+}
+
+async fn ask_once(mut cfg: Config, question: &str) -> Result<Answer> {
+    let mut db = Db::open(&cfg)?;
+    if !apply_budget(&mut cfg, &db)? {
+        return Err(anyhow!("The monthly budget is spent"));
+    }
+    answer_question(&cfg, &mut db, question).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_terms() {
+        assert_eq!(search_terms("What did Slot say about Gravenberch this month?"), vec!["slot", "gravenberch"]);
+        assert_eq!(search_terms("Van Dijk's contract: any news?"), vec!["van", "dijk", "contract"]);
+    }
+
+    #[test]
+    fn test_ask_question() {
+        assert_eq!(ask_question("/ask who scored?"), Some("who scored?"));
+        assert_eq!(ask_question("/ask@lfc_bot who scored?"), Some("who scored?"));
+        assert_eq!(ask_question("/ask"), Some(""));
+        assert_eq!(ask_question("/askew"), None);
+        assert_eq!(ask_question("what is /ask"), None);
+    }
+
+    #[test]
+    fn test_question_window() {
+        let today = NaiveDate::from_ymd_opt(2025, 10, 16).unwrap(); // a Thursday
+        let d = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+        assert_eq!(question_window("what did Slot say this month?", today, 90), (d(10, 1), today));
+        assert_eq!(question_window("injuries last month", today, 90), (d(9, 1), d(9, 30)));
+        assert_eq!(question_window("this week's team news", today, 90), (d(10, 13), today));
+        assert_eq!(question_window("last week", today, 90), (d(10, 6), d(10, 12)));
+        assert_eq!(question_window("Gravenberch", today, 30), (d(9, 16), today));
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use async_openai::types::{ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage};
+use chrono::Utc;
+use log::{debug, info};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::content_budget::truncate_content;
+use crate::db::Db;
+use crate::config::Config;
+use crate::llm::{chat_json, JsonCall};
+use crate::models::{AvailabilityChange, AvailabilityStatus, NewsArticle, PlayerAvailability};
+use crate::transfers::same_player;
+
+/// A fitness or suspension update as one fetch's articles report it
+#[derive(Debug, Deserialize)]
+pub struct AvailabilityReport {
+    pub player: String,
+    pub status: AvailabilityStatus,
+    pub issue: Option<String>,
+    pub expected_return: Option<String>,
+    pub sources: Vec<usize>, // indices into the fetch's articles
+}
+
+#[derive(Debug, Deserialize)]
+struct AvailabilityResponse {
+    players: Vec<AvailabilityReport>,
+}
+
+/// Pull every injury, illness, suspension or return-to-fitness update about Liverpool players
+pub async fn extract_availability(cfg: &Config, articles: &[NewsArticle]) -> Result<Vec<AvailabilityReport>> {
+    let system_prompt = r#"
+You track the fitness and availability of Liverpool FC men's first-team players. The articles are numbered [n].
+
+List every player whose injury, illness, suspension or fitness the articles give news on, once per player:
+  - player: full name as the articles give it
+  - status:
+      available → fit, back in full training, suspension served
+      doubtful  → a knock or illness that may keep him out, a late fitness test
+      out       → injured or ill and ruled out
+      suspended → banned for the next game(s)
+  - issue: the injury, illness or reason for the ban (e.g. "hamstring", "red card"), or null
+  - expected_return: when he is expected back as reported (e.g. "after the international break", "two weeks"), or null
+  - sources: the numbers of the articles reporting it
+
+Only include players of Liverpool's men's first team, and only news from these articles, not injuries they mention as history.
+"#;
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "players": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "player": { "type": "string" },
+                        "status": { "type": "string", "enum": ["available", "doubtful", "out", "suspended"] },
+                        "issue": { "type": ["string", "null"] },
+                        "expected_return": { "type": ["string", "null"] },
+                        "sources": { "type": "array", "items": { "type": "integer" } }
+                    },
+                    "required": ["player", "status", "issue", "expected_return", "sources"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["players"],
+        "additionalProperties": false
+    });
+
+    let parsed: AvailabilityResponse = chat_json(cfg, JsonCall {
+        step: "availability",
+        schema_name: "availability_reports",
+        schema,
+        messages: vec![
+            ChatCompletionRequestSystemMessage::from(system_prompt).into(),
+            ChatCompletionRequestUserMessage::from(truncate_content(cfg, "availability", articles, &HashSet::new())?).into(),
+        ],
+        max_completion_tokens: 3000,
+    }).await?;
+
+    debug!("Extracted availability reports: {:#?}", parsed.players);
+    Ok(parsed.players)
+}
+
+/// The tracked players, or only those out, doubtful or suspended, after expiring the ones with
+/// no report in `availability.expiry_days`
+pub fn current_availability(cfg: &Config, db: &Db, unavailable_only: bool) -> Result<Vec<PlayerAvailability>> {
+    let expired = db.expire_availability(cfg.availability.expiry_days)?;
+    if expired > 0 {
+        info!("{} players with no injury or suspension news in {} days are available again", expired, cfg.availability.expiry_days);
+    }
+    db.player_availability(unavailable_only)
+}
+
+/// Merge one fetch's reports into the tracked players, recording every report in the history,
+/// and return the players whose status changed. A player first seen as available is tracked
+/// but not reported, there is nothing to flag.
+pub fn merge_availability(cfg: &Config, db: &Db, fetch_id: i64, articles: &[NewsArticle], reports: &[AvailabilityReport]) -> Result<Vec<AvailabilityChange>> {
+    let mut known = current_availability(cfg, db, false)?;
+    let mut before: HashMap<i64, Option<AvailabilityStatus>> = HashMap::new();
+    let mut sources: HashMap<i64, Vec<String>> = HashMap::new();
+    let now = Utc::now().naive_utc();
+
+    for report in reports {
+        let index = match known.iter().position(|p| same_player(&p.player, &report.player)) {
+            Some(i) => {
+                let player = &mut known[i];
+                before.entry(player.id).or_insert(Some(player.status));
+                player.status = report.status;
+                // a returning player's old injury no longer applies
+                if report.status == AvailabilityStatus::Available {
+                    player.issue = report.issue.clone();
+                    player.expected_return = None;
+                } else {
+                    player.issue = report.issue.clone().or(player.issue.take());
+                    player.expected_return = report.expected_return.clone().or(player.expected_return.take());
+                }
+                db.upsert_player_availability(player)?;
+                i
+            }
+            None => {
+                let mut player = PlayerAvailability {
+                    id: 0,
+                    player: report.player.clone(),
+                    status: report.status,
+                    issue: report.issue.clone(),
+                    expected_return: report.expected_return.clone(),
+                    last_updated: now,
+                };
+                player.id = db.upsert_player_availability(&player)?;
+                before.insert(player.id, None);
+                known.push(player);
+                known.len() - 1
+            }
+        };
+
+        let player = &known[index];
+        for article in report.sources.iter().filter_map(|&n| articles.get(n)) {
+            db.insert_availability_update(player.id, fetch_id, player, &article.source, article.url.as_str())?;
+            let names = sources.entry(player.id).or_default();
+            if !names.contains(&article.source) {
+                names.push(article.source.clone());
+            }
+        }
+    }
+
+    let changes: Vec<AvailabilityChange> = known
+        .into_iter()
+        .filter_map(|player| {
+            let previous = *before.get(&player.id)?;
+            let changed = match previous {
+                Some(status) => status != player.status,
+                None => player.status != AvailabilityStatus::Available,
+            };
+            changed.then(|| AvailabilityChange {
+                sources: sources.remove(&player.id).unwrap_or_default(),
+                previous,
+                player,
+            })
+        })
+        .collect();
+
+    info!("{} availability reports, {} status changes", reports.len(), changes.len());
+    Ok(changes)
+}
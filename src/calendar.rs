@@ -1,13 +1,35 @@
 use reqwest;
-use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
 use anyhow::Result;
 
 const CALENDAR_URL: &'static str = "https://ics.ecal.com/ecal-sub/688cce50a0357c0008f39998/Liverpool%20FC.ics";
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Fixture {
     pub date: NaiveDateTime,
     pub opponent: String,
+    pub home: bool,
+}
+
+impl Fixture {
+    /// "Liverpool vs Opponent" at home, "Opponent vs Liverpool" away
+    pub fn title(&self) -> String {
+        if self.home {
+            format!("Liverpool vs {}", self.opponent)
+        } else {
+            format!("{} vs Liverpool", self.opponent)
+        }
+    }
+}
+
+/// What the fixture list says about today
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MatchMode {
+    /// We play today
+    PreMatch(Fixture),
+    /// We played yesterday
+    PostMatch(Fixture),
 }
 
 fn parse_ical(ical_data: &str) -> Result<Vec<Fixture>> {
@@ -51,6 +73,7 @@ fn parse_ical(ical_data: &str) -> Result<Vec<Fixture>> {
                         fixtures.push(Fixture {
                             date,
                             opponent,
+                            home: is_home,
                         });
                     }
                 }
@@ -82,19 +105,43 @@ fn extract_opponent(summary: &str, is_home: bool) -> Option<String> {
     }
 }
 
-pub async fn check_today_fixture() -> Result<Option<Fixture>> {
+/// A match today wins over one yesterday
+fn match_mode_on(fixtures: &[Fixture], today: NaiveDate) -> Option<MatchMode> {
+    let on = |day: NaiveDate| fixtures.iter().find(|f| f.date.date() == day).cloned();
+    on(today)
+        .map(MatchMode::PreMatch)
+        .or_else(|| on(today - Duration::days(1)).map(MatchMode::PostMatch))
+}
+
+/// The match mode a run on `day` had
+pub async fn check_match_mode_on(day: NaiveDate) -> Result<Option<MatchMode>> {
     let response = reqwest::get(CALENDAR_URL).await?;
     let ical_data = response.text().await?;
 
     // Parse fixtures
     let fixtures = parse_ical(&ical_data)?;
 
-    let now = Utc::now().naive_utc();
-    let today = now.date();
+    Ok(match_mode_on(&fixtures, day))
+}
 
-    let fixture = fixtures.iter()
-        .find(|fixture| fixture.date.date() == today)
-        .cloned();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(fixture)
+    #[test]
+    fn test_match_mode_on() {
+        let fixture = |day: u32, opponent: &str| Fixture {
+            date: NaiveDate::from_ymd_opt(2026, 10, day).unwrap().and_hms_opt(15, 0, 0).unwrap(),
+            opponent: opponent.to_string(),
+            home: true,
+        };
+        let fixtures = vec![fixture(14, "Everton"), fixture(18, "Chelsea"), fixture(19, "PSV")];
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+
+        assert!(matches!(match_mode_on(&fixtures, day(15)), Some(MatchMode::PostMatch(f)) if f.opponent == "Everton"));
+        assert!(matches!(match_mode_on(&fixtures, day(18)), Some(MatchMode::PreMatch(f)) if f.opponent == "Chelsea"));
+        // back-to-back games: today's preview wins over yesterday's report
+        assert!(matches!(match_mode_on(&fixtures, day(19)), Some(MatchMode::PreMatch(f)) if f.opponent == "PSV"));
+        assert!(match_mode_on(&fixtures, day(16)).is_none());
+    }
 }
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     env,
     fs::{self, File},
     io::Write,
@@ -9,13 +10,59 @@ use anyhow::{anyhow, Context, Result};
 use serde::Deserialize;
 use serde_yaml::Deserializer;
 
+use crate::entities::EntityKind;
+use crate::models::BulletVariant;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub model: String,
     #[serde(default = "default_db_path")]
     pub db_path: PathBuf,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    #[serde(default)]
+    pub llm: LlmConfig,
+    #[serde(default)]
+    pub costs: CostsConfig,
+    #[serde(default = "default_categories")]
+    pub categories: Vec<Category>,
+    #[serde(default)]
+    pub digest: DigestConfig,
+    #[serde(default)]
+    pub content_budget: ContentBudgetConfig,
+    #[serde(default)]
+    pub followups: FollowupsConfig,
+    #[serde(default)]
+    pub squad: SquadConfig,
+    #[serde(default)]
+    pub entities: EntitiesConfig,
+    #[serde(default)]
+    pub stories: StoriesConfig,
+    #[serde(default)]
+    pub grounding: GroundingConfig,
+    #[serde(default)]
+    pub offline: OfflineConfig,
+    #[serde(default)]
+    pub rollups: RollupsConfig,
+    #[serde(default)]
+    pub match_day: MatchDayConfig,
+    #[serde(default)]
+    pub transfers: TransfersConfig,
+    #[serde(default)]
+    pub availability: AvailabilityConfig,
+    #[serde(default)]
+    pub ask: AskConfig,
+    #[serde(default)]
+    pub prompts: PromptsConfig,
+    #[serde(default)]
+    pub eval: EvalConfig,
+    #[serde(default)]
+    pub channels: ChannelsConfig,
+    /// Per-recipient preferences, keyed by email address or Telegram chat ID
+    #[serde(default)]
+    pub recipients: HashMap<String, RecipientPrefs>,
     #[serde(skip)]
-    pub api_key: String,
+    pub api_key: Option<String>,
     #[serde(skip)]
     pub emails: Option<Vec<String>>,
     #[serde(skip)]
@@ -28,6 +75,640 @@ pub struct Config {
     pub email_app_password: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupStrategy {
+    /// Send every previous and candidate bullet to the chat model
+    Llm,
+    /// Compare stored bullet embeddings, asking the chat model only about borderline pairs
+    Embedding,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DedupConfig {
+    pub strategy: DedupStrategy,
+    pub embedding_model: String,
+    /// Compare against every published summary of the last N days…
+    #[serde(alias = "history_days")] // its name before lookback_summaries was added
+    pub lookback_days: Option<u32>,
+    /// …or against the last N published summaries (the default, with N = 1)
+    pub lookback_summaries: Option<u32>,
+    /// Similarity subtracted per day of a previous bullet's age, so older bullets suppress less
+    pub decay_per_day: f32,
+    /// With more previous bullets than this, only the most similar ones are sent to the LLM
+    pub prefilter_min_history: usize,
+    /// How many of the most similar previous bullets per candidate survive the pre-filter
+    pub prefilter_top_k: usize,
+    /// Cosine similarity at or above which a candidate is rejected outright
+    pub threshold: f32,
+    /// Cosine similarity at or above which (but below `threshold`) the LLM decides
+    pub borderline: f32,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig {
+            strategy: DedupStrategy::Llm,
+            embedding_model: "text-embedding-3-small".to_string(),
+            lookback_days: None,
+            lookback_summaries: None,
+            decay_per_day: 0.0,
+            prefilter_min_history: 30,
+            prefilter_top_k: 5,
+            threshold: 0.88,
+            borderline: 0.75,
+        }
+    }
+}
+
+/// A model to fall back to; `model: None` means the main `model`
+#[derive(Debug, Clone, Deserialize)]
+pub struct FallbackModel {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub flex: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LlmConfig {
+    /// Per-attempt timeout for chat completion and embedding calls
+    pub timeout_secs: u64,
+    /// Retries per model before moving down the fallback chain
+    pub max_retries: u32,
+    /// First retry delay, doubled on every further retry up to five minutes
+    pub backoff_secs: u64,
+    /// Whether the main `model` is called on the Flex service tier
+    pub flex: bool,
+    /// Tried in order once the main model has used up its retries
+    pub fallback: Vec<FallbackModel>,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        LlmConfig {
+            timeout_secs: 60,
+            max_retries: 2,
+            backoff_secs: 2,
+            flex: true,
+            fallback: vec![FallbackModel { model: None, flex: false }],
+        }
+    }
+}
+
+impl LlmConfig {
+    /// (model, flex) pairs in the order they should be tried
+    pub fn chain(&self, model: &str) -> Vec<(String, bool)> {
+        let mut chain = vec![(model.to_string(), self.flex)];
+        for f in &self.fallback {
+            let target = (f.model.clone().unwrap_or_else(|| model.to_string()), f.flex);
+            if !chain.contains(&target) {
+                chain.push(target);
+            }
+        }
+        chain
+    }
+}
+
+/// A digest section the summarizer can file bullets under
+#[derive(Debug, Clone, Deserialize)]
+pub struct Category {
+    pub name: String,
+    /// Section header, defaults to the capitalized name
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Tells the model what belongs here
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Category {
+    /// "name: description" for the summarizer's category list, just the name without one
+    pub fn prompt_line(&self) -> String {
+        if self.description.trim().is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}: {}", self.name, self.description)
+        }
+    }
+
+    pub fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| {
+            let mut chars = self.name.chars();
+            match chars.next() {
+                Some(c) => c.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+    }
+}
+
+fn default_categories() -> Vec<Category> {
+    [
+        ("transfers", "signings, sales, loans, contract talks and transfer rumours"),
+        ("injuries", "injuries, suspensions, fitness and availability"),
+        ("match", "match previews, results, reports, stats and reactions"),
+        ("club", "everything else about the club, staff, finances and fans"),
+    ]
+    .into_iter()
+    .map(|(name, description)| Category {
+        name: name.to_string(),
+        title: None,
+        description: description.to_string(),
+    })
+    .collect()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DigestConfig {
+    /// Most bullets in one digest; the rest are carried over to the next run
+    pub max_bullets: Option<usize>,
+    /// Bullets scored below this are carried over instead of sent
+    pub min_importance: Option<u8>,
+    /// How many runs a cut bullet may be carried over before it is dropped
+    pub max_carryovers: u32,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        DigestConfig {
+            max_bullets: None,
+            min_importance: None,
+            max_carryovers: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ContentBudgetConfig {
+    /// Most tokens of article text sent in one prompt
+    pub max_tokens: usize,
+    /// An article this many hours older than the newest one keeps half its weight
+    pub half_life_hours: f32,
+    /// Weight per source name; sources not listed weigh 1.0
+    pub sources: HashMap<String, f32>,
+    pub classes: ClassWeights,
+    /// Over the budget, articles weighing less than this are dropped before any text is cut
+    pub drop_below: f32,
+}
+
+impl Default for ContentBudgetConfig {
+    fn default() -> Self {
+        ContentBudgetConfig {
+            max_tokens: 350_000,
+            half_life_hours: 48.0,
+            sources: HashMap::new(),
+            classes: ClassWeights::default(),
+            drop_below: 0.25,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FollowupMethod {
+    /// Share of word sequences already seen in a published article
+    Fingerprint,
+    /// Cosine similarity of article embeddings
+    Embedding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FollowupAction {
+    /// Send them to the summarizer marked as background only
+    Background,
+    /// Leave them out of the prompt
+    Drop,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FollowupsConfig {
+    /// Compare new articles with the ones behind recently published bullets before summarizing
+    pub enabled: bool,
+    pub method: FollowupMethod,
+    /// How far back the published articles go
+    pub lookback_days: u32,
+    /// Share of an article's word sequences found in a published one that makes it a follow-up
+    pub fingerprint_threshold: f32,
+    /// Similarity to a published article's embedding that makes it a follow-up
+    pub embedding_threshold: f32,
+    pub action: FollowupAction,
+}
+
+impl Default for FollowupsConfig {
+    fn default() -> Self {
+        FollowupsConfig {
+            enabled: false,
+            method: FollowupMethod::Fingerprint,
+            lookback_days: 3,
+            fingerprint_threshold: 0.5,
+            embedding_threshold: 0.9,
+            action: FollowupAction::Background,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SquadConfig {
+    /// The squad file `lfc squad` maintains, defaults to squad.yaml next to this config
+    pub path: Option<PathBuf>,
+}
+
+impl SquadConfig {
+    pub fn path(&self) -> PathBuf {
+        self.path.clone().unwrap_or_else(|| config_dir().join("squad.yaml"))
+    }
+}
+
+/// A name to tag articles and bullets with, on top of the squad file and the built-in clubs
+/// and competitions
+#[derive(Debug, Clone, Deserialize)]
+pub struct EntityEntry {
+    pub name: String,
+    pub kind: EntityKind,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EntitiesConfig {
+    /// Tag articles and bullets with the players, clubs, competitions and staff they name
+    pub enabled: bool,
+    /// Also have the chat model list who each article is about, catching names not in the dictionary
+    pub llm: bool,
+    /// Tokens of each article shown to the model
+    pub max_article_tokens: usize,
+    pub dictionary: Vec<EntityEntry>,
+}
+
+/// How articles are matched to story threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoryMethod {
+    /// Title words and named entities
+    Lexical,
+    /// Article embeddings, with title words and entities
+    Embedding,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StoriesConfig {
+    /// Group articles and accepted bullets into ongoing story threads
+    pub enabled: bool,
+    pub method: StoryMethod,
+    /// A story not updated for this long takes no new articles
+    pub window_days: u32,
+    /// Score an article needs against a story's articles to join it, with the lexical method
+    pub threshold: f32,
+    /// The same with the embedding method
+    pub embedding_threshold: f32,
+    /// Label bullets on stories earlier digests reported as "Update on …"
+    pub label_updates: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OfflineConfig {
+    /// Send an extractive digest when the LLM summary fails
+    pub fallback: bool,
+    /// Stories in the digest, before the digest caps
+    pub max_bullets: usize,
+    /// Sentences in a bullet's detailed variant
+    pub detail_sentences: usize,
+    /// Word overlap with a published or earlier bullet that makes an offline bullet a repeat
+    pub repeat_threshold: f32,
+}
+
+impl Default for OfflineConfig {
+    fn default() -> Self {
+        OfflineConfig {
+            fallback: false,
+            max_bullets: 10,
+            detail_sentences: 3,
+            repeat_threshold: 0.5,
+        }
+    }
+}
+
+impl Default for StoriesConfig {
+    fn default() -> Self {
+        StoriesConfig {
+            enabled: false,
+            method: StoryMethod::Lexical,
+            window_days: 14,
+            threshold: 0.4,
+            embedding_threshold: 0.6,
+            label_updates: true,
+        }
+    }
+}
+
+impl Default for EntitiesConfig {
+    fn default() -> Self {
+        EntitiesConfig {
+            enabled: false,
+            llm: false,
+            max_article_tokens: 400,
+            dictionary: Vec::new(),
+        }
+    }
+}
+
+/// Weight per content class
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClassWeights {
+    pub news: f32,
+    pub opinion: f32,
+    pub listicle: f32,
+    /// An article telling the same story as a weightier one
+    pub duplicate: f32,
+}
+
+impl Default for ClassWeights {
+    fn default() -> Self {
+        ClassWeights {
+            news: 1.0,
+            opinion: 0.6,
+            listicle: 0.5,
+            duplicate: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unsupported {
+    /// Send the bullet with a warning marker
+    Flag,
+    /// Reject the bullet
+    Drop,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GroundingConfig {
+    pub enabled: bool,
+    /// Ask the chat model as well as checking numbers and names lexically
+    pub llm_check: bool,
+    /// What happens to bullets their sources don't support
+    pub action: Unsupported,
+    /// Tokens of each source article shown to the checking model
+    pub max_source_tokens: usize,
+}
+
+impl Default for GroundingConfig {
+    fn default() -> Self {
+        GroundingConfig {
+            enabled: false,
+            llm_check: true,
+            action: Unsupported::Flag,
+            max_source_tokens: 1500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MatchDayConfig {
+    /// Add a preview on match day and a report the day after
+    pub enabled: bool,
+    /// How far back the preview looks for articles about the opponent
+    pub archive_days: u32,
+    /// Most articles sent to the preview or report prompt
+    pub max_articles: usize,
+    /// Tokens of each of those articles
+    pub max_article_tokens: usize,
+}
+
+impl Default for MatchDayConfig {
+    fn default() -> Self {
+        MatchDayConfig {
+            enabled: true,
+            archive_days: 14,
+            max_articles: 12,
+            max_article_tokens: 1500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TransfersConfig {
+    /// Track rumours in their own tables and show only status changes in the digest
+    pub enabled: bool,
+    /// How long a finished or collapsed deal is remembered, so repeat reports don't reopen it
+    pub memory_days: u32,
+    /// Category whose recipients get the tracker's changes
+    pub section: String,
+}
+
+impl Default for TransfersConfig {
+    fn default() -> Self {
+        TransfersConfig {
+            enabled: false,
+            memory_days: 30,
+            section: "transfers".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AvailabilityConfig {
+    /// Track injuries and suspensions, list them in match-day previews and flag changes
+    pub enabled: bool,
+    /// A player out, doubtful or suspended with no report for this long counts as available
+    pub expiry_days: u32,
+    /// Category whose recipients get the tracker's changes
+    pub section: String,
+}
+
+impl Default for AvailabilityConfig {
+    fn default() -> Self {
+        AvailabilityConfig {
+            enabled: false,
+            expiry_days: 21,
+            section: "injuries".to_string(),
+        }
+    }
+}
+
+/// Files replacing the built-in instructions of a prompt, for trying out prompt versions.
+/// The categories, date and tracker notes are still appended.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PromptsConfig {
+    pub summarize: Option<PathBuf>,
+    pub dedup: Option<PathBuf>,
+}
+
+impl PromptsConfig {
+    /// The contents of an override file, or None to use the built-in prompt
+    pub fn load(path: &Option<PathBuf>) -> Result<Option<String>> {
+        path.as_ref()
+            .map(|p| fs::read_to_string(p).with_context(|| format!("Failed to read prompt {}", p.display())))
+            .transpose()
+    }
+}
+
+/// A model and prompt combination `lfc eval` compares against the others
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalVariant {
+    pub name: String,
+    /// Defaults to `model`
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Defaults to the top-level `prompts`
+    #[serde(default)]
+    pub prompts: Option<PromptsConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EvalConfig {
+    pub variants: Vec<EvalVariant>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AskConfig {
+    /// How far back `lfc ask` searches when the question names no period
+    pub lookback_days: u32,
+    /// Most articles shown to the model with the question
+    pub max_articles: usize,
+    /// Most digest bullets shown to the model with the question
+    pub max_bullets: usize,
+    /// Tokens of each of those articles
+    pub max_article_tokens: usize,
+}
+
+impl Default for AskConfig {
+    fn default() -> Self {
+        AskConfig {
+            lookback_days: 90,
+            max_articles: 8,
+            max_bullets: 20,
+            max_article_tokens: 1000,
+        }
+    }
+}
+
+/// A weekly or monthly recap, sent to its own recipients
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RollupConfig {
+    /// Email subject, "LFC week in review" or "LFC month in review" when unset
+    pub subject: Option<String>,
+    #[serde(skip)]
+    pub emails: Vec<String>, // from LFC_WEEKLY_EMAILS or LFC_MONTHLY_EMAILS
+    #[serde(skip)]
+    pub telegram_chat_ids: Vec<String>, // from LFC_WEEKLY_TELEGRAM_CHAT_IDS or LFC_MONTHLY_TELEGRAM_CHAT_IDS
+    /// Most bullets in the recap
+    pub max_bullets: usize,
+}
+
+impl Default for RollupConfig {
+    fn default() -> Self {
+        RollupConfig {
+            subject: None,
+            emails: vec![],
+            telegram_chat_ids: vec![],
+            max_bullets: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RollupsConfig {
+    pub weekly: RollupConfig,
+    pub monthly: RollupConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RecipientPrefs {
+    /// Category names to include; all sections when unset
+    pub sections: Option<Vec<String>>,
+    /// Bullet length for this recipient; the channel's when unset
+    pub variant: Option<BulletVariant>,
+}
+
+/// The bullet length each channel renders unless a recipient picks another
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ChannelsConfig {
+    pub email: BulletVariant,
+    pub telegram: BulletVariant,
+}
+
+/// USD per million tokens
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverBudget {
+    /// Switch `model` to `costs.downgrade_model`
+    Downgrade,
+    /// Scrape, but skip summarizing and sending
+    Skip,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CostsConfig {
+    pub prices: HashMap<String, ModelPrice>,
+    /// Price multiplier for Flex tier calls
+    pub flex_multiplier: f64,
+    /// Month-to-date spend in USD after which `over_budget` kicks in
+    pub monthly_budget: Option<f64>,
+    pub over_budget: OverBudget,
+    pub downgrade_model: Option<String>,
+}
+
+impl Default for CostsConfig {
+    fn default() -> Self {
+        CostsConfig {
+            prices: HashMap::new(),
+            flex_multiplier: 0.5,
+            monthly_budget: None,
+            over_budget: OverBudget::Skip,
+            downgrade_model: None,
+        }
+    }
+}
+
+/// Which published bullets new candidates are deduplicated against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookback {
+    Days(u32),
+    Summaries(u32),
+}
+
+impl DedupConfig {
+    pub fn lookback(&self) -> Lookback {
+        match (self.lookback_days, self.lookback_summaries) {
+            (Some(days), _) => Lookback::Days(days),
+            (None, Some(n)) => Lookback::Summaries(n),
+            (None, None) => Lookback::Summaries(1),
+        }
+    }
+}
+
 fn config_dir() -> PathBuf {
     dirs::config_dir().expect("Could not determine config directory").join("lfc")
 }
@@ -69,17 +750,165 @@ impl Config {
             write!(
                 &mut config_file,
                 r#"# LFC config (YAML)
-# Only model and db_path are configured here.
+# Only non-secret settings are configured here.
 # All secrets are read from environment variables:
 #   LFC_API_KEY               - OpenAI API key
 #   LFC_EMAILS                - comma-separated recipient email addresses
 #   LFC_TELEGRAM_CHAT_IDS     - comma-separated Telegram chat IDs
+#   LFC_WEEKLY_EMAILS, LFC_WEEKLY_TELEGRAM_CHAT_IDS,
+#   LFC_MONTHLY_EMAILS, LFC_MONTHLY_TELEGRAM_CHAT_IDS
+#                             - the same for the weekly and monthly recaps
 #   LFC_TELEGRAM_BOT_TOKEN    - Telegram bot token
 #   LFC_EMAIL_USERNAME        - SMTP email username
 #   LFC_EMAIL_APP_PASSWORD    - SMTP email app password
 
 model: "gpt-4o-2024-08-06"
 # db_path: "/custom/path/to/articles.db"   # optional, defaults to data dir
+
+# dedup:                                     # optional, these are the defaults
+#   strategy: llm                            # llm | embedding
+#   embedding_model: text-embedding-3-small
+#   lookback_summaries: 1                    # compare against the last N published summaries…
+#   lookback_days: 7                         # …or everything published in the last N days
+#   decay_per_day: 0.0                       # older bullets suppress less strongly
+#   prefilter_min_history: 30                # above this many previous bullets…
+#   prefilter_top_k: 5                       # …send the LLM only the most similar ones
+#   threshold: 0.88                          # reject at or above this similarity
+#   borderline: 0.75                         # ask the LLM between this and threshold
+
+# llm:                                       # optional, these are the defaults
+#   timeout_secs: 60                         # per attempt
+#   max_retries: 2                           # per model, with exponential backoff
+#   backoff_secs: 2
+#   flex: true                               # call `model` on the Flex tier first
+#   fallback:                                # then these, in order
+#     - flex: false                          # same model, default tier
+#     # - model: gpt-5-nano                  # a cheaper model
+
+# costs:                                     # optional
+#   prices:                                  # USD per million tokens
+#     gpt-5-mini: {{ input: 0.25, output: 2.0 }}
+#     text-embedding-3-small: {{ input: 0.02, output: 0.0 }}
+#   flex_multiplier: 0.5                     # Flex tier discount
+#   monthly_budget: 5.0                      # USD
+#   over_budget: skip                        # skip | downgrade
+#   downgrade_model: gpt-5-nano
+
+# categories:                                # optional, digest sections
+#   - name: transfers
+#     title: "Transfers 🔄"                   # optional header
+#     description: signings, sales, loans and rumours
+#   - name: injuries
+#   - name: match
+#   - name: club
+
+# digest:                                    # optional
+#   max_bullets: 8                           # the rest wait for the next run
+#   min_importance: 3                        # 1 (filler) to 10 (huge news)
+#   max_carryovers: 2                        # runs a cut bullet may wait
+
+# content_budget:                            # optional, how articles are cut to fit a prompt
+#   max_tokens: 350000
+#   half_life_hours: 48                      # older articles keep less of their text
+#   sources:                                 # weight per source, default 1.0
+#     thisisanfield: 1.0
+#     football365: 0.8
+#   classes:
+#     news: 1.0
+#     opinion: 0.6
+#     listicle: 0.5
+#     duplicate: 0.3                         # same story as a weightier article
+#   drop_below: 0.25                         # over budget, drop articles weighing less
+
+# followups:                                 # optional, articles restating published stories
+#   enabled: false
+#   method: fingerprint                      # fingerprint | embedding (uses dedup.embedding_model)
+#   lookback_days: 3
+#   fingerprint_threshold: 0.5               # share of word sequences already published
+#   embedding_threshold: 0.9
+#   action: background                       # background | drop
+
+# squad:                                     # optional, the squad list `lfc squad` keeps
+#   path: /path/to/squad.yaml                # defaults to squad.yaml next to this file
+
+# entities:                                  # optional, who articles and bullets are about
+#   enabled: false
+#   llm: false                               # also ask the chat model, for names not listed
+#   max_article_tokens: 400
+#   dictionary:                              # on top of the squad file and built-in clubs
+#     - name: Xabi Alonso
+#       kind: staff                          # player | club | competition | staff
+#       aliases: [Alonso]
+
+# stories:                                   # optional, ongoing story threads
+#   enabled: false
+#   method: lexical                          # lexical (title words, entities) | embedding
+#   window_days: 14                          # stories quiet for longer are closed
+#   threshold: 0.4
+#   embedding_threshold: 0.6
+#   label_updates: true                      # "Update on …" bullets of reported stories
+
+# offline:                                   # optional, extractive digests without the LLM
+#   fallback: false                          # send one when the LLM summary fails
+#   max_bullets: 10                          # stories taken, before the digest caps
+#   detail_sentences: 3                      # key sentences in the detailed variant
+#   repeat_threshold: 0.5                    # word overlap that makes a bullet a repeat
+
+# channels:                                  # optional, bullet length per channel
+#   email: standard                          # headline | standard | detailed
+#   telegram: standard
+
+# recipients:                                # optional, keyed by email or Telegram chat ID
+#   "someone@example.com":
+#     sections: [transfers, match]
+#     variant: detailed                      # overrides the channel's
+
+# grounding:                                 # optional, check bullets against their sources
+#   enabled: false
+#   llm_check: true                          # false = only the lexical number/name check
+#   action: flag                             # flag | drop unsupported bullets
+#   max_source_tokens: 1500                  # per article shown to the checker
+
+# match_day:                                 # optional, these are the defaults
+#   enabled: true                            # preview on match day, report the day after
+#   archive_days: 14                         # look this far back for news on the opponent
+#   max_articles: 12
+#   max_article_tokens: 1500
+
+# transfers:                                 # optional, transfer-rumour tracker
+#   enabled: false
+#   memory_days: 30                          # how long done/collapsed deals are remembered
+#   section: transfers                       # category the changes are sent with
+
+# availability:                              # optional, injury and suspension tracker
+#   enabled: false
+#   expiry_days: 21                          # no report for this long → available again
+#   section: injuries                        # category the changes are sent with
+
+# prompts:                                   # optional, files replacing the built-in instructions
+#   summarize: /path/to/summarize-v2.txt
+#   dedup: /path/to/dedup-v2.txt
+
+# eval:                                      # optional, variants compared by `lfc eval`
+#   variants:
+#     - name: current
+#     - name: nano-v2
+#       model: gpt-5-nano
+#       prompts:
+#         summarize: /path/to/summarize-v2.txt
+
+# ask:                                       # optional, `lfc ask` and the bot's /ask
+#   lookback_days: 90                        # searched when the question names no period
+#   max_articles: 8
+#   max_bullets: 20
+#   max_article_tokens: 1000
+
+# rollups:                                   # optional, recaps sent by `lfc rollup weekly|monthly`
+#   weekly:
+#     subject: "LFC week in review"
+#     max_bullets: 10
+#   monthly:
+#     subject: "LFC month in review"
 "#
             )?;
 
@@ -114,14 +943,87 @@ model: "gpt-4o-2024-08-06"
             })?;
 
         // Populate secrets from environment variables
-        cfg.api_key = env::var("LFC_API_KEY")
-            .map_err(|_| anyhow!("LFC_API_KEY environment variable is not set"))?;
+        // only needed for the LLM steps, so --offline runs work without it
+        cfg.api_key = env::var("LFC_API_KEY").ok();
         cfg.emails = env_csv("LFC_EMAILS");
         cfg.telegram_chat_ids = env_csv("LFC_TELEGRAM_CHAT_IDS");
+        cfg.rollups.weekly.emails = env_csv("LFC_WEEKLY_EMAILS").unwrap_or_default();
+        cfg.rollups.weekly.telegram_chat_ids = env_csv("LFC_WEEKLY_TELEGRAM_CHAT_IDS").unwrap_or_default();
+        cfg.rollups.monthly.emails = env_csv("LFC_MONTHLY_EMAILS").unwrap_or_default();
+        cfg.rollups.monthly.telegram_chat_ids = env_csv("LFC_MONTHLY_TELEGRAM_CHAT_IDS").unwrap_or_default();
         cfg.telegram_bot_token = env::var("LFC_TELEGRAM_BOT_TOKEN").ok();
         cfg.email_username = env::var("LFC_EMAIL_USERNAME").ok();
         cfg.email_app_password = env::var("LFC_EMAIL_APP_PASSWORD").ok();
 
+        if cfg.dedup.lookback_days.is_some() && cfg.dedup.lookback_summaries.is_some() {
+            return Err(anyhow!(
+                "Invalid config in {}: set only one of dedup.lookback_days and dedup.lookback_summaries",
+                config_path.display()
+            ));
+        }
+
+        if cfg.categories.is_empty() {
+            return Err(anyhow!("Invalid config in {}: categories must not be empty", config_path.display()));
+        }
+
+        for (tracker, enabled, section) in [
+            ("transfers", cfg.transfers.enabled, &cfg.transfers.section),
+            ("availability", cfg.availability.enabled, &cfg.availability.section),
+        ] {
+            if enabled && !cfg.categories.iter().any(|c| &c.name == section) {
+                return Err(anyhow!(
+                    "Invalid config in {}: {}.section `{}` is not one of the categories",
+                    config_path.display(),
+                    tracker,
+                    section
+                ));
+            }
+        }
+
+        for (recipient, prefs) in &cfg.recipients {
+            for section in prefs.sections.iter().flatten() {
+                if !cfg.categories.iter().any(|c| &c.name == section) {
+                    return Err(anyhow!(
+                        "Invalid config in {}: recipient {} asks for unknown section `{}`",
+                        config_path.display(),
+                        recipient,
+                        section
+                    ));
+                }
+            }
+        }
+
+        if cfg.costs.over_budget == OverBudget::Downgrade && cfg.costs.downgrade_model.is_none() {
+            return Err(anyhow!(
+                "Invalid config in {}: costs.over_budget is downgrade but costs.downgrade_model is not set",
+                config_path.display()
+            ));
+        }
+
+        if cfg.dedup.borderline > cfg.dedup.threshold {
+            return Err(anyhow!(
+                "Invalid config in {}: dedup.borderline ({}) must not exceed dedup.threshold ({})",
+                config_path.display(),
+                cfg.dedup.borderline,
+                cfg.dedup.threshold
+            ));
+        }
+
         Ok(cfg)
     }
+
+    /// The OpenAI API key, an error when LFC_API_KEY isn't set
+    pub fn openai_key(&self) -> Result<&str> {
+        self.api_key.as_deref().ok_or_else(|| anyhow!("LFC_API_KEY environment variable is not set"))
+    }
+
+    /// The sections a recipient asked for, `None` meaning all of them
+    pub fn sections_for(&self, recipient: &str) -> Option<&[String]> {
+        self.recipients.get(recipient).and_then(|p| p.sections.as_deref())
+    }
+
+    /// The bullet variant a recipient asked for, `channel_default` when they didn't
+    pub fn variant_for(&self, recipient: &str, channel_default: BulletVariant) -> BulletVariant {
+        self.recipients.get(recipient).and_then(|p| p.variant).unwrap_or(channel_default)
+    }
 }
@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use log::{debug, info};
+use tiktoken_rs::o200k_base;
+
+use crate::ai_summarizer::decode_first_n_tokens;
+use crate::config::{Config, ContentBudgetConfig};
+use crate::models::NewsArticle;
+
+const MIN_BODY_TOKENS: usize = 40; // don't over-trim tiny bodies
+const SEP_TOKENS_PER_ARTICLE: usize = 6; // rough buffer for "\n\n" joins
+const MIN_WEIGHT: f32 = 0.01; // keeps the levelling finite for zero weights
+const DUPLICATE_SIMILARITY: f32 = 0.5; // title word overlap at which two articles tell the same story
+
+// words that say nothing about which story a title is about
+const TITLE_STOP_WORDS: &[&str] = &[
+    "liverpool", "reds", "anfield", "about", "after", "from", "have", "into", "over", "says",
+    "that", "their", "they", "this", "what", "when", "will", "with",
+];
+
+// title words or URL path segments that mark an opinion piece
+const OPINION_MARKERS: &[&str] = &["opinion", "mailbox", "column", "verdict", "comment", "editorial", "blog"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentClass {
+    News,
+    Opinion,
+    Listicle,
+    /// Tells the same story as a weightier article
+    Duplicate,
+}
+
+impl ContentClass {
+    /// Stored in `content_cuts.class`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentClass::News => "news",
+            ContentClass::Opinion => "opinion",
+            ContentClass::Listicle => "listicle",
+            ContentClass::Duplicate => "duplicate",
+        }
+    }
+}
+
+/// An article the budgeter trimmed or dropped
+#[derive(Debug, Clone)]
+pub struct ContentCut {
+    pub step: String,
+    pub article_id: Option<i64>,
+    pub class: ContentClass,
+    pub weight: f32,
+    pub body_tokens: usize,
+    pub kept_tokens: usize,
+    pub dropped: bool,
+}
+
+// Cuts made since the last drain, stored against the current fetch like the LLM call log
+static CUT_LOG: Mutex<Vec<ContentCut>> = Mutex::new(Vec::new());
+
+pub fn drain_cut_log() -> Vec<ContentCut> {
+    CUT_LOG.lock().map(|mut log| std::mem::take(&mut *log)).unwrap_or_default()
+}
+
+fn record(cuts: Vec<ContentCut>) {
+    if let Ok(mut log) = CUT_LOG.lock() {
+        log.extend(cuts);
+    }
+}
+
+/// News, opinion or listicle, guessed from the title and URL
+pub fn classify(article: &NewsArticle) -> ContentClass {
+    let title = article.og_title.to_lowercase();
+    let mut words = title.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty());
+    let first = words.next().unwrap_or_default();
+    let numbered = first.parse::<u32>().is_ok()
+        || (first == "top" && words.next().is_some_and(|w| w.parse::<u32>().is_ok()));
+    if numbered || title.contains("ranked") || title.contains("ranking") {
+        return ContentClass::Listicle;
+    }
+
+    let path = article.url.path().to_lowercase();
+    let marked = |text: &str| text.split(|c: char| !c.is_alphanumeric()).any(|w| OPINION_MARKERS.contains(&w));
+    if marked(&title) || marked(&path) {
+        return ContentClass::Opinion;
+    }
+    ContentClass::News
+}
+
+pub fn title_words(title: &str) -> HashSet<String> {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 4 && !TITLE_STOP_WORDS.contains(w))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Jaccard similarity of two word sets
+pub fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+/// Class and weight of every article: source weight × class weight × recency, where recency
+/// halves every `half_life_hours` behind the newest article. Of articles whose titles tell the
+/// same story, all but the weightiest become duplicates.
+pub fn weigh(budget: &ContentBudgetConfig, articles: &[NewsArticle]) -> Vec<(ContentClass, f32)> {
+    weigh_stories(budget, articles).into_iter().map(|(class, weight, _)| (class, weight)).collect()
+}
+
+/// Like `weigh`, also giving each duplicate the index of the article that keeps its story
+pub fn weigh_stories(budget: &ContentBudgetConfig, articles: &[NewsArticle]) -> Vec<(ContentClass, f32, Option<usize>)> {
+    let Some(newest) = articles.iter().map(|a| a.published_time).max() else {
+        return Vec::new();
+    };
+    let class_weight = |class: ContentClass| match class {
+        ContentClass::News => budget.classes.news,
+        ContentClass::Opinion => budget.classes.opinion,
+        ContentClass::Listicle => budget.classes.listicle,
+        ContentClass::Duplicate => budget.classes.duplicate,
+    };
+    let base: Vec<f32> = articles
+        .iter()
+        .map(|a| {
+            let age_hours = (newest - a.published_time).num_minutes() as f32 / 60.0;
+            let recency = 0.5f32.powf(age_hours / budget.half_life_hours.max(f32::EPSILON));
+            budget.sources.get(&a.source).copied().unwrap_or(1.0) * recency
+        })
+        .collect();
+    let mut weighed: Vec<(ContentClass, f32, Option<usize>)> = articles
+        .iter()
+        .zip(&base)
+        .map(|(a, b)| {
+            let class = classify(a);
+            (class, b * class_weight(class), None)
+        })
+        .collect();
+
+    // heaviest first, so each story is kept by its best telling
+    let mut order: Vec<usize> = (0..articles.len()).collect();
+    order.sort_by(|&a, &b| weighed[b].1.total_cmp(&weighed[a].1));
+    let words: Vec<HashSet<String>> = articles.iter().map(|a| title_words(&a.og_title)).collect();
+    let mut kept: Vec<usize> = Vec::new();
+    for i in order {
+        match kept.iter().find(|&&k| similarity(&words[i], &words[k]) >= DUPLICATE_SIMILARITY) {
+            Some(&k) => weighed[i] = (ContentClass::Duplicate, base[i] * class_weight(ContentClass::Duplicate), Some(k)),
+            None => kept.push(i),
+        }
+    }
+    weighed
+}
+
+/// Body tokens each article keeps to fit `max_tokens`, None for dropped articles. Articles
+/// weighing less than `drop_below` are dropped first, lightest first, until the rest fit in
+/// full; the remaining bodies are then levelled so each keeps tokens in proportion to its weight.
+pub fn plan_cuts(titles: &[usize], bodies: &[usize], weights: &[f32], max_tokens: usize, drop_below: f32) -> Vec<Option<usize>> {
+    let mut keep: Vec<Option<usize>> = bodies.iter().copied().map(Some).collect();
+    let total = |keep: &[Option<usize>]| -> usize {
+        keep.iter()
+            .zip(titles)
+            .filter_map(|(k, t)| k.map(|k| k + t + SEP_TOKENS_PER_ARTICLE))
+            .sum()
+    };
+    if total(&keep) <= max_tokens {
+        return keep;
+    }
+
+    let mut light: Vec<usize> = (0..bodies.len()).filter(|&i| weights[i] < drop_below).collect();
+    light.sort_by(|&a, &b| weights[a].total_cmp(&weights[b]));
+    for i in light {
+        if total(&keep) <= max_tokens {
+            break;
+        }
+        keep[i] = None;
+    }
+    if total(&keep) <= max_tokens {
+        return keep;
+    }
+
+    // each body keeps `level × weight` tokens, never cut below the minimum; find the highest
+    // level that fits
+    let at_level = |level: f32| -> Vec<Option<usize>> {
+        keep.iter()
+            .enumerate()
+            .map(|(i, k)| k.map(|_| {
+                let share = (level * weights[i].max(MIN_WEIGHT)) as usize;
+                bodies[i].min(share.max(MIN_BODY_TOKENS))
+            }))
+            .collect()
+    };
+    let (mut low, mut high) = (0.0f32, bodies.iter().zip(weights)
+        .map(|(b, w)| *b as f32 / w.max(MIN_WEIGHT))
+        .fold(0.0, f32::max));
+    for _ in 0..64 {
+        let mid = (low + high) / 2.0;
+        if total(&at_level(mid)) <= max_tokens {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    at_level(low)
+}
+
+/// The articles as one prompt, each numbered by its position and the `background` ones marked
+/// as such, cut to the configured budget with the weightiest articles keeping the most. What was
+/// cut is logged and recorded for `step`.
+pub fn truncate_content(cfg: &Config, step: &str, articles: &[NewsArticle], background: &HashSet<usize>) -> Result<String> {
+    debug!("Starting truncate_content with {} articles", articles.len());
+    let bpe = o200k_base().unwrap();
+    let budget = &cfg.content_budget;
+
+    let titles: Vec<usize> = articles.iter().map(|a| bpe.encode_with_special_tokens(&a.og_title).len()).collect();
+    let bodies: Vec<usize> = articles.iter().map(|a| bpe.encode_with_special_tokens(&a.text).len()).collect();
+    let weighed = weigh(budget, articles);
+    let weights: Vec<f32> = weighed.iter().map(|(_, w)| *w).collect();
+    let keep = plan_cuts(&titles, &bodies, &weights, budget.max_tokens, budget.drop_below);
+
+    let mut out = Vec::with_capacity(articles.len());
+    let mut cuts = Vec::new();
+    let mut dropped: Vec<&str> = Vec::new();
+    for (i, a) in articles.iter().enumerate() {
+        let (class, weight) = weighed[i];
+        debug!("Article {}: {} weighing {:.2}, keeping {:?} of {} body tokens", i, class.as_str(), weight, keep[i], bodies[i]);
+        if let Some(k) = keep[i] {
+            // titles intact
+            let marker = if background.contains(&i) { "(background only) " } else { "" };
+            out.push(format!("[{i}] {marker}{}\n\n{}", a.og_title, decode_first_n_tokens(&bpe, &a.text, k)));
+        }
+        if keep[i].is_none() {
+            dropped.push(&a.og_title);
+        }
+        if keep[i] != Some(bodies[i]) {
+            cuts.push(ContentCut {
+                step: step.to_string(),
+                article_id: a.id,
+                class,
+                weight,
+                body_tokens: bodies[i],
+                kept_tokens: keep[i].unwrap_or(0),
+                dropped: keep[i].is_none(),
+            });
+        }
+    }
+
+    if !cuts.is_empty() {
+        let trimmed = cuts.iter().filter(|c| !c.dropped).count();
+        let shaved: usize = cuts.iter().filter(|c| !c.dropped).map(|c| c.body_tokens - c.kept_tokens).sum();
+        info!(
+            "{}: articles over the {} token budget, dropped {} {:?} and trimmed {} by {} tokens",
+            step, budget.max_tokens, dropped.len(), dropped, trimmed, shaved
+        );
+        record(cuts);
+    }
+
+    let final_text = out.join("\n\n");
+    debug!("Final combined text length: {} characters", final_text.len());
+    Ok(final_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::models::test_article;
+
+    #[test]
+    fn test_weigh() {
+        let mut budget = ContentBudgetConfig::default();
+        budget.sources.insert("football365".to_string(), 0.5);
+        let mut opinion = test_article(None, "Why Slot must start Ngumoha", "/opinion/ngumoha", "thisisanfield", "");
+        opinion.published_time -= Duration::hours(48);
+        let articles = vec![
+            test_article(None, "Salah signs new contract until 2028", "/news/salah", "thisisanfield", ""),
+            test_article(None, "Mohamed Salah signs new contract until 2028 at Anfield", "/news/salah-2", "thisisanfield", ""),
+            test_article(None, "16 Conclusions on Liverpool 2-0 Everton", "/features/16-conclusions", "football365", ""),
+            opinion,
+        ];
+        let weighed = weigh(&budget, &articles);
+
+        assert_eq!(weighed[0], (ContentClass::News, 1.0));
+        assert_eq!(weighed[1], (ContentClass::Duplicate, 0.3));
+        assert_eq!(weighed[2], (ContentClass::Listicle, 0.25));
+        assert_eq!(weighed[3].0, ContentClass::Opinion);
+        assert!((weighed[3].1 - 0.3).abs() < 1e-6); // 0.6, halved by age
+    }
+
+    #[test]
+    fn test_plan_cuts() {
+        let titles = [10, 10, 10];
+        let bodies = [1000, 1000, 1000];
+
+        // fits: nothing cut
+        assert_eq!(plan_cuts(&titles, &bodies, &[1.0, 1.0, 1.0], 10_000, 0.25), vec![Some(1000); 3]);
+
+        // the light article goes first
+        let keep = plan_cuts(&titles, &bodies, &[1.0, 1.0, 0.1], 2100, 0.25);
+        assert_eq!(keep, vec![Some(1000), Some(1000), None]);
+
+        // heavier articles keep more, and everything fits
+        let keep = plan_cuts(&titles, &bodies, &[1.0, 0.5, 0.5], 1600, 0.25);
+        let kept: Vec<usize> = keep.iter().map(|k| k.unwrap()).collect();
+        assert!(kept[0] > kept[1] && kept[1] == kept[2]);
+        assert!(kept.iter().sum::<usize>() + 3 * (10 + SEP_TOKENS_PER_ARTICLE) <= 1600);
+
+        // never below the minimum
+        let keep = plan_cuts(&titles, &bodies, &[1.0, 1.0, 1.0], 10, 0.25);
+        assert_eq!(keep, vec![Some(MIN_BODY_TOKENS); 3]);
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::config::{Config, OverBudget};
+use crate::db::Db;
+
+/// USD cost of a call from the configured price table, `None` if the model has no price
+pub fn call_cost(cfg: &Config, model: &str, flex: bool, prompt_tokens: u32, completion_tokens: u32) -> Option<f64> {
+    let Some(price) = cfg.costs.prices.get(model) else {
+        warn!("No price configured for {}, its cost won't be tracked", model);
+        return None;
+    };
+    let cost = (prompt_tokens as f64 * price.input + completion_tokens as f64 * price.output) / 1_000_000.0;
+    Some(if flex { cost * cfg.costs.flex_multiplier } else { cost })
+}
+
+/// Apply `costs.monthly_budget` to this run. Returns false if AI processing should be skipped,
+/// and switches `cfg.model` to the downgrade model if that is what the config asks for.
+pub fn apply_budget(cfg: &mut Config, db: &Db) -> Result<bool> {
+    let Some(budget) = cfg.costs.monthly_budget else { return Ok(true) };
+
+    let spent = db.month_to_date_cost()?;
+    if spent < budget {
+        info!("Spent ${:.4} of the ${:.2} monthly budget", spent, budget);
+        return Ok(true);
+    }
+
+    match (cfg.costs.over_budget, &cfg.costs.downgrade_model) {
+        (OverBudget::Downgrade, Some(model)) => {
+            warn!("Monthly budget exceeded (${:.4} of ${:.2}), downgrading from {} to {}", spent, budget, cfg.model, model);
+            cfg.model = model.clone();
+            Ok(true)
+        }
+        _ => {
+            warn!("Monthly budget exceeded (${:.4} of ${:.2}), skipping AI processing", spent, budget);
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelPrice;
+
+    #[test]
+    fn test_call_cost() {
+        let mut cfg: Config = serde_yaml::from_str("model: gpt-5-mini").unwrap();
+        cfg.costs.prices.insert("gpt-5-mini".to_string(), ModelPrice { input: 0.25, output: 2.0 });
+
+        let cost = call_cost(&cfg, "gpt-5-mini", false, 1_000_000, 500_000).unwrap();
+        assert!((cost - 1.25).abs() < 1e-9);
+
+        // Flex calls get the flex multiplier
+        let cost = call_cost(&cfg, "gpt-5-mini", true, 1_000_000, 500_000).unwrap();
+        assert!((cost - 0.625).abs() < 1e-9);
+
+        assert!(call_cost(&cfg, "unknown-model", false, 1, 1).is_none());
+    }
+}
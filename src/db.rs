@@ -1,15 +1,96 @@
 use rusqlite::OptionalExtension;
-use crate::models::{Summary, Bullet};
+use crate::models::{
+    Summary, Bullet, CostRow, DuplicateOf, MoodPoint, RejectedBullet,
+    TransferDirection, TransferRumour, TransferStatus, TransferUpdate,
+    AvailabilityStatus, PlayerAvailability, ArticleHit, BulletHit, SearchFilters, MentionRow,
+    Story, StoryEntry,
+};
 use std::collections::HashSet;
 
 use crate::models::NewsArticle;
-use crate::config::Config;
+use crate::config::{Config, Lookback};
+use crate::embeddings::{vector_to_blob, blob_to_vector};
+use crate::llm::LlmCall;
+use crate::content_budget::ContentCut;
+use crate::entities::EntityTag;
+use crate::runs::{Run, RunOptions, RunState, Stage};
 
 use rusqlite::{Connection, params};
 use anyhow::{Result, Context};
 use url::{Url};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 
 const SCHEMA_SQL: &str = include_str!("../schema.sql");
+const SQLITE_DATETIME: &str = "%Y-%m-%d %H:%M:%S"; // format of CURRENT_TIMESTAMP
+
+// a failed run, or one still 'running' that hasn't moved since the cutoff ?1 ("-N minutes")
+const RESUMABLE_RUN: &str =
+    "(status = 'failed' OR (status = 'running' AND (updated_at IS NULL OR updated_at < datetime('now', ?1))))";
+
+// Full-text indexes; schema.sql gives each the table it indexes as its content table. An index
+// created on an existing database starts out empty, so it is rebuilt from that table once, when
+// it first appears.
+const FTS_TABLES: &[&str] = &["articles_fts", "bullets_fts"];
+
+// Columns added after a table was first shipped. `CREATE TABLE IF NOT EXISTS` won't add them
+// to an existing database, so they are added here before the schema runs.
+const COLUMN_MIGRATIONS: &[(&str, &str, &str)] = &[
+    ("articles", "followup_of", "INTEGER REFERENCES articles(id) ON DELETE SET NULL"),
+    ("articles", "story_id", "INTEGER REFERENCES stories(id) ON DELETE SET NULL"),
+    ("bullets", "reason", "TEXT"),
+    ("bullets", "duplicate_of", "INTEGER REFERENCES bullets(id) ON DELETE SET NULL"),
+    ("bullets", "category", "TEXT"),
+    ("bullets", "importance", "INTEGER"),
+    ("bullets", "deferred", "BOOLEAN NOT NULL DEFAULT 0"),
+    ("bullets", "carryovers", "INTEGER NOT NULL DEFAULT 0"),
+    ("bullets", "grounded", "BOOLEAN"),
+    ("bullets", "grounding_notes", "TEXT"),
+    ("bullets", "headline", "TEXT"),
+    ("bullets", "detail", "TEXT"),
+    ("bullets", "story_id", "INTEGER REFERENCES stories(id) ON DELETE SET NULL"),
+    ("bullets", "update_on", "TEXT"),
+    ("summaries", "sentiment", "REAL"),
+    ("summaries", "category_sentiment", "TEXT"),
+    ("summaries", "match_section", "TEXT"),
+    ("summaries", "kind", "TEXT NOT NULL DEFAULT 'daily'"),
+    ("summaries", "period_start", "TEXT"),
+    ("summaries", "period_end", "TEXT"),
+    ("summaries", "version_of", "INTEGER"),
+    ("llm_calls", "prompt_tokens", "INTEGER"),
+    ("llm_calls", "completion_tokens", "INTEGER"),
+    ("llm_calls", "reasoning_tokens", "INTEGER"),
+    ("llm_calls", "cost_usd", "REAL"),
+    ("runs", "updated_at", "TEXT"),
+];
+
+fn add_missing_columns(conn: &Connection) -> Result<()> {
+    for (table, column, decl) in COLUMN_MIGRATIONS {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+        let columns = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // table doesn't exist yet, the schema will create it with every column
+        if columns.is_empty() || columns.iter().any(|c| c == column) {
+            continue;
+        }
+
+        conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl};"))
+            .with_context(|| format!("Failed to add column {table}.{column}"))?;
+    }
+    Ok(())
+}
+
+fn missing_tables<'a>(conn: &Connection, tables: &[&'a str]) -> Result<Vec<&'a str>> {
+    let mut stmt = conn.prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?")?;
+    let mut missing = vec![];
+    for table in tables {
+        if !stmt.exists([table])? {
+            missing.push(*table);
+        }
+    }
+    Ok(missing)
+}
 
 pub struct Db {
     conn: Connection
@@ -21,7 +102,13 @@ impl Db {
         let conn = Connection::open(path)
             .with_context(|| format!("Failed to open DB at {}", path.display()))?;
         conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        add_missing_columns(&conn)?;
+        let missing_fts = missing_tables(&conn, FTS_TABLES)?;
         conn.execute_batch(SCHEMA_SQL).context("Failed to initialize schema")?;
+        for table in missing_fts {
+            conn.execute_batch(&format!("INSERT INTO {table}({table}) VALUES ('rebuild');"))
+                .with_context(|| format!("Failed to build the {table} index"))?;
+        }
 
         Ok(Db { conn })
     }
@@ -36,6 +123,15 @@ impl Db {
         Ok(fetch_id)
     }
 
+    pub fn fetch_time(&self, fetch_id: i64) -> Result<Option<NaiveDateTime>> {
+        let fetched_at = self.conn.query_row(
+            "SELECT fetched_at FROM fetches WHERE id = ?",
+            [fetch_id],
+            |row| row.get::<_, Option<String>>(0),
+        ).optional()?.flatten();
+        Ok(fetched_at.and_then(|t| NaiveDateTime::parse_from_str(&t, SQLITE_DATETIME).ok()))
+    }
+
     pub fn insert_article(&self, fetch_id: i64, article: &NewsArticle) -> Result<()> {
         self.conn.execute(
             "INSERT INTO articles (
@@ -60,98 +156,1053 @@ impl Db {
 
     pub fn load_articles_for_latest_fetch(&self, fetch_id: &i64) -> Result<Vec<NewsArticle>> {
         let mut stmt = self.conn.prepare(
-            "SELECT url, og_title, published_time, og_image, author, text, source FROM articles WHERE fetch_id = ?",
-        )?;
-
-        let iter = stmt.query_and_then([fetch_id], |row| {
-            let url: Url = row.get::<_, String>(0)?.parse()?;
-            let published_time = row.get::<_, String>(2)?.parse()?;
-            let og_image: Url = row.get::<_, String>(3)?.parse()?;
-            Ok(NewsArticle {
-                url,
-                og_title: row.get(1)?,
-                published_time,
-                og_image,
-                author: row.get(4)?,
-                text: row.get(5)?,
-                source: row.get(6)?,
-            })
-        })?;
+            "SELECT id, url, og_title, published_time, og_image, author, text, source FROM articles WHERE fetch_id = ?",
+        )?;
+
+        let iter = stmt.query_and_then([fetch_id], article_from_row)?;
 
         Ok(iter.collect::<Result<Vec<_>>>()?)
     }
 
-    pub fn insert_summary(&mut self, fetch_id: i64, summary: &Summary) -> Result<()> {
+    /// Stored articles by row id, skipping ids that aren't there
+    pub fn load_articles_by_ids(&self, ids: &[i64]) -> Result<Vec<NewsArticle>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, og_title, published_time, og_image, author, text, source FROM articles WHERE id = ?",
+        )?;
+        let mut articles = vec![];
+        for id in ids {
+            if let Some(article) = stmt.query_and_then([id], article_from_row)?.next() {
+                articles.push(article?);
+            }
+        }
+        Ok(articles)
+    }
+
+    /// Returns the ids of the inserted bullets, in the same order as `summary.items`
+    pub fn insert_summary(&mut self, fetch_id: i64, summary: &Summary) -> Result<Vec<i64>> {
+        self.insert_summary_of(fetch_id, summary, "daily", None)
+    }
+
+    /// Store a roll-up of `start..=end`, kept out of the daily dedup by its kind
+    pub fn insert_rollup(&mut self, fetch_id: i64, summary: &Summary, kind: &str, start: NaiveDate, end: NaiveDate) -> Result<Vec<i64>> {
+        self.insert_summary_of(fetch_id, summary, kind, Some((start, end)))
+    }
+
+    fn insert_summary_of(&mut self, fetch_id: i64, summary: &Summary, kind: &str, period: Option<(NaiveDate, NaiveDate)>) -> Result<Vec<i64>> {
         let tx = self.conn.transaction()?; // transactional insert
 
-        // 📝 Insert into summaries table (just the mood now)
+        // 📝 Insert into summaries table (mood and sentiment)
         tx.execute(
-            "INSERT INTO summaries (fetch_id, mood_text) VALUES (?, ?)",
-            params![fetch_id, &summary.mood],
+            "INSERT INTO summaries (
+                fetch_id, mood_text, sentiment, category_sentiment, match_section, kind, period_start, period_end
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                fetch_id,
+                &summary.mood,
+                summary.sentiment,
+                serde_json::to_string(&summary.category_sentiment)?,
+                summary.match_section.as_ref().map(serde_json::to_string).transpose()?,
+                kind,
+                period.map(|(start, _)| start.to_string()),
+                period.map(|(_, end)| end.to_string()),
+            ],
         ).context("Failed to insert into summaries table")?;
 
         // ➕ Insert each bullet (with accepted flag) into bullets table
         let mut stmt = tx.prepare(
-            "INSERT INTO bullets (fetch_id, text, accepted) VALUES (?, ?, ?)"
+            "INSERT INTO bullets (
+                fetch_id, text, accepted, reason, category, importance, deferred, carryovers,
+                grounded, grounding_notes, headline, detail, update_on
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
         )?;
 
+        let mut bullet_ids = Vec::with_capacity(summary.items.len());
         for bullet in &summary.items {
             stmt.execute(params![
                 fetch_id,
                 bullet.text,
                 bullet.accepted,
+                bullet.reason,
+                bullet.category,
+                bullet.importance,
+                bullet.deferred,
+                bullet.carryovers,
+                bullet.grounded,
+                bullet.grounding_notes,
+                bullet.headline,
+                bullet.detail,
+                bullet.update_on,
             ])?;
+            bullet_ids.push(tx.last_insert_rowid());
+        }
+        drop(stmt);
+
+        let mut stmt = tx.prepare(
+            "INSERT OR IGNORE INTO bullet_sources (bullet_id, article_id) VALUES (?, ?)"
+        )?;
+        for (bullet, id) in summary.items.iter().zip(&bullet_ids) {
+            for article_id in &bullet.sources {
+                stmt.execute(params![id, article_id])?;
+            }
         }
+        drop(stmt);
 
+        // same-day matches only have ids now that every bullet is inserted
+        let mut stmt = tx.prepare("UPDATE bullets SET duplicate_of = ? WHERE id = ?")?;
+        for (bullet, id) in summary.items.iter().zip(&bullet_ids) {
+            let duplicate_of = match bullet.duplicate_of {
+                Some(DuplicateOf::Previous(prev_id)) => Some(prev_id),
+                Some(DuplicateOf::Candidate(i)) => bullet_ids.get(i).copied(),
+                None => None,
+            };
+            if duplicate_of.is_some() {
+                stmt.execute(params![duplicate_of, id])?;
+            }
+        }
         drop(stmt);
         tx.commit().context("Failed to commit summary + bullets")?;
 
+        Ok(bullet_ids)
+    }
+
+    // store the embedding of every bullet that has one, keyed by the ids from insert_summary
+    pub fn insert_bullet_embeddings(&mut self, bullet_ids: &[i64], bullets: &[Bullet], model: &str) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO bullet_embeddings (bullet_id, model, vector) VALUES (?, ?, ?)"
+            )?;
+            for (id, bullet) in bullet_ids.iter().zip(bullets) {
+                if let Some(vector) = &bullet.embedding {
+                    stmt.execute(params![id, model, vector_to_blob(vector)])?;
+                }
+            }
+        }
+        tx.commit().context("Failed to commit bullet embeddings")?;
+        Ok(())
+    }
+
+    pub fn insert_bullet_embedding(&self, bullet_id: i64, model: &str, vector: &[f32]) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO bullet_embeddings (bullet_id, model, vector) VALUES (?, ?, ?)",
+            params![bullet_id, model, vector_to_blob(vector)],
+        )?;
+        Ok(())
+    }
+
+    // stored embedding for a bullet, if one was computed with `model`
+    pub fn load_bullet_embedding(&self, bullet_id: i64, model: &str) -> Result<Option<Vec<f32>>> {
+        let blob = self.conn.query_row(
+            "SELECT vector FROM bullet_embeddings WHERE bullet_id = ? AND model = ?",
+            params![bullet_id, model],
+            |row| row.get::<_, Vec<u8>>(0),
+        ).optional()?;
+        Ok(blob.map(|b| blob_to_vector(&b)))
+    }
+
+    // stored embedding for an article, if one was computed with `model`
+    pub fn load_article_embedding(&self, article_id: i64, model: &str) -> Result<Option<Vec<f32>>> {
+        let blob = self.conn.query_row(
+            "SELECT vector FROM article_embeddings WHERE article_id = ? AND model = ?",
+            params![article_id, model],
+            |row| row.get::<_, Vec<u8>>(0),
+        ).optional()?;
+        Ok(blob.map(|b| blob_to_vector(&b)))
+    }
+
+    pub fn insert_article_embedding(&self, article_id: i64, model: &str, vector: &[f32]) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO article_embeddings (article_id, model, vector) VALUES (?, ?, ?)",
+            params![article_id, model, vector_to_blob(vector)],
+        )?;
         Ok(())
     }
-    // bullets from the most recent *published* (sent=1) summary
-    pub fn fetch_latest_published_bullets(&self) -> anyhow::Result<Vec<Bullet>> {
+
+    /// Articles cited by bullets published in the `days` before `fetch_id` ran
+    pub fn published_source_articles(&self, fetch_id: i64, days: u32) -> Result<Vec<NewsArticle>> {
         let mut stmt = self.conn.prepare(r#"
-            SELECT s.fetch_id
-            FROM summaries s
+            SELECT DISTINCT a.id, a.url, a.og_title, a.published_time, a.og_image, a.author, a.text, a.source
+            FROM articles a
+            JOIN bullet_sources bs ON bs.article_id = a.id
+            JOIN bullets b ON b.id = bs.bullet_id
+            JOIN summaries s ON s.fetch_id = b.fetch_id
+            WHERE s.sent = 1 AND s.kind = 'daily' AND b.accepted = 1 AND b.deferred = 0
+              AND s.fetch_id < ?1
+              AND s.generated_at >= datetime(COALESCE((SELECT fetched_at FROM fetches WHERE id = ?1), 'now'), ?2)
+        "#)?;
+        let iter = stmt.query_and_then(params![fetch_id, format!("-{days} days")], article_from_row)?;
+        iter.collect()
+    }
+
+    /// Record which published article each follow-up restates, clearing earlier marks of the fetch
+    pub fn mark_followups(&mut self, fetch_id: i64, followups: &[(i64, i64)]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE articles SET followup_of = NULL WHERE fetch_id = ?", [fetch_id])?;
+        for (article_id, of) in followups {
+            tx.execute("UPDATE articles SET followup_of = ? WHERE id = ?", [of, article_id])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // accepted bullets from the *published* (sent=1) summaries inside the lookback window, as the
+    // deduplicator saw them when `before` (a fetch id) ran; up to now if None
+    pub fn fetch_published_bullets_before(&self, before: Option<i64>, lookback: Lookback) -> Result<Vec<Bullet>> {
+        let window = match lookback {
+            Lookback::Days(_) => "s.generated_at >= datetime(COALESCE((SELECT fetched_at FROM fetches WHERE id = ?2), 'now'), ?1)",
+            Lookback::Summaries(_) => r#"s.fetch_id IN (
+                SELECT fetch_id FROM summaries WHERE sent = 1 AND kind = 'daily' AND (?2 IS NULL OR fetch_id < ?2)
+                ORDER BY generated_at DESC, fetch_id DESC LIMIT ?1
+            )"#,
+        };
+        let param = match lookback {
+            Lookback::Days(days) => format!("-{} days", days),
+            Lookback::Summaries(n) => n.to_string(),
+        };
+
+        let mut stmt = self.conn.prepare(&format!(r#"
+            SELECT b.id, b.text, b.accepted, s.generated_at
+            FROM bullets b
+            JOIN summaries s ON s.fetch_id = b.fetch_id
             WHERE s.sent = 1
-            ORDER BY s.generated_at DESC
-            LIMIT 1
+              AND s.kind = 'daily'
+              AND b.accepted = 1
+              AND b.deferred = 0
+              AND (?2 IS NULL OR s.fetch_id < ?2)
+              AND {window}
+            ORDER BY s.generated_at DESC, b.id
+        "#))?;
+        let iter = stmt.query_map(params![param, before], |row| {
+            Ok(Bullet {
+                id: Some(row.get(0)?),
+                text: row.get(1)?,
+                accepted: row.get::<_, Option<bool>>(2)?,
+                published_at: row.get::<_, Option<String>>(3)?
+                    .and_then(|t| NaiveDateTime::parse_from_str(&t, SQLITE_DATETIME).ok()),
+                ..Default::default()
+            })
+        })?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    // carryover candidates: accepted bullets from *unpublished* (sent=0) summaries since the last
+    // published one, plus bullets the last published summary deferred because of the digest caps.
+    // `fetch_id`'s own summary is left out, a regenerated fetch would otherwise carry itself over
+    pub fn fetch_carryover_bullets(&self, fetch_id: i64, max_carryovers: u32) -> anyhow::Result<Vec<Bullet>> {
+        let mut stmt = self.conn.prepare(r#"
+            WITH last_published AS (
+                SELECT fetch_id, generated_at FROM summaries
+                WHERE sent = 1 AND kind = 'daily'
+                ORDER BY generated_at DESC, fetch_id DESC
+                LIMIT 1
+            )
+            SELECT DISTINCT b.text, b.accepted, b.category, b.importance, b.carryovers,
+                   b.grounded, b.grounding_notes,
+                   (SELECT GROUP_CONCAT(article_id) FROM bullet_sources WHERE bullet_id = b.id),
+                   b.headline, b.detail, b.update_on
+            FROM bullets b
+            JOIN summaries s ON s.fetch_id = b.fetch_id
+            WHERE b.accepted = 1
+              AND s.kind = 'daily'
+              AND b.carryovers < ?1
+              AND s.fetch_id != ?2
+              AND (
+                  (s.sent = 0
+                   AND s.generated_at >
+                       COALESCE((SELECT generated_at FROM last_published), '0001-01-01T00:00:00Z'))
+                  OR (b.deferred = 1 AND s.fetch_id = (SELECT fetch_id FROM last_published))
+              )
+            ORDER BY s.generated_at DESC, b.id DESC
+        "#)?;
+        let iter = stmt.query_map(params![max_carryovers, fetch_id], |row| {
+            Ok(Bullet {
+                text: row.get(0)?,
+                accepted: row.get::<_, Option<bool>>(1)?,
+                category: row.get(2)?,
+                importance: row.get(3)?,
+                carryovers: row.get(4)?,
+                grounded: row.get(5)?,
+                grounding_notes: row.get(6)?,
+                sources: row.get::<_, Option<String>>(7)?
+                    .map(|ids| ids.split(',').filter_map(|id| id.parse().ok()).collect())
+                    .unwrap_or_default(),
+                headline: row.get(8)?,
+                detail: row.get(9)?,
+                update_on: row.get(10)?,
+                ..Default::default()
+            })
+        })?;
+        Ok(iter.filter_map(|r| r.ok()).collect())
+    }
+
+    /// `fetch_id` is None for calls made outside a run, like `lfc ask`
+    pub fn insert_llm_calls(&mut self, fetch_id: impl Into<Option<i64>>, calls: &[LlmCall]) -> Result<()> {
+        let fetch_id = fetch_id.into();
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO llm_calls (
+                    fetch_id, step, model, flex, attempt, succeeded, error, latency_ms,
+                    prompt_tokens, completion_tokens, reasoning_tokens, cost_usd
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            )?;
+            for c in calls {
+                stmt.execute(params![
+                    fetch_id, c.step, c.model, c.flex, c.attempt, c.succeeded, c.error, c.latency_ms as i64,
+                    c.prompt_tokens, c.completion_tokens, c.reasoning_tokens, c.cost_usd,
+                ])?;
+            }
+        }
+        tx.commit().context("Failed to commit LLM calls")?;
+        Ok(())
+    }
+
+    pub fn insert_content_cuts(&mut self, fetch_id: i64, cuts: &[ContentCut]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO content_cuts (
+                    fetch_id, step, article_id, class, weight, body_tokens, kept_tokens, dropped
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+            )?;
+            for c in cuts {
+                stmt.execute(params![
+                    fetch_id, c.step, c.article_id, c.class.as_str(), c.weight,
+                    c.body_tokens as i64, c.kept_tokens as i64, c.dropped,
+                ])?;
+            }
+        }
+        tx.commit().context("Failed to commit content cuts")?;
+        Ok(())
+    }
+
+    /// Articles of the stories updated since `since`, with their story and its last update
+    pub fn story_articles_since(&self, since: NaiveDateTime) -> Result<Vec<(i64, NaiveDateTime, NewsArticle)>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT a.id, a.url, a.og_title, a.published_time, a.og_image, a.author, a.text, a.source,
+                   s.id, s.last_updated
+            FROM articles a
+            JOIN stories s ON s.id = a.story_id
+            WHERE s.last_updated >= ?
+            ORDER BY a.id
         "#)?;
-        let fetch_id_opt = stmt.query_row([], |row| row.get::<_, i64>(0)).optional()?;
-        let Some(fetch_id) = fetch_id_opt else { return Ok(vec![]) };
+        let rows = stmt.query_and_then([since.format(SQLITE_DATETIME).to_string()], |row| -> Result<_> {
+            let last_updated = NaiveDateTime::parse_from_str(&row.get::<_, String>(9)?, SQLITE_DATETIME)?;
+            Ok((row.get(8)?, last_updated, article_from_row(row)?))
+        })?;
+        rows.collect()
+    }
 
+    /// Every stored article, oldest first, for rebuilding the story threads
+    pub fn load_all_articles(&self) -> Result<Vec<NewsArticle>> {
         let mut stmt = self.conn.prepare(
-            "SELECT text, accepted FROM bullets WHERE fetch_id = ? AND accepted = 1"
+            "SELECT id, url, og_title, published_time, og_image, author, text, source FROM articles ORDER BY published_time, id",
+        )?;
+        let rows = stmt.query_and_then([], article_from_row)?;
+        rows.collect()
+    }
+
+    /// Accepted bullets of daily summaries with their sources, oldest first
+    pub fn load_accepted_bullets(&self) -> Result<Vec<Bullet>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT b.id, b.text, b.headline,
+                   (SELECT GROUP_CONCAT(article_id) FROM bullet_sources WHERE bullet_id = b.id),
+                   s.generated_at
+            FROM bullets b
+            JOIN summaries s ON s.fetch_id = b.fetch_id
+            WHERE b.accepted = 1 AND s.kind = 'daily'
+            ORDER BY s.generated_at, b.id
+        "#)?;
+        let iter = stmt.query_map([], |row| {
+            let sources: Option<String> = row.get(3)?;
+            Ok(Bullet {
+                id: Some(row.get(0)?),
+                text: row.get(1)?,
+                headline: row.get(2)?,
+                accepted: Some(true),
+                sources: sources.iter().flat_map(|s| s.split(',')).filter_map(|id| id.parse().ok()).collect(),
+                published_at: row.get::<_, Option<String>>(4)?
+                    .and_then(|t| NaiveDateTime::parse_from_str(&t, SQLITE_DATETIME).ok()),
+                ..Default::default()
+            })
+        })?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    pub fn create_story(&self, title: &str, at: NaiveDateTime) -> Result<i64> {
+        let at = at.format(SQLITE_DATETIME).to_string();
+        self.conn.execute(
+            "INSERT INTO stories (title, first_seen, last_updated) VALUES (?1, ?2, ?2)",
+            params![title, at],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Put an article on a story, widening the story's dates to cover it
+    pub fn set_article_story(&self, article_id: i64, story_id: i64, at: NaiveDateTime) -> Result<()> {
+        self.conn.execute("UPDATE articles SET story_id = ? WHERE id = ?", params![story_id, article_id])?;
+        self.conn.execute(
+            "UPDATE stories SET first_seen = MIN(first_seen, ?1), last_updated = MAX(last_updated, ?1) WHERE id = ?2",
+            params![at.format(SQLITE_DATETIME).to_string(), story_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_bullet_story(&self, bullet_id: i64, story_id: i64) -> Result<()> {
+        self.conn.execute("UPDATE bullets SET story_id = ? WHERE id = ?", params![story_id, bullet_id])?;
+        Ok(())
+    }
+
+    /// Label a bullet as an update on the earlier reported story titled `title`
+    pub fn set_bullet_update_on(&self, bullet_id: i64, title: &str) -> Result<()> {
+        self.conn.execute("UPDATE bullets SET update_on = ? WHERE id = ?", params![title, bullet_id])?;
+        Ok(())
+    }
+
+    /// The story of each article in `ids` that has one
+    pub fn article_story_ids(&self, ids: &[i64]) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare("SELECT story_id FROM articles WHERE id = ? AND story_id IS NOT NULL")?;
+        let mut stories = vec![];
+        for id in ids {
+            if let Some(story) = stmt.query_row([id], |row| row.get(0)).optional()? {
+                stories.push(story);
+            }
+        }
+        Ok(stories)
+    }
+
+    /// Whether a sent digest before `fetch_id` already had a bullet on the story
+    pub fn story_reported_before(&self, story_id: i64, fetch_id: i64) -> Result<bool> {
+        Ok(self.conn.query_row(
+            r#"SELECT EXISTS (
+                SELECT 1 FROM bullets b
+                JOIN summaries s ON s.fetch_id = b.fetch_id
+                WHERE b.story_id = ?1 AND b.accepted = 1 AND b.deferred = 0
+                  AND s.sent = 1 AND s.kind = 'daily' AND s.fetch_id < ?2
+            )"#,
+            params![story_id, fetch_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Forget every story, before rebuilding them
+    pub fn clear_stories(&mut self) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE articles SET story_id = NULL WHERE story_id IS NOT NULL", [])?;
+        tx.execute("UPDATE bullets SET story_id = NULL WHERE story_id IS NOT NULL", [])?;
+        tx.execute("DELETE FROM stories", [])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stories updated in the last `days` (or all of them), latest first, with their article and
+    /// sent bullet counts. With `id`, only that story
+    pub fn list_stories(&self, days: Option<u32>, id: Option<i64>) -> Result<Vec<Story>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT st.id, st.title, st.first_seen, st.last_updated,
+                   (SELECT COUNT(*) FROM articles WHERE story_id = st.id),
+                   (SELECT COUNT(*) FROM bullets b JOIN summaries s ON s.fetch_id = b.fetch_id
+                    WHERE b.story_id = st.id AND b.accepted = 1 AND b.deferred = 0 AND s.sent = 1 AND s.kind = 'daily')
+            FROM stories st
+            WHERE (?1 IS NULL OR st.last_updated >= datetime('now', '-' || ?1 || ' days'))
+              AND (?2 IS NULL OR st.id = ?2)
+            ORDER BY st.last_updated DESC, st.id DESC
+        "#)?;
+        let rows = stmt.query_and_then(params![days, id], |row| -> Result<Story> {
+            Ok(Story {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                first_seen: NaiveDateTime::parse_from_str(&row.get::<_, String>(2)?, SQLITE_DATETIME)?,
+                last_updated: NaiveDateTime::parse_from_str(&row.get::<_, String>(3)?, SQLITE_DATETIME)?,
+                articles: row.get(4)?,
+                bullets: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// A story's articles and sent bullets, oldest first
+    pub fn story_timeline(&self, story_id: i64) -> Result<Vec<StoryEntry>> {
+        let mut entries = vec![];
+
+        let mut stmt = self.conn.prepare("SELECT published_time, og_title, source, url FROM articles WHERE story_id = ?")?;
+        for row in stmt.query_and_then([story_id], |row| -> Result<StoryEntry> {
+            let published: DateTime<Utc> = row.get::<_, String>(0)?.parse()?;
+            Ok(StoryEntry {
+                at: published.naive_utc(),
+                text: row.get(1)?,
+                source: row.get(2)?,
+                url: row.get(3)?,
+            })
+        })? {
+            entries.push(row?);
+        }
+
+        let mut stmt = self.conn.prepare(r#"
+            SELECT s.generated_at, b.text
+            FROM bullets b
+            JOIN summaries s ON s.fetch_id = b.fetch_id
+            WHERE b.story_id = ? AND b.accepted = 1 AND b.deferred = 0 AND s.sent = 1 AND s.kind = 'daily'
+        "#)?;
+        for row in stmt.query_and_then([story_id], |row| -> Result<StoryEntry> {
+            Ok(StoryEntry {
+                at: NaiveDateTime::parse_from_str(&row.get::<_, String>(0)?, SQLITE_DATETIME)?,
+                text: row.get(1)?,
+                source: None,
+                url: None,
+            })
+        })? {
+            entries.push(row?);
+        }
+
+        entries.sort_by_key(|e| e.at);
+        Ok(entries)
+    }
+
+    /// Tag an article with entities. Dictionary tags replace its earlier dictionary tags; LLM
+    /// tags only add entities the dictionary didn't find
+    pub fn insert_article_entities(&mut self, article_id: i64, method: &str, tags: &[EntityTag]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        if method == "alias" {
+            tx.execute("DELETE FROM article_entities WHERE article_id = ? AND method = 'alias'", [article_id])?;
+        }
+        for t in tags {
+            let entity_id = upsert_entity(&tx, t)?;
+            tx.execute(
+                "INSERT INTO article_entities (article_id, entity_id, mentions, method) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(article_id, entity_id) DO UPDATE SET mentions = excluded.mentions, method = excluded.method
+                 WHERE excluded.method = 'alias'",
+                params![article_id, entity_id, t.mentions, method],
+            )?;
+        }
+        tx.commit().context("Failed to commit article entities")?;
+        Ok(())
+    }
+
+    /// Replace a bullet's entity tags
+    pub fn insert_bullet_entities(&mut self, bullet_id: i64, tags: &[EntityTag]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM bullet_entities WHERE bullet_id = ?", [bullet_id])?;
+        for t in tags {
+            let entity_id = upsert_entity(&tx, t)?;
+            tx.execute("INSERT INTO bullet_entities (bullet_id, entity_id) VALUES (?, ?)", params![bullet_id, entity_id])?;
+        }
+        tx.commit().context("Failed to commit bullet entities")?;
+        Ok(())
+    }
+
+    /// Id, title and text of every stored article, for tagging them again
+    pub fn all_article_texts(&self) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare("SELECT id, COALESCE(og_title, '') || '\n' || COALESCE(text, '') FROM articles ORDER BY id")?;
+        let iter = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Id and text of every stored bullet
+    pub fn all_bullet_texts(&self) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare("SELECT id, COALESCE(text, '') FROM bullets ORDER BY id")?;
+        let iter = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Articles naming each entity in the last `days` and the `days` before, and sent bullets
+    /// naming it in the last `days`, most-named first
+    pub fn mention_counts(&self, days: u32, kind: Option<&str>) -> Result<Vec<MentionRow>> {
+        let mut stmt = self.conn.prepare(r#"
+            WITH a AS (
+                SELECT ae.entity_id,
+                       SUM(f.fetched_at >= datetime('now', ?1)) AS latest,
+                       SUM(f.fetched_at < datetime('now', ?1)) AS previous
+                FROM article_entities ae
+                JOIN articles ar ON ar.id = ae.article_id
+                JOIN fetches f ON f.id = ar.fetch_id
+                WHERE f.fetched_at >= datetime('now', ?2)
+                GROUP BY ae.entity_id
+            ), b AS (
+                SELECT be.entity_id, COUNT(*) AS bullets
+                FROM bullet_entities be
+                JOIN bullets bu ON bu.id = be.bullet_id
+                JOIN summaries s ON s.fetch_id = bu.fetch_id
+                WHERE s.sent = 1
+                  AND s.kind = 'daily'
+                  AND bu.accepted = 1
+                  AND bu.deferred = 0
+                  AND s.generated_at >= datetime('now', ?1)
+                GROUP BY be.entity_id
+            )
+            SELECT e.name, e.kind, COALESCE(a.latest, 0), COALESCE(a.previous, 0), COALESCE(b.bullets, 0)
+            FROM entities e
+            LEFT JOIN a ON a.entity_id = e.id
+            LEFT JOIN b ON b.entity_id = e.id
+            WHERE (?3 IS NULL OR e.kind = ?3)
+              AND (a.entity_id IS NOT NULL OR b.entity_id IS NOT NULL)
+            ORDER BY 3 DESC, 5 DESC, 4 DESC, e.name
+        "#)?;
+        let iter = stmt.query_map(
+            params![format!("-{} days", days), format!("-{} days", days * 2), kind],
+            |row| {
+                Ok(MentionRow {
+                    name: row.get(0)?,
+                    kind: row.get(1)?,
+                    articles: row.get(2)?,
+                    previous_articles: row.get(3)?,
+                    bullets: row.get(4)?,
+                })
+            },
+        )?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    pub fn month_to_date_cost(&self) -> Result<f64> {
+        let cost = self.conn.query_row(
+            "SELECT COALESCE(SUM(cost_usd), 0) FROM llm_calls WHERE called_at >= datetime('now', 'start of month')",
+            [],
+            |row| row.get::<_, f64>(0),
+        )?;
+        Ok(cost)
+    }
+
+    // token and cost totals per period ("%Y-%m-%d" or "%Y-%m") and model, newest first
+    pub fn cost_ledger(&self, period_format: &str) -> Result<Vec<CostRow>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT strftime(?1, called_at) AS period, model, COUNT(*),
+                   COALESCE(SUM(prompt_tokens), 0), COALESCE(SUM(completion_tokens), 0),
+                   COALESCE(SUM(reasoning_tokens), 0), SUM(cost_usd)
+            FROM llm_calls
+            GROUP BY period, model
+            ORDER BY period DESC, model
+        "#)?;
+        let iter = stmt.query_map([period_format], |row| {
+            Ok(CostRow {
+                period: row.get(0)?,
+                model: row.get(1)?,
+                calls: row.get(2)?,
+                prompt_tokens: row.get(3)?,
+                completion_tokens: row.get(4)?,
+                reasoning_tokens: row.get(5)?,
+                cost_usd: row.get(6)?,
+            })
+        })?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    // sentiment of every summary since `days` ago (all of them when None), oldest first
+    pub fn mood_history(&self, days: Option<u32>) -> Result<Vec<MoodPoint>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT fetch_id, generated_at, sentiment, category_sentiment
+            FROM summaries
+            WHERE sentiment IS NOT NULL
+              AND kind = 'daily'
+              AND (?1 IS NULL OR generated_at >= datetime('now', '-' || ?1 || ' days'))
+            ORDER BY generated_at, fetch_id
+        "#)?;
+        let iter = stmt.query_and_then([days], |row| -> Result<MoodPoint> {
+            let generated_at: String = row.get(1)?;
+            Ok(MoodPoint {
+                fetch_id: row.get(0)?,
+                generated_at: NaiveDateTime::parse_from_str(&generated_at, SQLITE_DATETIME)?,
+                sentiment: row.get(2)?,
+                categories: row.get::<_, Option<String>>(3)?
+                    .map(|json| serde_json::from_str(&json))
+                    .transpose()?
+                    .unwrap_or_default(),
+            })
+        })?;
+        iter.collect()
+    }
+
+    // rejected bullets of a fetch (the latest one by default) with the bullet each one matched
+    pub fn fetch_rejected_bullets(&self, fetch_id: Option<i64>) -> Result<Vec<RejectedBullet>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT b.id, b.fetch_id, b.text, b.reason,
+                   m.id, m.fetch_id, m.text
+            FROM bullets b
+            LEFT JOIN bullets m ON m.id = b.duplicate_of
+            WHERE b.accepted = 0
+              AND b.fetch_id = COALESCE(?, (SELECT MAX(fetch_id) FROM summaries WHERE kind = 'daily'))
+            ORDER BY b.id
+        "#)?;
+        let iter = stmt.query_map([fetch_id], |row| {
+            Ok(RejectedBullet {
+                id: row.get(0)?,
+                fetch_id: row.get(1)?,
+                text: row.get(2)?,
+                reason: row.get(3)?,
+                matched_id: row.get(4)?,
+                matched_fetch_id: row.get(5)?,
+                matched_text: row.get(6)?,
+            })
+        })?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    // every bullet a fetch's summary judged, with the deduplicator's decision
+    pub fn fetch_judged_bullets(&self, fetch_id: i64) -> Result<Vec<Bullet>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, text, accepted, reason FROM bullets WHERE fetch_id = ? AND accepted IS NOT NULL ORDER BY id"
         )?;
         let iter = stmt.query_map([fetch_id], |row| {
             Ok(Bullet {
-                text: row.get(0)?,
-                accepted: row.get::<_, Option<bool>>(1)?,
+                id: Some(row.get(0)?),
+                text: row.get(1)?,
+                accepted: row.get(2)?,
+                reason: row.get(3)?,
+                ..Default::default()
             })
         })?;
-        Ok(iter.filter_map(|r| r.ok()).collect())
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
     }
 
-    // accepted bullets from the most recent *unpublished* (sent=0) summary
-    pub fn fetch_unpublished_accepted_bullets_since_last_published(&self) -> anyhow::Result<Vec<Bullet>> {
+    // bullets sent in daily summaries between two dates (inclusive), oldest first
+    pub fn fetch_sent_bullets_between(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<Bullet>> {
         let mut stmt = self.conn.prepare(r#"
-            SELECT DISTINCT b.text, b.accepted
+            SELECT b.id, b.text, s.generated_at, b.category, b.importance
             FROM bullets b
             JOIN summaries s ON s.fetch_id = b.fetch_id
-            WHERE s.sent = 0
+            WHERE s.sent = 1
+              AND s.kind = 'daily'
               AND b.accepted = 1
-              AND s.generated_at >
-                  COALESCE((SELECT MAX(generated_at) FROM summaries WHERE sent = 1),
-                           '0001-01-01T00:00:00Z')
-            ORDER BY s.generated_at DESC, b.id DESC
+              AND b.deferred = 0
+              AND s.generated_at >= ?1 AND s.generated_at < date(?2, '+1 day')
+            ORDER BY s.generated_at, b.id
         "#)?;
-        let iter = stmt.query_map([], |row| {
+        let iter = stmt.query_map([start.to_string(), end.to_string()], |row| {
             Ok(Bullet {
-                text: row.get(0)?,
-                accepted: row.get::<_, Option<bool>>(1)?,
+                id: Some(row.get(0)?),
+                text: row.get(1)?,
+                accepted: Some(true),
+                published_at: row.get::<_, Option<String>>(2)?
+                    .and_then(|t| NaiveDateTime::parse_from_str(&t, SQLITE_DATETIME).ok()),
+                category: row.get(3)?,
+                importance: row.get(4)?,
+                ..Default::default()
             })
         })?;
-        Ok(iter.filter_map(|r| r.ok()).collect())
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    // (generated_at, mood sentence) of the daily summaries between two dates (inclusive)
+    pub fn fetch_moods_between(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT generated_at, mood_text FROM summaries
+            WHERE kind = 'daily' AND sent = 1 AND mood_text IS NOT NULL
+              AND generated_at >= ?1 AND generated_at < date(?2, '+1 day')
+            ORDER BY generated_at
+        "#)?;
+        let iter = stmt.query_map([start.to_string(), end.to_string()], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    // articles scraped between two dates (inclusive)
+    pub fn load_articles_between(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<NewsArticle>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT a.id, a.url, a.og_title, a.published_time, a.og_image, a.author, a.text, a.source
+            FROM articles a
+            JOIN fetches f ON f.id = a.fetch_id
+            WHERE f.fetched_at >= ?1 AND f.fetched_at < date(?2, '+1 day')
+            ORDER BY f.fetched_at, a.id
+        "#)?;
+        let iter = stmt.query_and_then([start.to_string(), end.to_string()], article_from_row)?;
+        iter.collect()
+    }
+
+    // most recent articles of the last `days` days whose title or text mentions `term`
+    pub fn load_articles_mentioning(&self, term: &str, days: u32, limit: usize) -> Result<Vec<NewsArticle>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT a.id, a.url, a.og_title, a.published_time, a.og_image, a.author, a.text, a.source
+            FROM articles a
+            JOIN fetches f ON f.id = a.fetch_id
+            WHERE f.fetched_at >= datetime('now', '-' || ?2 || ' days')
+              AND (a.og_title LIKE '%' || ?1 || '%' OR a.text LIKE '%' || ?1 || '%')
+            ORDER BY f.fetched_at DESC, a.id DESC
+            LIMIT ?3
+        "#)?;
+        let iter = stmt.query_and_then(params![term, days, limit as i64], article_from_row)?;
+        iter.collect()
+    }
+
+    // articles scraped between two dates (inclusive) that mention any of `terms`, best matches
+    // first, a match in the title counting triple
+    pub fn search_articles(&self, terms: &[String], start: NaiveDate, end: NaiveDate, limit: usize) -> Result<Vec<NewsArticle>> {
+        if terms.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut stmt = self.conn.prepare(r#"
+            SELECT a.id, a.url, a.og_title, a.published_time, a.og_image, a.author, a.text, a.source
+            FROM articles_fts
+            JOIN articles a ON a.id = articles_fts.rowid
+            JOIN fetches f ON f.id = a.fetch_id
+            WHERE articles_fts MATCH ?1
+              AND f.fetched_at >= ?2 AND f.fetched_at < date(?3, '+1 day')
+            ORDER BY bm25(articles_fts, 3.0, 1.0, 1.0)
+            LIMIT ?4
+        "#)?;
+        let iter = stmt.query_and_then(
+            params![any_term(terms), start.to_string(), end.to_string(), limit as i64],
+            article_from_row,
+        )?;
+        iter.collect()
+    }
+
+    // bullets sent in daily summaries between two dates (inclusive) that mention any of `terms`,
+    // best matches first
+    pub fn search_bullets(&self, terms: &[String], start: NaiveDate, end: NaiveDate, limit: usize) -> Result<Vec<Bullet>> {
+        if terms.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut stmt = self.conn.prepare(r#"
+            SELECT b.id, b.text, s.generated_at, b.category,
+                   (SELECT GROUP_CONCAT(article_id) FROM bullet_sources WHERE bullet_id = b.id)
+            FROM bullets_fts
+            JOIN bullets b ON b.id = bullets_fts.rowid
+            JOIN summaries s ON s.fetch_id = b.fetch_id
+            WHERE bullets_fts MATCH ?1
+              AND s.sent = 1 AND s.kind = 'daily' AND b.accepted = 1 AND b.deferred = 0
+              AND s.generated_at >= ?2 AND s.generated_at < date(?3, '+1 day')
+            ORDER BY bm25(bullets_fts)
+            LIMIT ?4
+        "#)?;
+        let iter = stmt.query_map(
+            params![any_term(terms), start.to_string(), end.to_string(), limit as i64],
+            sent_bullet_from_row,
+        )?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Articles matching an FTS5 query (words, "phrases", prefix*, AND/OR/NOT), best first,
+    /// with a snippet around the match
+    pub fn search_article_hits(&self, query: &str, filters: &SearchFilters, limit: usize) -> Result<Vec<ArticleHit>> {
+        let mut sql = String::from(r#"
+            SELECT a.id, a.og_title, a.source, a.author, a.url, date(a.published_time),
+                   snippet(articles_fts, -1, '[', ']', '…', 16)
+            FROM articles_fts
+            JOIN articles a ON a.id = articles_fts.rowid
+            WHERE articles_fts MATCH ?
+        "#);
+        let mut values: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(query.to_string())];
+        if let Some(from) = filters.from {
+            sql.push_str(" AND date(a.published_time) >= ?");
+            values.push(Box::new(from.to_string()));
+        }
+        if let Some(to) = filters.to {
+            sql.push_str(" AND date(a.published_time) <= ?");
+            values.push(Box::new(to.to_string()));
+        }
+        if let Some(source) = &filters.source {
+            sql.push_str(" AND a.source LIKE '%' || ? || '%'");
+            values.push(Box::new(source.clone()));
+        }
+        if let Some(author) = &filters.author {
+            sql.push_str(" AND a.author LIKE '%' || ? || '%'");
+            values.push(Box::new(author.clone()));
+        }
+        sql.push_str(" ORDER BY bm25(articles_fts, 3.0, 1.0, 1.0) LIMIT ?");
+        values.push(Box::new(limit as i64));
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let iter = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+            Ok(ArticleHit {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                source: row.get(2)?,
+                author: row.get(3)?,
+                url: row.get(4)?,
+                published: row.get::<_, Option<String>>(5)?
+                    .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+                snippet: row.get(6)?,
+            })
+        })?;
+        iter.collect::<rusqlite::Result<Vec<_>>>().context("Search failed, check the query syntax")
+    }
+
+    /// Sent digest bullets matching an FTS5 query, best first
+    pub fn search_bullet_hits(&self, query: &str, from: Option<NaiveDate>, to: Option<NaiveDate>, limit: usize) -> Result<Vec<BulletHit>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT b.id, s.generated_at, snippet(bullets_fts, 0, '[', ']', '…', 32)
+            FROM bullets_fts
+            JOIN bullets b ON b.id = bullets_fts.rowid
+            JOIN summaries s ON s.fetch_id = b.fetch_id
+            WHERE bullets_fts MATCH ?1
+              AND s.sent = 1 AND s.kind = 'daily' AND b.accepted = 1 AND b.deferred = 0
+              AND (?2 IS NULL OR date(s.generated_at) >= ?2)
+              AND (?3 IS NULL OR date(s.generated_at) <= ?3)
+            ORDER BY bm25(bullets_fts)
+            LIMIT ?4
+        "#)?;
+        let iter = stmt.query_map(
+            params![query, from.map(|d| d.to_string()), to.map(|d| d.to_string()), limit as i64],
+            |row| {
+                Ok(BulletHit {
+                    id: row.get(0)?,
+                    sent_at: row.get::<_, Option<String>>(1)?
+                        .and_then(|t| NaiveDateTime::parse_from_str(&t, SQLITE_DATETIME).ok()),
+                    snippet: row.get(2)?,
+                })
+            },
+        )?;
+        iter.collect::<rusqlite::Result<Vec<_>>>().context("Search failed, check the query syntax")
+    }
+
+    // bullets sent in daily summaries between two dates (inclusive) that have an embedding
+    // from `model`, with the embedding filled in
+    pub fn embedded_bullets_between(&self, model: &str, start: NaiveDate, end: NaiveDate) -> Result<Vec<Bullet>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT b.id, b.text, s.generated_at, b.category,
+                   (SELECT GROUP_CONCAT(article_id) FROM bullet_sources WHERE bullet_id = b.id),
+                   e.vector
+            FROM bullets b
+            JOIN summaries s ON s.fetch_id = b.fetch_id
+            JOIN bullet_embeddings e ON e.bullet_id = b.id AND e.model = ?1
+            WHERE s.sent = 1 AND s.kind = 'daily' AND b.accepted = 1 AND b.deferred = 0
+              AND s.generated_at >= ?2 AND s.generated_at < date(?3, '+1 day')
+        "#)?;
+        let iter = stmt.query_map(params![model, start.to_string(), end.to_string()], |row| {
+            let mut bullet = sent_bullet_from_row(row)?;
+            bullet.embedding = Some(blob_to_vector(&row.get::<_, Vec<u8>>(5)?));
+            Ok(bullet)
+        })?;
+        Ok(iter.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    // open rumours plus closed ones touched in the last `days` days, so a done deal that is
+    // reported again isn't opened as a new rumour
+    pub fn recent_transfer_rumours(&self, days: u32) -> Result<Vec<TransferRumour>> {
+        self.query_rumours(
+            "WHERE status NOT IN ('done', 'collapsed') OR last_updated >= datetime('now', '-' || ?1 || ' days')",
+            params![days],
+        )
+    }
+
+    // every rumour, or only the open ones, most recently updated first
+    pub fn list_transfer_rumours(&self, include_closed: bool) -> Result<Vec<TransferRumour>> {
+        self.query_rumours("WHERE ?1 OR status NOT IN ('done', 'collapsed')", params![include_closed])
+    }
+
+    fn query_rumours(&self, filter: &str, params: impl rusqlite::Params) -> Result<Vec<TransferRumour>> {
+        let mut stmt = self.conn.prepare(&format!(r#"
+            SELECT id, player, from_club, to_club, direction, fee, status, first_seen, last_updated
+            FROM transfer_rumours
+            {filter}
+            ORDER BY last_updated DESC, id DESC
+        "#))?;
+        let iter = stmt.query_and_then(params, |row| -> Result<TransferRumour> {
+            let direction: String = row.get(4)?;
+            let status: String = row.get(6)?;
+            Ok(TransferRumour {
+                id: row.get(0)?,
+                player: row.get(1)?,
+                from_club: row.get(2)?,
+                to_club: row.get(3)?,
+                direction: if direction == "out" { TransferDirection::Out } else { TransferDirection::In },
+                fee: row.get(5)?,
+                status: TransferStatus::parse(&status).with_context(|| format!("Unknown transfer status {status}"))?,
+                first_seen: NaiveDateTime::parse_from_str(&row.get::<_, String>(7)?, SQLITE_DATETIME)?,
+                last_updated: NaiveDateTime::parse_from_str(&row.get::<_, String>(8)?, SQLITE_DATETIME)?,
+            })
+        })?;
+        iter.collect()
+    }
+
+    /// Insert a new rumour, or overwrite the tracked fields of an existing one; returns its id
+    pub fn upsert_transfer_rumour(&self, rumour: &TransferRumour) -> Result<i64> {
+        if rumour.id == 0 {
+            self.conn.execute(
+                "INSERT INTO transfer_rumours (player, from_club, to_club, direction, fee, status) VALUES (?, ?, ?, ?, ?, ?)",
+                params![rumour.player, rumour.from_club, rumour.to_club, rumour.direction.as_str(), rumour.fee, rumour.status.as_str()],
+            )?;
+            Ok(self.conn.last_insert_rowid())
+        } else {
+            self.conn.execute(
+                "UPDATE transfer_rumours SET from_club = ?, to_club = ?, fee = ?, status = ?, last_updated = CURRENT_TIMESTAMP WHERE id = ?",
+                params![rumour.from_club, rumour.to_club, rumour.fee, rumour.status.as_str(), rumour.id],
+            )?;
+            Ok(rumour.id)
+        }
+    }
+
+    pub fn insert_transfer_update(&self, rumour_id: i64, fetch_id: i64, status: TransferStatus, fee: Option<&str>, source: &str, url: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO transfer_updates (rumour_id, fetch_id, status, fee, source, url) VALUES (?, ?, ?, ?, ?, ?)",
+            params![rumour_id, fetch_id, status.as_str(), fee, source, url],
+        )?;
+        Ok(())
+    }
+
+    // every report about a rumour, oldest first
+    pub fn transfer_history(&self, rumour_id: i64) -> Result<Vec<TransferUpdate>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT status, fee, source, url, reported_at
+            FROM transfer_updates
+            WHERE rumour_id = ?
+            ORDER BY reported_at, id
+        "#)?;
+        let iter = stmt.query_and_then([rumour_id], |row| -> Result<TransferUpdate> {
+            let status: String = row.get(0)?;
+            Ok(TransferUpdate {
+                status: TransferStatus::parse(&status).with_context(|| format!("Unknown transfer status {status}"))?,
+                fee: row.get(1)?,
+                source: row.get(2)?,
+                url: row.get(3)?,
+                reported_at: NaiveDateTime::parse_from_str(&row.get::<_, String>(4)?, SQLITE_DATETIME)?,
+            })
+        })?;
+        iter.collect()
+    }
+
+    // every tracked player, or only those not available, most recently updated first
+    pub fn player_availability(&self, unavailable_only: bool) -> Result<Vec<PlayerAvailability>> {
+        let mut stmt = self.conn.prepare(r#"
+            SELECT id, player, status, issue, expected_return, last_updated
+            FROM player_availability
+            WHERE NOT ?1 OR status != 'available'
+            ORDER BY last_updated DESC, id DESC
+        "#)?;
+        let iter = stmt.query_and_then([unavailable_only], |row| -> Result<PlayerAvailability> {
+            let status: String = row.get(2)?;
+            Ok(PlayerAvailability {
+                id: row.get(0)?,
+                player: row.get(1)?,
+                status: AvailabilityStatus::parse(&status).with_context(|| format!("Unknown availability status {status}"))?,
+                issue: row.get(3)?,
+                expected_return: row.get(4)?,
+                last_updated: NaiveDateTime::parse_from_str(&row.get::<_, String>(5)?, SQLITE_DATETIME)?,
+            })
+        })?;
+        iter.collect()
+    }
+
+    /// Mark players unavailable with no report in the last `days` available again, returning how many
+    pub fn expire_availability(&self, days: u32) -> Result<usize> {
+        Ok(self.conn.execute(
+            "UPDATE player_availability SET status = 'available', expected_return = NULL, last_updated = CURRENT_TIMESTAMP
+             WHERE status != 'available' AND last_updated < datetime('now', ?)",
+            [format!("-{days} days")],
+        )?)
+    }
+
+    /// Insert a newly tracked player, or overwrite an existing one's status; returns its id
+    pub fn upsert_player_availability(&self, player: &PlayerAvailability) -> Result<i64> {
+        if player.id == 0 {
+            self.conn.execute(
+                "INSERT INTO player_availability (player, status, issue, expected_return) VALUES (?, ?, ?, ?)",
+                params![player.player, player.status.as_str(), player.issue, player.expected_return],
+            )?;
+            Ok(self.conn.last_insert_rowid())
+        } else {
+            self.conn.execute(
+                "UPDATE player_availability SET status = ?, issue = ?, expected_return = ?, last_updated = CURRENT_TIMESTAMP WHERE id = ?",
+                params![player.status.as_str(), player.issue, player.expected_return, player.id],
+            )?;
+            Ok(player.id)
+        }
+    }
+
+    pub fn insert_availability_update(&self, availability_id: i64, fetch_id: i64, player: &PlayerAvailability, source: &str, url: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO availability_updates (availability_id, fetch_id, status, issue, expected_return, source, url) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![availability_id, fetch_id, player.status.as_str(), player.issue, player.expected_return, source, url],
+        )?;
+        Ok(())
     }
 
     // mark a summary as sent after notifications succeed
@@ -162,6 +1213,238 @@ impl Db {
         )?;
         Ok(())
     }
+
+    /// When the daily summary of a fetch was generated and whether it was sent, if it has one
+    pub fn summary_state(&self, fetch_id: i64) -> Result<Option<(Option<NaiveDateTime>, bool)>> {
+        let state = self.conn.query_row(
+            "SELECT generated_at, sent FROM summaries WHERE fetch_id = ? AND kind = 'daily'",
+            [fetch_id],
+            |row| Ok((
+                row.get::<_, Option<String>>(0)?
+                    .and_then(|t| NaiveDateTime::parse_from_str(&t, SQLITE_DATETIME).ok()),
+                row.get::<_, bool>(1)?,
+            )),
+        ).optional()?;
+        Ok(state)
+    }
+
+    pub fn count_fetch_articles(&self, fetch_id: i64) -> Result<i64> {
+        Ok(self.conn.query_row("SELECT COUNT(*) FROM articles WHERE fetch_id = ?", [fetch_id], |row| row.get(0))?)
+    }
+
+    /// The last fetch on `day` (UTC) that stored any articles
+    pub fn latest_fetch_on(&self, day: NaiveDate) -> Result<Option<i64>> {
+        Ok(self.conn.query_row(
+            r#"SELECT MAX(f.id) FROM fetches f
+               WHERE date(f.fetched_at) = ?
+                 AND EXISTS (SELECT 1 FROM articles a WHERE a.fetch_id = f.id)"#,
+            [day.to_string()],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Whether a fetch after this one has a daily summary
+    pub fn has_later_summary(&self, fetch_id: i64) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM summaries WHERE kind = 'daily' AND fetch_id > ?)",
+            [fetch_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Delete the daily summary of a fetch and its bullets, with their sources and embeddings
+    pub fn delete_summary(&mut self, fetch_id: i64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM bullets WHERE fetch_id = ?", [fetch_id])?;
+        tx.execute("DELETE FROM summaries WHERE fetch_id = ? AND kind = 'daily'", [fetch_id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Keep the daily summary of a fetch and its bullets as a superseded version, moved to a
+    /// fetch of their own so the fetch can be summarized again
+    pub fn supersede_summary(&mut self, fetch_id: i64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM summaries WHERE fetch_id = ? AND kind = 'daily')",
+            [fetch_id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            tx.execute("INSERT INTO fetches DEFAULT VALUES", [])?;
+            let version_fetch = tx.last_insert_rowid();
+            tx.execute(
+                "UPDATE summaries SET fetch_id = ?1, kind = 'superseded', version_of = ?2 WHERE fetch_id = ?2",
+                params![version_fetch, fetch_id],
+            )?;
+            tx.execute("UPDATE bullets SET fetch_id = ?1 WHERE fetch_id = ?2", params![version_fetch, fetch_id])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn create_run(&self, fetch_id: Option<i64>, finished: Option<Stage>, options: &RunOptions) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO runs (fetch_id, stage, options, state, updated_at) VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)",
+            params![
+                fetch_id,
+                finished.map(Stage::as_str),
+                serde_json::to_string(options)?,
+                serde_json::to_string(&RunState::default())?,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn load_run(&self, run_id: i64) -> Result<Run> {
+        self.conn.query_row_and_then(
+            "SELECT id, fetch_id, stage, attempts, options, state FROM runs WHERE id = ?",
+            [run_id],
+            run_from_row,
+        )
+    }
+
+    /// Runs that failed, or are still running but haven't moved for `stale_minutes` and so
+    /// crashed, oldest first
+    pub fn incomplete_runs(&self, stale_minutes: u32) -> Result<Vec<Run>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, fetch_id, stage, attempts, options, state FROM runs WHERE {RESUMABLE_RUN} ORDER BY id"
+        ))?;
+        let iter = stmt.query_and_then([format!("-{stale_minutes} minutes")], run_from_row)?;
+        iter.collect()
+    }
+
+    /// Record what a run handed on after finishing `stage`
+    pub fn save_run(&self, run: &Run) -> Result<()> {
+        self.conn.execute(
+            "UPDATE runs SET fetch_id = ?, stage = ?, options = ?, state = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            params![
+                run.fetch_id,
+                run.stage.map(Stage::as_str),
+                serde_json::to_string(&run.options)?,
+                serde_json::to_string(&run.state)?,
+                run.id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Run `f` in one transaction, keeping its writes only if it succeeds
+    pub fn atomically<T>(&self, f: impl FnOnce(&Db) -> Result<T>) -> Result<T> {
+        let tx = self.conn.unchecked_transaction()?;
+        let out = f(self)?;
+        tx.commit()?;
+        Ok(out)
+    }
+
+    /// Claim a run found by `incomplete_runs` for another attempt. False when another process
+    /// claimed it first.
+    pub fn retry_run(&self, run_id: i64, stale_minutes: u32) -> Result<bool> {
+        let claimed = self.conn.execute(
+            &format!(
+                "UPDATE runs SET status = 'running', attempts = attempts + 1, updated_at = CURRENT_TIMESTAMP \
+                 WHERE id = ?2 AND {RESUMABLE_RUN}"
+            ),
+            params![format!("-{stale_minutes} minutes"), run_id],
+        )?;
+        Ok(claimed == 1)
+    }
+
+    /// `status` is done, failed or abandoned
+    pub fn finish_run(&self, run_id: i64, status: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE runs SET status = ?, finished_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            params![status, run_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn start_stage(&self, run_id: i64, stage: Stage) -> Result<i64> {
+        self.conn.execute("UPDATE runs SET updated_at = CURRENT_TIMESTAMP WHERE id = ?", [run_id])?;
+        self.conn.execute(
+            "INSERT INTO run_stages (run_id, stage, status) VALUES (?, ?, 'running')",
+            params![run_id, stage.as_str()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// `status` is done, skipped or failed
+    pub fn finish_stage(&self, stage_id: i64, status: &str, error: Option<&str>, duration_ms: u128) -> Result<()> {
+        self.conn.execute(
+            "UPDATE run_stages SET status = ?, error = ?, duration_ms = ?, finished_at = CURRENT_TIMESTAMP WHERE id = ?",
+            params![status, error, duration_ms as i64, stage_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_summary_time(&self, fetch_id: i64, generated_at: NaiveDateTime) -> Result<()> {
+        self.conn.execute(
+            "UPDATE summaries SET generated_at = ? WHERE fetch_id = ?",
+            params![generated_at.format(SQLITE_DATETIME).to_string(), fetch_id],
+        )?;
+        Ok(())
+    }
+}
+
+/// Id of the entity named by `tag`, adding it the first time it is seen
+fn upsert_entity(conn: &Connection, tag: &EntityTag) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO entities (name, kind) VALUES (?, ?) ON CONFLICT(name) DO NOTHING",
+        params![tag.name, tag.kind.as_str()],
+    )?;
+    Ok(conn.query_row("SELECT id FROM entities WHERE name = ?", [&tag.name], |row| row.get(0))?)
+}
+
+// id, fetch_id, stage, attempts, options, state
+fn run_from_row(row: &rusqlite::Row) -> Result<Run> {
+    let stage: Option<String> = row.get(2)?;
+    let state: Option<String> = row.get(5)?;
+    Ok(Run {
+        id: row.get(0)?,
+        fetch_id: row.get(1)?,
+        stage: stage.as_deref().and_then(Stage::parse),
+        attempts: row.get(3)?,
+        options: serde_json::from_str(&row.get::<_, String>(4)?).context("Unreadable run options")?,
+        state: state.as_deref().map(serde_json::from_str).transpose().context("Unreadable run state")?.unwrap_or_default(),
+    })
+}
+
+// id, text, generated_at, category, comma-separated source ids
+fn sent_bullet_from_row(row: &rusqlite::Row) -> rusqlite::Result<Bullet> {
+    Ok(Bullet {
+        id: Some(row.get(0)?),
+        text: row.get(1)?,
+        accepted: Some(true),
+        published_at: row.get::<_, Option<String>>(2)?
+            .and_then(|t| NaiveDateTime::parse_from_str(&t, SQLITE_DATETIME).ok()),
+        category: row.get(3)?,
+        sources: row.get::<_, Option<String>>(4)?
+            .map(|ids| ids.split(',').filter_map(|id| id.parse().ok()).collect())
+            .unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+// an FTS5 query matching any of the words, each quoted so none is read as an operator
+fn any_term(terms: &[String]) -> String {
+    terms.iter().map(|t| format!("\"{}\"", t.replace('"', "\"\""))).collect::<Vec<_>>().join(" OR ")
+}
+
+// expects the columns id, url, og_title, published_time, og_image, author, text, source
+fn article_from_row(row: &rusqlite::Row) -> Result<NewsArticle> {
+    let url: Url = row.get::<_, String>(1)?.parse()?;
+    let published_time = row.get::<_, String>(3)?.parse()?;
+    let og_image: Url = row.get::<_, String>(4)?.parse()?;
+    Ok(NewsArticle {
+        id: Some(row.get(0)?),
+        url,
+        og_title: row.get(2)?,
+        published_time,
+        og_image,
+        author: row.get(5)?,
+        text: row.get(6)?,
+        source: row.get(7)?,
+    })
 }
 
 pub fn load_existing_urls_from_db(db: &Db) -> Result<HashSet<Url>> {
@@ -9,10 +9,10 @@ use std::{net::{ToSocketAddrs, SocketAddr}, time::Duration};
 
 use crate::config::Config;
 
-pub async fn send_email(cfg: &Config, plain_text: &str) -> Result<()> {
-    let Some(recipients) = &cfg.emails else { return Ok(()); };
-    if recipients.is_empty() { return Ok(()); }
-    
+/// Send each (recipient, text) pair as its own email
+pub async fn send_email(cfg: &Config, subject: &str, messages: &[(String, String)]) -> Result<()> {
+    if messages.is_empty() { return Ok(()); }
+
     let Some(email_username) = &cfg.email_username else { return Ok(()); };
     let Some(email_app_password) = &cfg.email_app_password else { return Ok(()); };
 
@@ -38,11 +38,11 @@ pub async fn send_email(cfg: &Config, plain_text: &str) -> Result<()> {
         .timeout(Some(Duration::from_secs(20)))
         .build();
 
-    for rcpt in recipients {
+    for (rcpt, plain_text) in messages {
         let email = Message::builder()
             .from(email_username.parse::<Mailbox>()?)
             .to(rcpt.parse::<Mailbox>().context("Invalid recipient email")?)
-            .subject("LFC news summary")
+            .subject(subject)
             .body(plain_text.to_owned())?;
 
         mailer.send(email).await?;
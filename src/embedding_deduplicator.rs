@@ -0,0 +1,200 @@
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use log::{debug, info, warn};
+
+use crate::ai_deduplicator::ai_confirm_duplicates;
+use crate::config::Config;
+use crate::db::Db;
+use crate::embeddings::{cosine_similarity, embed_texts};
+use crate::models::{Bullet, DuplicateOf, Summary};
+
+/// Vectors for `history`, loading stored ones and embedding (and storing) the rest
+pub async fn embed_history(cfg: &Config, db: &Db, history: &[Bullet]) -> Result<Vec<Option<Vec<f32>>>> {
+    let model = &cfg.dedup.embedding_model;
+
+    let mut history_vectors: Vec<Option<Vec<f32>>> = Vec::with_capacity(history.len());
+    let mut missing: Vec<usize> = vec![];
+    for (i, b) in history.iter().enumerate() {
+        let stored = match b.id {
+            Some(id) => db.load_bullet_embedding(id, model)?,
+            None => None,
+        };
+        if stored.is_none() {
+            missing.push(i);
+        }
+        history_vectors.push(stored);
+    }
+
+    if !missing.is_empty() {
+        debug!("Backfilling embeddings for {} history bullets", missing.len());
+        let texts: Vec<String> = missing.iter().map(|&i| history[i].text.clone()).collect();
+        let vectors = embed_texts(cfg, &texts).await?;
+        for (&i, vector) in missing.iter().zip(vectors) {
+            if let Some(id) = history[i].id {
+                db.insert_bullet_embedding(id, model, &vector)?;
+            }
+            history_vectors[i] = Some(vector);
+        }
+    }
+
+    Ok(history_vectors)
+}
+
+/// Similarity of a candidate to a previous bullet, lowered by `dedup.decay_per_day` of its age
+fn decayed_similarity(cfg: &Config, candidate: &[f32], previous: &Bullet, vector: &[f32], now: NaiveDateTime) -> f32 {
+    cosine_similarity(candidate, vector) - cfg.dedup.decay_per_day * previous.age_days(now)
+}
+
+/// Shrink a large history to the `dedup.prefilter_top_k` most similar previous bullets per
+/// candidate, so the LLM deduplicator gets a short prompt however long the lookback is.
+/// The returned summary carries the candidate embeddings so they can be stored.
+pub async fn prefilter_history(cfg: &Config, db: &Db, history: &[Bullet], current_summary: &Summary) -> Result<(Vec<Bullet>, Summary)> {
+    let history_vectors = embed_history(cfg, db, history).await?;
+    let texts: Vec<String> = current_summary.items.iter().map(|b| b.text.clone()).collect();
+    let candidate_vectors = embed_texts(cfg, &texts).await?;
+    let now = Utc::now().naive_utc();
+
+    let mut keep = vec![false; history.len()];
+    for candidate in &candidate_vectors {
+        let mut scored: Vec<(usize, f32)> = history
+            .iter()
+            .zip(&history_vectors)
+            .enumerate()
+            .filter_map(|(i, (b, v))| v.as_ref().map(|v| (i, decayed_similarity(cfg, candidate, b, v, now))))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for (i, _) in scored.into_iter().take(cfg.dedup.prefilter_top_k) {
+            keep[i] = true;
+        }
+    }
+
+    let filtered: Vec<Bullet> = history
+        .iter()
+        .zip(keep)
+        .filter(|(_, k)| *k)
+        .map(|(b, _)| b.clone())
+        .collect();
+
+    let mut summary = current_summary.clone();
+    for (b, vector) in summary.items.iter_mut().zip(candidate_vectors) {
+        b.embedding = Some(vector);
+    }
+
+    Ok((filtered, summary))
+}
+
+/// Deduplicate `current_summary` against `history` by cosine similarity of bullet embeddings.
+///
+/// Candidates at or above `dedup.threshold` are rejected, candidates below `dedup.borderline`
+/// are accepted, and anything in between is decided by the chat model one pair at a time.
+/// History bullets without a stored embedding are embedded and stored on the way.
+pub async fn embedding_deduplicate(cfg: &Config, db: &Db, history: &[Bullet], current_summary: &Summary) -> Result<Summary> {
+    // 1) Make sure every history bullet has a vector
+    let history_vectors = embed_history(cfg, db, history).await?;
+    let now = Utc::now().naive_utc();
+
+    // 2) Embed today's candidates (carryover bullets are re-embedded, they are new rows)
+    let texts: Vec<String> = current_summary.items.iter().map(|b| b.text.clone()).collect();
+    let candidate_vectors = embed_texts(cfg, &texts).await?;
+
+    // 3) Compare each candidate against history and the earlier candidates still in play
+    let mut decisions: Vec<Option<bool>> = vec![None; texts.len()];
+    let mut reasons: Vec<Option<String>> = vec![None; texts.len()];
+    let mut matches: Vec<Option<DuplicateOf>> = vec![None; texts.len()];
+    let mut borderline: Vec<(usize, String)> = vec![];
+
+    for i in 0..texts.len() {
+        let mut best_sim = f32::MIN;
+        // a history bullet without a row id (an eval fixture's) still suppresses, unrecorded
+        let mut best: Option<(Option<DuplicateOf>, &str)> = None;
+
+        for (b, vector) in history.iter().zip(&history_vectors) {
+            let Some(vector) = vector else { continue };
+            let sim = decayed_similarity(cfg, &candidate_vectors[i], b, vector, now);
+            if sim > best_sim {
+                best_sim = sim;
+                best = Some((b.id.map(DuplicateOf::Previous), &b.text));
+            }
+        }
+
+        for j in 0..i {
+            if decisions[j] == Some(false) {
+                continue;
+            }
+            let sim = cosine_similarity(&candidate_vectors[i], &candidate_vectors[j]);
+            if sim > best_sim {
+                best_sim = sim;
+                best = Some((Some(DuplicateOf::Candidate(j)), &texts[j]));
+            }
+        }
+
+        debug!("Candidate {} best similarity {:.3}: {}", i, best_sim, texts[i]);
+
+        let Some((matched, matched_text)) = best else {
+            decisions[i] = Some(true);
+            reasons[i] = Some("Nothing to compare against".to_string());
+            continue;
+        };
+
+        if best_sim >= cfg.dedup.threshold {
+            decisions[i] = Some(false);
+            reasons[i] = Some(format!("Similarity {:.2} is at or above the {:.2} threshold", best_sim, cfg.dedup.threshold));
+            matches[i] = matched;
+        } else if best_sim >= cfg.dedup.borderline {
+            matches[i] = matched;
+            borderline.push((i, matched_text.to_string()));
+        } else {
+            decisions[i] = Some(true);
+            reasons[i] = Some(format!("Closest bullet has similarity {:.2}", best_sim));
+        }
+    }
+
+    // 4) Let the LLM settle the borderline pairs
+    if !borderline.is_empty() {
+        info!("Asking the LLM about {} borderline bullet pairs", borderline.len());
+        let pairs: Vec<(String, String)> = borderline
+            .iter()
+            .map(|(i, matched)| (texts[*i].clone(), matched.clone()))
+            .collect();
+
+        match ai_confirm_duplicates(cfg, &pairs).await {
+            Ok(verdicts) => {
+                for ((i, _), (duplicate, reason)) in borderline.iter().zip(verdicts) {
+                    decisions[*i] = Some(!duplicate);
+                    reasons[*i] = Some(format!("Borderline, LLM: {}", reason));
+                    if !duplicate {
+                        matches[*i] = None;
+                    }
+                }
+            }
+            Err(e) => {
+                // better to repeat a story than to drop one
+                warn!("Borderline deduplication failed, accepting those bullets: {e:?}");
+                for (i, _) in &borderline {
+                    decisions[*i] = Some(true);
+                    reasons[*i] = Some("Borderline, accepted because the LLM check failed".to_string());
+                    matches[*i] = None;
+                }
+            }
+        }
+    }
+
+    let items: Vec<Bullet> = current_summary
+        .items
+        .iter()
+        .zip(candidate_vectors)
+        .zip(decisions.into_iter().zip(reasons).zip(matches))
+        .map(|((b, vector), ((accepted, reason), duplicate_of))| Bullet {
+            accepted,
+            embedding: Some(vector),
+            reason,
+            duplicate_of,
+            ..b.clone()
+        })
+        .collect();
+
+    Ok(Summary {
+        items,
+        ..current_summary.clone()
+    })
+}
@@ -0,0 +1,133 @@
+use anyhow::{Context, Result, anyhow};
+use async_openai::types::CreateEmbeddingRequestArgs;
+use log::{debug, warn};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::costs::call_cost;
+use crate::llm::{backoff_delay, openai_client, record, retryable, LlmCall};
+
+/// Embed `texts` with the configured embedding model, returning one vector per input in order.
+/// Calls get the `llm` timeout and retries, with the same backoff; there is no fallback model,
+/// since vectors of different models can't be compared.
+pub async fn embed_texts(cfg: &Config, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+    if texts.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let client = openai_client(cfg)?;
+    let model = &cfg.dedup.embedding_model;
+    let request = CreateEmbeddingRequestArgs::default()
+        .model(model)
+        .input(texts.to_vec())
+        .build()
+        .context("Failed to build embedding request")?;
+    let timeout = Duration::from_secs(cfg.llm.timeout_secs);
+    let mut last_error = String::new();
+
+    for attempt in 1..=cfg.llm.max_retries + 1 {
+        if attempt > 1 {
+            let delay = backoff_delay(cfg, attempt - 1);
+            debug!("Waiting {:?} before retry {} of embed on {}", delay, attempt - 1, model);
+            tokio::time::sleep(delay).await;
+        }
+
+        debug!("Requesting {} embeddings from {} (attempt {})", texts.len(), model, attempt);
+        let start_time = Instant::now();
+        let result = tokio::time::timeout(timeout, client.embeddings().create(request.clone())).await;
+        let latency_ms = start_time.elapsed().as_millis() as u64;
+
+        let (error, retry) = match result {
+            Err(_) => (format!("timed out after {} seconds", cfg.llm.timeout_secs), true),
+            Ok(Err(e)) => retryable(e),
+            Ok(Ok(response)) if response.data.len() != texts.len() => {
+                (format!("returned {} vectors, expected {}", response.data.len(), texts.len()), true)
+            }
+            Ok(Ok(response)) => {
+                let prompt_tokens = response.usage.prompt_tokens;
+                record(LlmCall {
+                    step: "embed".to_string(),
+                    model: model.clone(),
+                    flex: false,
+                    attempt,
+                    succeeded: true,
+                    error: None,
+                    latency_ms,
+                    prompt_tokens: Some(prompt_tokens),
+                    completion_tokens: Some(0),
+                    reasoning_tokens: None,
+                    cost_usd: call_cost(cfg, model, false, prompt_tokens, 0),
+                });
+                let mut data = response.data;
+                data.sort_by_key(|e| e.index);
+                return Ok(data.into_iter().map(|e| e.embedding).collect());
+            }
+        };
+
+        warn!("embed attempt {} on {} failed: {}", attempt, model, error);
+        record(LlmCall {
+            step: "embed".to_string(),
+            model: model.clone(),
+            flex: false,
+            attempt,
+            succeeded: false,
+            error: Some(error.clone()),
+            latency_ms,
+            prompt_tokens: None,
+            completion_tokens: None,
+            reasoning_tokens: None,
+            cost_usd: None,
+        });
+        last_error = error;
+        if !retry {
+            break;
+        }
+    }
+
+    Err(anyhow!("Embedding call failed, last error: {}", last_error))
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+pub fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 1.0], &[2.0, 2.0]) - 1.0).abs() < 1e-6);
+
+        // Mismatched or degenerate vectors are never similar
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_blob_round_trip() {
+        let vector = vec![0.25, -1.5, 3.0e-7, f32::MAX];
+        assert_eq!(blob_to_vector(&vector_to_blob(&vector)), vector);
+    }
+}
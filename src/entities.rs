@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use async_openai::types::{ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage};
+use clap::ValueEnum;
+use log::{debug, info};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::json;
+use tiktoken_rs::o200k_base;
+
+use crate::ai_summarizer::decode_first_n_tokens;
+use crate::config::Config;
+use crate::db::Db;
+use crate::llm::{chat_json, JsonCall};
+use crate::models::{Bullet, NewsArticle};
+use crate::squad::{Squad, Status};
+
+/// What an article or bullet can be tagged with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    Player,
+    Club,
+    Competition,
+    Staff,
+}
+
+impl EntityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityKind::Player => "player",
+            EntityKind::Club => "club",
+            EntityKind::Competition => "competition",
+            EntityKind::Staff => "staff",
+        }
+    }
+}
+
+/// An entity an article or bullet names, and how many times
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityTag {
+    pub name: String,
+    pub kind: EntityKind,
+    pub mentions: u32,
+}
+
+// Liverpool itself is left out: every article is about it
+const CLUBS: &[(&str, &[&str])] = &[
+    ("Arsenal", &["Gunners"]),
+    ("Aston Villa", &["Villa"]),
+    ("Bournemouth", &["Cherries"]),
+    ("Brentford", &[]),
+    ("Brighton", &["Brighton & Hove Albion", "Seagulls"]),
+    ("Burnley", &[]),
+    ("Chelsea", &[]),
+    ("Crystal Palace", &["Palace"]),
+    ("Everton", &["Toffees"]),
+    ("Fulham", &[]),
+    ("Leeds United", &["Leeds"]),
+    ("Manchester City", &["Man City"]),
+    ("Manchester United", &["Man Utd", "Man United"]),
+    ("Newcastle United", &["Newcastle", "Magpies"]),
+    ("Nottingham Forest", &["Forest"]),
+    ("Sunderland", &[]),
+    ("Tottenham Hotspur", &["Tottenham", "Spurs"]),
+    ("West Ham United", &["West Ham", "Hammers"]),
+    ("Wolverhampton Wanderers", &["Wolves"]),
+    ("Real Madrid", &[]),
+    ("Barcelona", &["Barca", "Barça"]),
+    ("Atletico Madrid", &["Atlético Madrid", "Atletico", "Atlético"]),
+    ("Bayern Munich", &["Bayern"]),
+    ("Borussia Dortmund", &["Dortmund"]),
+    ("Bayer Leverkusen", &["Leverkusen"]),
+    ("Paris Saint-Germain", &["PSG"]),
+    ("Inter Milan", &["Inter"]),
+    ("AC Milan", &[]),
+    ("Juventus", &["Juve"]),
+    ("Al-Hilal", &["Al Hilal"]),
+];
+
+const COMPETITIONS: &[(&str, &[&str])] = &[
+    ("Premier League", &[]),
+    ("Champions League", &["UCL"]),
+    ("Europa League", &[]),
+    ("FA Cup", &[]),
+    ("Carabao Cup", &["League Cup", "EFL Cup"]),
+    ("Community Shield", &[]),
+    ("Club World Cup", &[]),
+    ("World Cup", &[]),
+];
+
+/// Every name articles may use for a player, club, competition or staff member: the squad file
+/// first, then `entities.dictionary`, then the built-in clubs and competitions
+pub struct Dictionary {
+    entities: Vec<(String, EntityKind)>,
+    by_alias: HashMap<String, usize>, // lowercase alias to its entity
+    pattern: Option<Regex>,
+}
+
+impl Dictionary {
+    pub fn new(cfg: &Config, squad: &Squad) -> Dictionary {
+        let mut entries: Vec<(String, EntityKind, Vec<String>)> = vec![];
+        for m in &squad.members {
+            let kind = if m.status == Status::Staff { EntityKind::Staff } else { EntityKind::Player };
+            entries.push((m.name.clone(), kind, m.aliases().map(str::to_string).collect()));
+        }
+        for e in &cfg.entities.dictionary {
+            entries.push((e.name.clone(), e.kind, e.aliases.clone()));
+        }
+        for (kind, list) in [(EntityKind::Club, CLUBS), (EntityKind::Competition, COMPETITIONS)] {
+            for (name, aliases) in list {
+                entries.push((name.to_string(), kind, aliases.iter().map(|a| a.to_string()).collect()));
+            }
+        }
+        Dictionary::from_entries(entries)
+    }
+
+    fn from_entries(entries: Vec<(String, EntityKind, Vec<String>)>) -> Dictionary {
+        let mut entities = vec![];
+        let mut by_alias = HashMap::new();
+        for (name, kind, aliases) in entries {
+            // an entity listed twice keeps its first kind and gains the other aliases
+            let i = match entities.iter().position(|(n, _)| n == &name) {
+                Some(i) => i,
+                None => {
+                    entities.push((name.clone(), kind));
+                    entities.len() - 1
+                }
+            };
+            for alias in std::iter::once(name).chain(aliases) {
+                by_alias.entry(alias.to_lowercase()).or_insert(i);
+            }
+        }
+
+        // longest first, so "Club World Cup" wins over "World Cup" where both match
+        let mut aliases: Vec<&String> = by_alias.keys().collect();
+        aliases.sort_by_key(|a| std::cmp::Reverse(a.len()));
+        let pattern = (!aliases.is_empty()).then(|| {
+            let alternatives: Vec<String> = aliases.iter().map(|a| regex::escape(a)).collect();
+            Regex::new(&format!(r"(?i)\b(?:{})\b", alternatives.join("|"))).unwrap()
+        });
+        Dictionary { entities, by_alias, pattern }
+    }
+
+    /// The entities `text` names, with how many times
+    pub fn tag(&self, text: &str) -> Vec<EntityTag> {
+        let Some(pattern) = &self.pattern else { return vec![] };
+        let mut counts: Vec<(usize, u32)> = vec![];
+        for m in pattern.find_iter(text) {
+            // a one-word name has to be capitalized, so "forest" or "spurs" in a sentence isn't a club
+            if !m.as_str().contains(char::is_whitespace) && !m.as_str().starts_with(char::is_uppercase) {
+                continue;
+            }
+            let Some(&i) = self.by_alias.get(&m.as_str().to_lowercase()) else { continue };
+            match counts.iter_mut().find(|(e, _)| *e == i) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((i, 1)),
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(i, mentions)| EntityTag { name: self.entities[i].0.clone(), kind: self.entities[i].1, mentions })
+            .collect()
+    }
+
+    /// The listed entity `name` is an alias of, if any
+    fn lookup(&self, name: &str) -> Option<(String, EntityKind)> {
+        self.by_alias.get(&name.to_lowercase()).map(|&i| self.entities[i].clone())
+    }
+}
+
+/// Tag articles with the entities the dictionary finds in their title and text
+pub fn tag_articles(cfg: &Config, db: &mut Db, articles: &[NewsArticle]) -> Result<()> {
+    let dictionary = Dictionary::new(cfg, &Squad::load(cfg)?);
+    for a in articles {
+        let Some(id) = a.id else { continue };
+        let tags = dictionary.tag(&format!("{}\n{}", a.og_title, a.text));
+        db.insert_article_entities(id, "alias", &tags)?;
+    }
+    debug!("Tagged {} articles with entities", articles.len());
+    Ok(())
+}
+
+/// Tag stored bullets, `ids` matching `bullets`
+pub fn tag_bullets(cfg: &Config, db: &mut Db, ids: &[i64], bullets: &[Bullet]) -> Result<()> {
+    let dictionary = Dictionary::new(cfg, &Squad::load(cfg)?);
+    for (&id, b) in ids.iter().zip(bullets) {
+        db.insert_bullet_entities(id, &dictionary.tag(&b.text))?;
+    }
+    Ok(())
+}
+
+/// Tag every stored article and bullet again, e.g. after the squad file changed. Returns how
+/// many articles and bullets were tagged
+pub fn retag_all(cfg: &Config, db: &mut Db) -> Result<(usize, usize)> {
+    let dictionary = Dictionary::new(cfg, &Squad::load(cfg)?);
+    let articles = db.all_article_texts()?;
+    for (id, text) in &articles {
+        db.insert_article_entities(*id, "alias", &dictionary.tag(text))?;
+    }
+    let bullets = db.all_bullet_texts()?;
+    for (id, text) in &bullets {
+        db.insert_bullet_entities(*id, &dictionary.tag(text))?;
+    }
+    Ok((articles.len(), bullets.len()))
+}
+
+#[derive(Debug, Deserialize)]
+struct LlmEntity {
+    name: String,
+    kind: EntityKind,
+}
+
+#[derive(Debug, Deserialize)]
+struct LlmArticle {
+    index: usize,
+    entities: Vec<LlmEntity>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LlmEntities {
+    articles: Vec<LlmArticle>,
+}
+
+/// Have the chat model list who each article is about, adding the entities the dictionary
+/// missed. Names the dictionary knows under another alias are stored under their listed name
+pub async fn tag_articles_llm(cfg: &Config, db: &mut Db, articles: &[NewsArticle]) -> Result<()> {
+    if articles.is_empty() {
+        return Ok(());
+    }
+    let dictionary = Dictionary::new(cfg, &Squad::load(cfg)?);
+    let bpe = o200k_base().unwrap();
+    let articles_text = articles
+        .iter()
+        .enumerate()
+        .map(|(i, a)| format!("[{i}] {}\n\n{}", a.og_title, decode_first_n_tokens(&bpe, &a.text, cfg.entities.max_article_tokens)))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let system_prompt = r#"
+You tag Liverpool FC news articles with who and what they are about.
+
+For each numbered article, list the players, clubs, competitions and staff (managers, coaches, directors, owners) it names. Use each one's full, usual name ("Mohamed Salah", "Manchester United", "Champions League"), never a nickname. Leave out Liverpool FC itself, journalists and pundits.
+
+Respond only with a JSON object listing every article index with its entities.
+"#;
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "articles": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "index": { "type": "integer" },
+                        "entities": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "name": { "type": "string" },
+                                    "kind": { "type": "string", "enum": ["player", "club", "competition", "staff"] }
+                                },
+                                "required": ["name", "kind"],
+                                "additionalProperties": false
+                            }
+                        }
+                    },
+                    "required": ["index", "entities"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["articles"],
+        "additionalProperties": false
+    });
+
+    let parsed: LlmEntities = chat_json(cfg, JsonCall {
+        step: "entities",
+        schema_name: "article_entities",
+        schema,
+        messages: vec![
+            ChatCompletionRequestSystemMessage::from(system_prompt).into(),
+            ChatCompletionRequestUserMessage::from(articles_text).into(),
+        ],
+        max_completion_tokens: 6000,
+    }).await?;
+
+    let mut tagged = 0;
+    for found in parsed.articles {
+        let Some(id) = articles.get(found.index).and_then(|a| a.id) else { continue };
+        let mut tags: Vec<EntityTag> = vec![];
+        for e in found.entities {
+            let (name, kind) = dictionary.lookup(&e.name).unwrap_or((e.name, e.kind));
+            if name.to_lowercase().starts_with("liverpool") || tags.iter().any(|t| t.name == name) {
+                continue;
+            }
+            tags.push(EntityTag { name, kind, mentions: 1 });
+        }
+        tagged += tags.len();
+        db.insert_article_entities(id, "llm", &tags)?;
+    }
+    info!("The model named {} entities in {} articles", tagged, articles.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag() {
+        let dictionary = Dictionary::from_entries(vec![
+            ("Virgil van Dijk".to_string(), EntityKind::Player, vec!["Van Dijk".to_string(), "VVD".to_string()]),
+            ("Nottingham Forest".to_string(), EntityKind::Club, vec!["Forest".to_string()]),
+            ("Club World Cup".to_string(), EntityKind::Competition, vec![]),
+            ("World Cup".to_string(), EntityKind::Competition, vec![]),
+        ]);
+
+        let tags = dictionary.tag("Virgil van Dijk starts against Forest. van Dijk said the forest of flags at the Club World Cup was great.");
+        assert_eq!(tags, vec![
+            EntityTag { name: "Virgil van Dijk".to_string(), kind: EntityKind::Player, mentions: 2 },
+            EntityTag { name: "Nottingham Forest".to_string(), kind: EntityKind::Club, mentions: 1 },
+            EntityTag { name: "Club World Cup".to_string(), kind: EntityKind::Competition, mentions: 1 },
+        ]);
+
+        assert_eq!(dictionary.lookup("vvd"), Some(("Virgil van Dijk".to_string(), EntityKind::Player)));
+        assert!(Dictionary::from_entries(vec![]).tag("Van Dijk").is_empty());
+    }
+}
@@ -0,0 +1,526 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Local, Utc};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::ai_summarizer::summarize_articles;
+use crate::app::deduplicate;
+use crate::config::{Config, EvalVariant};
+use crate::costs::apply_budget;
+use crate::db::Db;
+use crate::grounding::lexical_check;
+use crate::llm::{drain_call_log, LlmCall};
+use crate::logger::init_logger;
+use crate::models::{Bullet, NewsArticle, Summary};
+use crate::squad::Squad;
+
+/// A frozen fetch: its articles, the published bullets it is deduplicated against and
+/// human labels saying which candidate bullets repeat them
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Fixture {
+    pub name: String,
+    pub articles: Vec<FixtureArticle>,
+    #[serde(default)]
+    pub history: Vec<HistoryBullet>,
+    #[serde(default)]
+    pub labels: Vec<DedupLabel>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FixtureArticle {
+    pub title: String,
+    pub url: String,
+    pub source: String,
+    #[serde(default)]
+    pub author: String,
+    pub published: DateTime<Utc>,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryBullet {
+    pub text: String,
+    #[serde(default)]
+    pub days_ago: u32,
+}
+
+/// A candidate bullet and whether it should be rejected as a repeat
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DedupLabel {
+    pub text: String,
+    pub duplicate: bool,
+}
+
+/// One fetch or fixture, ready to run
+struct Case {
+    name: String,
+    articles: Vec<NewsArticle>,
+    history: Vec<Bullet>,
+    labels: Vec<DedupLabel>,
+}
+
+impl Case {
+    fn from_fixture(fixture: Fixture) -> Result<Case> {
+        let now = Utc::now().naive_utc();
+        let articles = fixture.articles.into_iter()
+            .enumerate()
+            .map(|(i, a)| {
+                let url: url::Url = a.url.parse().with_context(|| format!("Invalid article URL {}", a.url))?;
+                Ok(NewsArticle {
+                    // stand-in ids so bullets can cite the articles
+                    id: Some(i as i64 + 1),
+                    og_image: url.clone(),
+                    url,
+                    og_title: a.title,
+                    published_time: a.published,
+                    author: a.author,
+                    text: a.text,
+                    source: a.source,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let history = fixture.history.into_iter()
+            .map(|h| Bullet {
+                text: h.text,
+                accepted: Some(true),
+                published_at: Some(now - Duration::days(h.days_ago as i64)),
+                ..Default::default()
+            })
+            .collect();
+        Ok(Case { name: fixture.name, articles, history, labels: fixture.labels })
+    }
+
+    fn from_fetch(cfg: &Config, db: &Db, fetch_id: i64) -> Result<Case> {
+        let articles = db.load_articles_for_latest_fetch(&fetch_id)?;
+        if articles.is_empty() {
+            return Err(anyhow!("Fetch {fetch_id} has no stored articles"));
+        }
+        Ok(Case {
+            name: format!("fetch {fetch_id}"),
+            articles,
+            history: db.fetch_published_bullets_before(Some(fetch_id), cfg.dedup.lookback())?,
+            labels: vec![],
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Metrics {
+    runs: usize,
+    failed_runs: usize,
+    articles: usize,
+    bullets: usize,
+    cited_bullets: usize,   // bullets citing at least one of the articles
+    cited_articles: usize,  // articles at least one bullet cites
+    rejected: usize,        // bullets the deduplicator rejected
+    ungrounded: usize,      // bullets with numbers or names their sources don't mention
+    labels: usize,
+    agreed: usize,          // labels the deduplicator agreed with
+    tokens: u64,
+    cost_usd: f64,
+    latency_ms: u64,
+    failed_calls: usize,
+}
+
+impl Metrics {
+    fn add(&mut self, other: &Metrics) {
+        self.runs += other.runs;
+        self.failed_runs += other.failed_runs;
+        self.articles += other.articles;
+        self.bullets += other.bullets;
+        self.cited_bullets += other.cited_bullets;
+        self.cited_articles += other.cited_articles;
+        self.rejected += other.rejected;
+        self.ungrounded += other.ungrounded;
+        self.labels += other.labels;
+        self.agreed += other.agreed;
+        self.tokens += other.tokens;
+        self.cost_usd += other.cost_usd;
+        self.latency_ms += other.latency_ms;
+        self.failed_calls += other.failed_calls;
+    }
+
+    fn add_calls(&mut self, calls: &[LlmCall]) {
+        for c in calls {
+            self.tokens += (c.prompt_tokens.unwrap_or(0) + c.completion_tokens.unwrap_or(0)) as u64;
+            self.cost_usd += c.cost_usd.unwrap_or(0.0);
+            self.latency_ms += c.latency_ms;
+            self.failed_calls += usize::from(!c.succeeded);
+        }
+    }
+
+    /// Count bullets, citations, dedup rejections and lexical grounding failures of a
+    /// deduplicated summary
+    fn score_summary(&mut self, articles: &[NewsArticle], summary: &Summary, squad: &Squad) {
+        let mut cited: HashSet<i64> = HashSet::new();
+        for b in &summary.items {
+            let sources: Vec<&NewsArticle> = b.sources.iter()
+                .filter_map(|id| articles.iter().find(|a| a.id == Some(*id)))
+                .collect();
+            self.bullets += 1;
+            self.cited_bullets += usize::from(!sources.is_empty());
+            self.rejected += usize::from(b.accepted == Some(false));
+            let texts: Vec<&str> = sources.iter().flat_map(|a| [a.og_title.as_str(), a.text.as_str()]).collect();
+            self.ungrounded += usize::from(sources.is_empty() || !lexical_check(&b.text, &texts, squad).is_empty());
+            cited.extend(sources.iter().filter_map(|a| a.id));
+        }
+        self.cited_articles += cited.len();
+    }
+}
+
+/// What one variant made of one case
+struct Run {
+    metrics: Metrics,
+    summary: Option<Summary>,
+    labelled: Vec<(String, bool, Option<bool>)>, // label text, duplicate per the label, per the deduplicator
+    errors: Vec<String>,
+}
+
+async fn run_case(cfg: &Config, db: &mut Db, case: &Case) -> Result<Run> {
+    let mut run = Run { metrics: Metrics::default(), summary: None, labelled: vec![], errors: vec![] };
+    run.metrics.runs = 1;
+    run.metrics.articles = case.articles.len();
+    let squad = Squad::load(cfg)?;
+
+    match summarize_articles(cfg, &case.articles, &HashSet::new(), &None).await {
+        Ok(summary) => match deduplicate(cfg, db, &case.history, &summary).await {
+            Ok(judged) => {
+                run.metrics.score_summary(&case.articles, &judged, &squad);
+                run.summary = Some(judged);
+            }
+            Err(e) => {
+                run.metrics.score_summary(&case.articles, &summary, &squad);
+                run.errors.push(format!("dedup: {e}"));
+                run.summary = Some(summary);
+            }
+        },
+        Err(e) => run.errors.push(format!("summarize: {e}")),
+    }
+
+    if !case.labels.is_empty() {
+        let candidates = Summary {
+            items: case.labels.iter().map(|l| Bullet { text: l.text.clone(), ..Default::default() }).collect(),
+            ..Default::default()
+        };
+        match deduplicate(cfg, db, &case.history, &candidates).await {
+            Ok(judged) => {
+                for (label, b) in case.labels.iter().zip(&judged.items) {
+                    let duplicate = b.accepted.map(|a| !a);
+                    run.metrics.labels += 1;
+                    run.metrics.agreed += usize::from(duplicate == Some(label.duplicate));
+                    run.labelled.push((label.text.clone(), label.duplicate, duplicate));
+                }
+            }
+            Err(e) => {
+                run.metrics.labels += case.labels.len();
+                run.errors.push(format!("labelled dedup: {e}"));
+            }
+        }
+    }
+
+    let calls = drain_call_log();
+    run.metrics.add_calls(&calls);
+    db.insert_llm_calls(None, &calls)?;
+    run.metrics.failed_runs = usize::from(!run.errors.is_empty());
+    Ok(run)
+}
+
+/// The config a variant runs with. Fallback models are dropped so every call is made by
+/// the variant's own model.
+fn variant_config(cfg: &Config, variant: &EvalVariant) -> Config {
+    let mut cfg = cfg.clone();
+    if let Some(model) = &variant.model {
+        cfg.model = model.clone();
+    }
+    if let Some(prompts) = &variant.prompts {
+        cfg.prompts = prompts.clone();
+    }
+    cfg.llm.fallback.retain(|f| f.model.is_none());
+    cfg
+}
+
+fn pick_variants(cfg: &Config, models: &[String], names: &[String]) -> Result<Vec<EvalVariant>> {
+    if !models.is_empty() {
+        return Ok(models.iter()
+            .map(|m| EvalVariant { name: m.clone(), model: Some(m.clone()), prompts: None })
+            .collect());
+    }
+    if let Some(unknown) = names.iter().find(|n| !cfg.eval.variants.iter().any(|v| &v.name == *n)) {
+        return Err(anyhow!("No eval variant named {unknown:?} in the config"));
+    }
+    let variants: Vec<EvalVariant> = cfg.eval.variants.iter()
+        .filter(|v| names.is_empty() || names.contains(&v.name))
+        .cloned()
+        .collect();
+    if variants.is_empty() {
+        return Ok(vec![EvalVariant { name: "current".to_string(), model: None, prompts: None }]);
+    }
+    Ok(variants)
+}
+
+fn load_fixtures(dir: &Path) -> Result<Vec<Case>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read fixtures in {}", dir.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yaml" | "yml")))
+        .collect();
+    paths.sort();
+
+    paths.iter()
+        .map(|path| {
+            let raw = fs::read_to_string(path)?;
+            let fixture: Fixture = serde_yaml::from_str(&raw)
+                .with_context(|| format!("Invalid fixture {}", path.display()))?;
+            Case::from_fixture(fixture)
+        })
+        .collect()
+}
+
+/// Write a stored fetch as a fixture, labelling its bullets with the deduplicator's decisions
+/// at the time for a human to correct
+fn freeze_fetch(cfg: &Config, db: &Db, fetch_id: i64, dir: &Path) -> Result<PathBuf> {
+    let articles = db.load_articles_for_latest_fetch(&fetch_id)?;
+    if articles.is_empty() {
+        return Err(anyhow!("Fetch {fetch_id} has no stored articles"));
+    }
+    let fetched_at = db.fetch_time(fetch_id)?.unwrap_or_else(|| Utc::now().naive_utc());
+    let fixture = Fixture {
+        name: format!("fetch {fetch_id} of {}", fetched_at.format("%Y-%m-%d")),
+        articles: articles.into_iter()
+            .map(|a| FixtureArticle {
+                title: a.og_title,
+                url: a.url.to_string(),
+                source: a.source,
+                author: a.author,
+                published: a.published_time,
+                text: a.text,
+            })
+            .collect(),
+        history: db.fetch_published_bullets_before(Some(fetch_id), cfg.dedup.lookback())?
+            .into_iter()
+            .map(|b| HistoryBullet {
+                days_ago: b.published_at.map_or(0, |t| (fetched_at - t).num_days().max(0) as u32),
+                text: b.text,
+            })
+            .collect(),
+        labels: db.fetch_judged_bullets(fetch_id)?
+            .into_iter()
+            .map(|b| DedupLabel { text: b.text, duplicate: b.accepted == Some(false) })
+            .collect(),
+    };
+
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("fetch-{fetch_id}.yaml"));
+    if path.exists() {
+        return Err(anyhow!("{} already exists, not overwriting its labels", path.display()));
+    }
+    fs::write(&path, serde_yaml::to_string(&fixture)?)?;
+    Ok(path)
+}
+
+fn ratio(part: usize, whole: usize) -> String {
+    if whole == 0 {
+        "n/a".to_string()
+    } else {
+        format!("{}/{} ({:.0}%)", part, whole, 100.0 * part as f64 / whole as f64)
+    }
+}
+
+/// Metrics as a markdown table, one column per variant
+fn metrics_table(names: &[String], metrics: &[Metrics]) -> String {
+    let rows: Vec<(&str, Vec<String>)> = vec![
+        ("runs (failed)", metrics.iter().map(|m| format!("{} ({})", m.runs, m.failed_runs)).collect()),
+        ("bullets", metrics.iter().map(|m| m.bullets.to_string()).collect()),
+        ("bullets citing a source", metrics.iter().map(|m| ratio(m.cited_bullets, m.bullets)).collect()),
+        ("articles cited", metrics.iter().map(|m| ratio(m.cited_articles, m.articles)).collect()),
+        ("rejected by dedup", metrics.iter().map(|m| m.rejected.to_string()).collect()),
+        ("dedup agrees with labels", metrics.iter().map(|m| ratio(m.agreed, m.labels)).collect()),
+        ("grounding failures", metrics.iter().map(|m| m.ungrounded.to_string()).collect()),
+        ("tokens", metrics.iter().map(|m| m.tokens.to_string()).collect()),
+        ("cost", metrics.iter().map(|m| format!("${:.4}", m.cost_usd)).collect()),
+        ("model time", metrics.iter().map(|m| format!("{:.1}s", m.latency_ms as f64 / 1000.0)).collect()),
+        ("failed calls", metrics.iter().map(|m| m.failed_calls.to_string()).collect()),
+    ];
+
+    let label_width = rows.iter().map(|(l, _)| l.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = names.iter().enumerate()
+        .map(|(i, n)| rows.iter().map(|(_, cells)| cells[i].len()).chain([n.len()]).max().unwrap_or(0))
+        .collect();
+    let line = |label: &str, cells: &[String]| {
+        let cells: Vec<String> = cells.iter().zip(&widths).map(|(c, w)| format!("{c:>w$}")).collect();
+        format!("| {:<label_width$} | {} |\n", label, cells.join(" | "))
+    };
+
+    let mut table = line("", names);
+    table.push_str(&format!(
+        "|{}|{}|\n",
+        "-".repeat(label_width + 2),
+        widths.iter().map(|w| format!("{}:", "-".repeat(w + 1))).collect::<Vec<_>>().join("|")
+    ));
+    for (label, cells) in &rows {
+        table.push_str(&line(label, cells));
+    }
+    table
+}
+
+fn describe_run(name: &str, run: &Run) -> String {
+    let mut out = format!("### {name}\n\n");
+    for e in &run.errors {
+        out.push_str(&format!("Failed: {e}\n"));
+    }
+    if let Some(summary) = &run.summary {
+        for b in &summary.items {
+            let state = match (b.accepted, &b.reason) {
+                (Some(false), Some(reason)) => format!("rejected: {reason}"),
+                (Some(false), None) => "rejected".to_string(),
+                _ => "kept".to_string(),
+            };
+            let sources = b.sources.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(", ");
+            out.push_str(&format!("- [{state}] {} (sources: {})\n", b.text, if sources.is_empty() { "none" } else { &sources }));
+        }
+    }
+    let disagreements: Vec<_> = run.labelled.iter().filter(|(_, want, got)| *got != Some(*want)).collect();
+    if !disagreements.is_empty() {
+        out.push_str("\nDisagrees with the labels on:\n");
+        for (text, want, _) in disagreements {
+            out.push_str(&format!("- {} (labelled {})\n", text, if *want { "duplicate" } else { "new" }));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// `lfc eval`: run every variant over every case and report the metrics side by side, or
+/// with `freeze`, write that fetch to `fixtures` as a fixture to label
+pub async fn run_eval(
+    fetches: &[i64],
+    fixtures: Option<&Path>,
+    models: &[String],
+    variant_names: &[String],
+    out: Option<&Path>,
+    freeze: Option<i64>,
+) -> Result<()> {
+    init_logger()?;
+    let mut cfg = Config::get_user_config()?;
+    let mut db = Db::open(&cfg)?;
+
+    if let Some(fetch_id) = freeze {
+        let dir = fixtures.ok_or_else(|| anyhow!("--freeze needs --fixtures DIR to write to"))?;
+        let path = freeze_fetch(&cfg, &db, fetch_id, dir)?;
+        println!("Wrote {}. Check its labels before evaluating against it.", path.display());
+        return Ok(());
+    }
+
+    let mut cases = fetches.iter()
+        .map(|&id| Case::from_fetch(&cfg, &db, id))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(dir) = fixtures {
+        cases.extend(load_fixtures(dir)?);
+    }
+    if cases.is_empty() {
+        return Err(anyhow!("Nothing to evaluate, pass --fetch ID or --fixtures DIR"));
+    }
+    let variants = pick_variants(&cfg, models, variant_names)?;
+    if !apply_budget(&mut cfg, &db)? {
+        return Err(anyhow!("The monthly budget is spent, not running the eval"));
+    }
+
+    let names: Vec<String> = variants.iter().map(|v| v.name.clone()).collect();
+    let mut totals = vec![Metrics::default(); variants.len()];
+    let mut details = String::new();
+    for case in &cases {
+        info!("Evaluating {} ({} articles) with {} variants", case.name, case.articles.len(), variants.len());
+        let mut runs = vec![];
+        for (variant, total) in variants.iter().zip(&mut totals) {
+            let run = run_case(&variant_config(&cfg, variant), &mut db, case).await?;
+            for e in &run.errors {
+                error!("{} on {}: {}", variant.name, case.name, e);
+            }
+            total.add(&run.metrics);
+            runs.push(run);
+        }
+
+        details.push_str(&format!(
+            "## {} ({} articles, {} history bullets, {} labels)\n\n",
+            case.name, case.articles.len(), case.history.len(), case.labels.len()
+        ));
+        let metrics: Vec<Metrics> = runs.iter().map(|r| r.metrics.clone()).collect();
+        details.push_str(&metrics_table(&names, &metrics));
+        details.push('\n');
+        for (name, run) in names.iter().zip(&runs) {
+            details.push_str(&describe_run(name, run));
+        }
+    }
+
+    let overview = format!(
+        "# Eval {}\n\n{} cases, variants: {}\n\n{}",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        cases.len(),
+        variants.iter()
+            .map(|v| format!("{} ({})", v.name, v.model.as_deref().unwrap_or(&cfg.model)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        metrics_table(&names, &totals)
+    );
+    match out {
+        Some(path) => {
+            fs::write(path, format!("{overview}\n{details}"))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("{overview}\nFull report written to {}", path.display());
+        }
+        None => println!("{overview}\n{details}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_article;
+
+    #[test]
+    fn test_score_summary() {
+        let articles = vec![
+            test_article(Some(1), "Article 1", "/1", "Test", "Salah scored twice against Chelsea."),
+            test_article(Some(2), "Article 2", "/2", "Test", "Slot praised the defence."),
+        ];
+        let bullet = |text: &str, sources: Vec<i64>, accepted: bool| Bullet {
+            text: text.to_string(),
+            sources,
+            accepted: Some(accepted),
+            ..Default::default()
+        };
+        let summary = Summary {
+            items: vec![
+                bullet("Salah scored twice against Chelsea", vec![1], true),
+                bullet("Salah scored 3 against Arsenal", vec![1], true), // neither 3 nor Arsenal is in the source
+                bullet("Slot is happy", vec![], false),
+            ],
+            ..Default::default()
+        };
+
+        let mut m = Metrics { articles: articles.len(), ..Default::default() };
+        m.score_summary(&articles, &summary, &Squad::default());
+        assert_eq!((m.bullets, m.cited_bullets, m.cited_articles), (3, 2, 1));
+        assert_eq!((m.rejected, m.ungrounded), (1, 2));
+    }
+
+    #[test]
+    fn test_metrics_table() {
+        let a = Metrics { runs: 2, bullets: 10, cited_bullets: 9, labels: 4, agreed: 3, ..Default::default() };
+        let b = Metrics { runs: 2, failed_runs: 1, ..Default::default() };
+        let table = metrics_table(&["mini".to_string(), "nano".to_string()], &[a, b]);
+        let row = |label: &str| -> Vec<String> {
+            let line = table.lines().find(|l| l.split('|').nth(1).map(str::trim) == Some(label)).unwrap();
+            line.split('|').skip(2).map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect()
+        };
+        assert_eq!(row(""), vec!["mini", "nano"]);
+        assert_eq!(row("runs (failed)"), vec!["2 (0)", "2 (1)"]);
+        assert_eq!(row("bullets citing a source"), vec!["9/10 (90%)", "n/a"]);
+        assert_eq!(row("dedup agrees with labels"), vec!["3/4 (75%)", "n/a"]);
+    }
+}
@@ -0,0 +1,349 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use log::info;
+
+use crate::config::Config;
+use crate::content_budget::{similarity, title_words, weigh_stories};
+use crate::models::{Bullet, DuplicateOf, NewsArticle, Summary};
+
+const MIN_WORDS: usize = 6; // shorter "sentences" are captions, bylines and the like
+const MAX_WORDS: usize = 60;
+const MAX_SENTENCES: usize = 40; // per article, the rest is rarely about the story
+const DAMPING: f32 = 0.85;
+const ITERATIONS: usize = 30;
+const LEAD_BONUS: f32 = 0.5; // news writing puts the story first
+
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "also", "been", "before", "being", "could", "does", "from", "have", "into",
+    "just", "more", "over", "said", "says", "should", "than", "that", "their", "them", "then",
+    "there", "they", "this", "those", "very", "were", "what", "when", "where", "which", "while",
+    "will", "with", "would", "your", "liverpool",
+];
+
+/// A sentence of one of the articles, with its place in it
+struct Sentence {
+    article: usize,
+    position: usize,
+    text: String,
+}
+
+/// The sentences of `text` of a digestible length, split at ., ! and ? followed by a space
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut out = vec![];
+    for paragraph in text.lines() {
+        let mut current = String::new();
+        let mut chars = paragraph.chars().peekable();
+        while let Some(c) = chars.next() {
+            current.push(c);
+            if !matches!(c, '.' | '!' | '?') {
+                continue;
+            }
+            // a closing quote or bracket belongs to the sentence it ends
+            while let Some(&q) = chars.peek().filter(|q| matches!(q, '"' | '\'' | '”' | '’' | ')')) {
+                current.push(q);
+                chars.next();
+            }
+            if chars.peek().is_some_and(|c| c.is_whitespace()) {
+                out.push(current.trim().to_string());
+                current.clear();
+            }
+        }
+        if !current.trim().is_empty() {
+            out.push(current.trim().to_string());
+        }
+    }
+    out.retain(|s| (MIN_WORDS..=MAX_WORDS).contains(&s.split_whitespace().count()));
+    out
+}
+
+/// Lowercase words of `text` that say something
+fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|w| w.chars().count() >= 3 && !STOP_WORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// Unit-length TF-IDF vector of each document
+fn tf_idf(docs: &[Vec<String>]) -> Vec<HashMap<String, f32>> {
+    let mut df: HashMap<&str, usize> = HashMap::new();
+    for doc in docs {
+        for term in doc.iter().map(String::as_str).collect::<HashSet<_>>() {
+            *df.entry(term).or_default() += 1;
+        }
+    }
+    docs.iter()
+        .map(|doc| {
+            let mut v: HashMap<String, f32> = HashMap::new();
+            for term in doc {
+                *v.entry(term.clone()).or_default() += 1.0;
+            }
+            for (term, w) in v.iter_mut() {
+                *w *= (docs.len() as f32 / df[term.as_str()] as f32).ln() + 1.0;
+            }
+            let norm = v.values().map(|w| w * w).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                v.values_mut().for_each(|w| *w /= norm);
+            }
+            v
+        })
+        .collect()
+}
+
+fn dot(a: &HashMap<String, f32>, b: &HashMap<String, f32>) -> f32 {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small.iter().filter_map(|(t, w)| large.get(t).map(|x| w * x)).sum()
+}
+
+/// TextRank centrality of each vector on the graph weighted by cosine similarity
+fn textrank(vectors: &[HashMap<String, f32>]) -> Vec<f32> {
+    let n = vectors.len();
+    let mut edges: Vec<Vec<(usize, f32)>> = vec![vec![]; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let w = dot(&vectors[i], &vectors[j]);
+            if w > 0.0 {
+                edges[i].push((j, w));
+                edges[j].push((i, w));
+            }
+        }
+    }
+    let out: Vec<f32> = edges.iter().map(|e| e.iter().map(|(_, w)| w).sum()).collect();
+
+    let mut scores = vec![1.0; n];
+    for _ in 0..ITERATIONS {
+        scores = edges
+            .iter()
+            .map(|e| (1.0 - DAMPING) + DAMPING * e.iter().map(|&(j, w)| w / out[j] * scores[j]).sum::<f32>())
+            .collect();
+    }
+    scores
+}
+
+/// First five letters of the words of `text`, close enough to match "injured" to "injuries"
+fn stems(text: &str) -> HashSet<String> {
+    terms(text).into_iter().filter(|w| w.chars().count() >= 4).map(|w| w.chars().take(5).collect()).collect()
+}
+
+/// The configured category whose name and description share the most words with `text`
+fn guess_category(cfg: &Config, text: &str) -> Option<String> {
+    let words = stems(text);
+    cfg.categories
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, stems(&format!("{} {}", c.name, c.description)).intersection(&words).count()))
+        .filter(|&(_, n)| n > 0)
+        .max_by_key(|&(i, n)| (n, Reverse(i)))
+        .map(|(i, _)| cfg.categories[i].name.clone())
+}
+
+/// A digest without the LLM: one bullet per story, most important first, each the story's best
+/// headline and its most central sentence with a link. Sentences are ranked by TextRank over all
+/// of the day's articles with a bonus for coming early in their article, and articles telling
+/// the same story, as the content budget groups them, make one bullet.
+pub fn summarize_offline(cfg: &Config, articles: &[NewsArticle]) -> Summary {
+    let sentences: Vec<Sentence> = articles
+        .iter()
+        .enumerate()
+        .flat_map(|(article, a)| {
+            split_sentences(&a.text)
+                .into_iter()
+                .take(MAX_SENTENCES)
+                .enumerate()
+                .map(move |(position, text)| Sentence { article, position, text })
+        })
+        .collect();
+    let centrality = textrank(&tf_idf(&sentences.iter().map(|s| terms(&s.text)).collect::<Vec<_>>()));
+    let scores: Vec<f32> = sentences
+        .iter()
+        .zip(&centrality)
+        .map(|(s, c)| c * (1.0 + LEAD_BONUS / (s.position + 1) as f32))
+        .collect();
+    let best_of = |article: usize| {
+        (0..sentences.len()).filter(|&i| sentences[i].article == article).max_by(|&a, &b| scores[a].total_cmp(&scores[b]))
+    };
+
+    // each story is told by the weightiest of its articles
+    let weighed = weigh_stories(&cfg.content_budget, articles);
+    let mut stories: Vec<(usize, Vec<usize>)> = vec![];
+    for (i, &(_, _, of)) in weighed.iter().enumerate() {
+        let lead = of.unwrap_or(i);
+        match stories.iter_mut().find(|(l, _)| *l == lead) {
+            Some((_, members)) => members.push(i),
+            None => stories.push((lead, vec![i])),
+        }
+    }
+    let mut ranked: Vec<(usize, Vec<usize>, Option<usize>, f32)> = stories
+        .into_iter()
+        .map(|(lead, members)| {
+            // a lead without usable text borrows the best sentence of another telling
+            let key = best_of(lead).or_else(|| {
+                members.iter().filter_map(|&m| best_of(m)).max_by(|&a, &b| scores[a].total_cmp(&scores[b]))
+            });
+            let centrality = key.map(|k| scores[k]).unwrap_or(1.0 - DAMPING);
+            let score = weighed[lead].1 * (1.0 + (members.len() as f32).ln()) * centrality;
+            (lead, members, key, score)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.3.total_cmp(&a.3));
+    ranked.truncate(cfg.offline.max_bullets);
+
+    let top = ranked.first().map(|r| r.3).unwrap_or(0.0);
+    let items: Vec<Bullet> = ranked
+        .iter()
+        .map(|(lead, members, key, score)| {
+            let a = &articles[*lead];
+            let link = format!("({}: {})", a.source, a.url);
+            let key_text = key.map(|k| sentences[k].text.as_str());
+
+            // the best few sentences of the key sentence's article, in their order
+            let mut detail: Vec<usize> = match key {
+                Some(k) => (0..sentences.len()).filter(|&i| sentences[i].article == sentences[*k].article).collect(),
+                None => vec![],
+            };
+            detail.sort_by(|&x, &y| scores[y].total_cmp(&scores[x]));
+            detail.truncate(cfg.offline.detail_sentences.max(1));
+            detail.sort_by_key(|&i| sentences[i].position);
+            let mut detail_text: Vec<String> = detail.iter().map(|&i| sentences[i].text.clone()).collect();
+            let others: Vec<&str> = members.iter().filter(|&&m| m != *lead).map(|&m| articles[m].source.as_str()).collect();
+            if !others.is_empty() {
+                detail_text.push(format!("Also reported by {}.", others.join(", ")));
+            }
+
+            Bullet {
+                text: match key_text {
+                    Some(k) => format!("{}: {} {}", a.og_title, k, link),
+                    None => format!("{} {}", a.og_title, link),
+                },
+                headline: Some(format!("{} {}", a.og_title, link)),
+                detail: Some(format!("{} {}", a.og_title, link) + &detail_text.iter().map(|s| format!("\n{s}")).collect::<String>()),
+                category: guess_category(cfg, &format!("{} {}", a.og_title, key_text.unwrap_or_default())),
+                importance: Some(if top > 0.0 { (1.0 + 9.0 * score / top).round() as u8 } else { 5 }),
+                sources: members.iter().filter_map(|&m| articles[m].id).collect(),
+                ..Default::default()
+            }
+        })
+        .collect();
+    info!("Built an offline digest of {} stories from {} articles", items.len(), articles.len());
+
+    Summary {
+        mood: "Offline digest: the day's top stories in their own words, picked without the AI summarizer.".to_string(),
+        sentiment: None,
+        category_sentiment: vec![],
+        match_section: None,
+        transfer_changes: vec![],
+        availability_changes: vec![],
+        items,
+        date: Utc::now().date_naive(),
+    }
+}
+
+/// Words of a bullet without its links
+fn bullet_words(bullet: &Bullet) -> HashSet<String> {
+    title_words(&bullet.text.split_whitespace().filter(|w| !w.contains("://")).collect::<Vec<_>>().join(" "))
+}
+
+/// Judge an offline digest's candidates without the LLM. A candidate whose words overlap a
+/// published bullet's, or an earlier kept candidate's, by `offline.repeat_threshold` repeats it.
+pub fn deduplicate_offline(cfg: &Config, history: &[Bullet], candidates: &Summary) -> Summary {
+    let history_words: Vec<HashSet<String>> = history.iter().map(bullet_words).collect();
+    let mut kept: Vec<(usize, HashSet<String>)> = vec![];
+    let mut out = candidates.clone();
+
+    for (i, b) in out.items.iter_mut().enumerate() {
+        let words = bullet_words(b);
+        let best = history
+            .iter()
+            .zip(&history_words)
+            .map(|(h, w)| (h.id.map(DuplicateOf::Previous), similarity(&words, w)))
+            .chain(kept.iter().map(|(k, w)| (Some(DuplicateOf::Candidate(*k)), similarity(&words, w))))
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+        match best {
+            Some((of, overlap)) if overlap >= cfg.offline.repeat_threshold => {
+                b.accepted = Some(false);
+                b.duplicate_of = of;
+                b.reason = Some(format!("Word overlap {overlap:.2} is at or above the {:.2} threshold", cfg.offline.repeat_threshold));
+            }
+            _ => {
+                b.accepted = Some(true);
+                b.reason = Some(match best {
+                    Some((_, overlap)) => format!("Closest bullet has word overlap {overlap:.2}"),
+                    None => "Nothing to compare against".to_string(),
+                });
+                kept.push((i, words));
+            }
+        }
+    }
+    info!("Kept {} of {} offline bullets after comparing them with {} published ones", kept.len(), out.items.len(), history.len());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_article;
+
+    #[test]
+    fn test_split_sentences() {
+        let text = "Photo: PA\nSlot said: \"We were brilliant in the second half tonight.\" Salah then scored his 250th goal for the club. Great!";
+        assert_eq!(
+            split_sentences(text),
+            vec![
+                "Slot said: \"We were brilliant in the second half tonight.\"".to_string(),
+                "Salah then scored his 250th goal for the club.".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarize_offline() {
+        let cfg: Config = serde_yaml::from_str("model: gpt-5-mini").unwrap();
+        let articles = vec![
+            test_article(Some(1), "Konaté injury blow ahead of Arsenal clash", "/news/1", "thisisanfield",
+                "Ibrahima Konaté has been ruled out of Sunday's clash with Arsenal with a hamstring injury. \
+                 The defender picked up the injury in training on Thursday morning. \
+                 Slot confirmed the news in his pre-match press conference at the AXA Training Centre."),
+            test_article(Some(2), "Konaté ruled out of Arsenal clash with injury", "/news/2", "liverpoolecho",
+                "Liverpool will be without Ibrahima Konaté against Arsenal after a hamstring injury in training. \
+                 Joe Gomez is expected to start alongside Virgil van Dijk at the Emirates."),
+            test_article(Some(3), "Under-21s beat Everton in the mini derby", "/news/3", "liverpoolfc",
+                "Liverpool's under-21s won the mini derby against Everton at the Academy on Saturday afternoon."),
+        ];
+        let summary = summarize_offline(&cfg, &articles);
+
+        // the two Konaté articles make one story, the bigger one, led by the weightier telling
+        assert_eq!(summary.items.len(), 2);
+        let first = &summary.items[0];
+        assert_eq!(first.sources, vec![1, 2]);
+        assert_eq!(first.importance, Some(10));
+        assert_eq!(first.category.as_deref(), Some("injuries"));
+        assert!(first.text.starts_with("Konaté injury blow ahead of Arsenal clash: "));
+        assert!(first.text.contains("injury in training")); // the sentence both tellings share
+        assert!(first.text.ends_with("(thisisanfield: https://example.com/news/1)"));
+        assert!(first.detail.as_deref().unwrap().ends_with("Also reported by liverpoolecho."));
+        assert_eq!(summary.items[1].sources, vec![3]);
+    }
+
+    #[test]
+    fn test_deduplicate_offline() {
+        let cfg: Config = serde_yaml::from_str("model: gpt-5-mini").unwrap();
+        let bullet = |id: Option<i64>, text: &str| Bullet { id, text: text.to_string(), ..Default::default() };
+        let history = vec![bullet(Some(7), "Konaté ruled out of the Arsenal clash with a hamstring injury (liverpoolecho: https://example.com/a)")];
+        let candidates = Summary {
+            items: vec![
+                bullet(None, "Konaté hamstring injury rules him out of Arsenal clash (thisisanfield: https://example.com/b)"),
+                bullet(None, "Under-21s beat Everton in the mini derby at the Academy (liverpoolfc: https://example.com/c)"),
+                bullet(None, "Under-21s beat Everton in the Academy mini derby (liverpoolecho: https://example.com/d)"),
+            ],
+            ..Default::default()
+        };
+        let judged = deduplicate_offline(&cfg, &history, &candidates);
+
+        assert_eq!(judged.items[0].accepted, Some(false));
+        assert_eq!(judged.items[0].duplicate_of, Some(DuplicateOf::Previous(7)));
+        assert_eq!(judged.items[1].accepted, Some(true));
+        assert_eq!(judged.items[2].duplicate_of, Some(DuplicateOf::Candidate(1)));
+    }
+}
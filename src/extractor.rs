@@ -169,6 +169,7 @@ pub async fn extract_f365_article(client: &Client, url: &Url) -> Result<NewsArti
     info!("Successfully scraped football365 article: {}", url.clone());
 
     Ok(NewsArticle {
+        id: None,
         url: url.clone(),
         og_title,
         published_time,
@@ -356,6 +357,7 @@ pub async fn extract_thisisanfield_article(client: &Client, url: &Url) -> Result
     info!("Successfully scraped thisisanfield article: {}", url.clone());
 
     Ok(NewsArticle {
+        id: None,
         url: url.clone(),
         og_title,
         published_time,
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use anyhow::Result;
+use log::{debug, info};
+use tiktoken_rs::o200k_base;
+
+use crate::ai_summarizer::decode_first_n_tokens;
+use crate::config::{Config, FollowupMethod};
+use crate::db::Db;
+use crate::embeddings::{cosine_similarity, embed_texts};
+use crate::models::NewsArticle;
+
+const SHINGLE_WORDS: usize = 5; // words per fingerprinted sequence
+const EMBED_TOKENS: usize = 1000; // of each article's title and text
+
+/// A new article that only restates a published one
+#[derive(Debug, Clone)]
+pub struct Followup {
+    /// Position in the fetch's articles
+    pub index: usize,
+    /// Row id of the published article it restates
+    pub of: i64,
+    pub similarity: f32,
+}
+
+/// Hashes of every run of `SHINGLE_WORDS` words in the title and text
+fn fingerprint(article: &NewsArticle) -> HashSet<u64> {
+    let text = format!("{} {}", article.og_title, article.text).to_lowercase();
+    let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    words
+        .windows(SHINGLE_WORDS)
+        .map(|shingle| {
+            let mut hasher = DefaultHasher::new();
+            shingle.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+/// Share of `new`'s fingerprints found in `old`
+fn containment(new: &HashSet<u64>, old: &HashSet<u64>) -> f32 {
+    if new.is_empty() {
+        return 0.0;
+    }
+    new.intersection(old).count() as f32 / new.len() as f32
+}
+
+/// The published article each of `articles` is most similar to, with the similarity
+fn closest<T>(articles: &[T], published: &[T], similarity: impl Fn(&T, &T) -> f32) -> Vec<Option<(usize, f32)>> {
+    articles
+        .iter()
+        .map(|a| {
+            published
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (i, similarity(a, p)))
+                .max_by(|x, y| x.1.total_cmp(&y.1))
+        })
+        .collect()
+}
+
+/// Embeddings of `articles`, loading stored ones and storing the rest
+pub async fn embed_articles(cfg: &Config, db: &Db, articles: &[NewsArticle]) -> Result<Vec<Vec<f32>>> {
+    let model = &cfg.dedup.embedding_model;
+    let mut vectors: Vec<Option<Vec<f32>>> = Vec::with_capacity(articles.len());
+    for a in articles {
+        vectors.push(match a.id {
+            Some(id) => db.load_article_embedding(id, model)?,
+            None => None,
+        });
+    }
+
+    let missing: Vec<usize> = (0..articles.len()).filter(|&i| vectors[i].is_none()).collect();
+    if !missing.is_empty() {
+        debug!("Embedding {} articles", missing.len());
+        let bpe = o200k_base().unwrap();
+        let texts: Vec<String> = missing
+            .iter()
+            .map(|&i| decode_first_n_tokens(&bpe, &format!("{}\n\n{}", articles[i].og_title, articles[i].text), EMBED_TOKENS))
+            .collect();
+        for (&i, vector) in missing.iter().zip(embed_texts(cfg, &texts).await?) {
+            if let Some(id) = articles[i].id {
+                db.insert_article_embedding(id, model, &vector)?;
+            }
+            vectors[i] = Some(vector);
+        }
+    }
+    Ok(vectors.into_iter().map(Option::unwrap_or_default).collect())
+}
+
+/// The articles of `fetch_id` with nothing new over the articles behind bullets published in the
+/// `followups.lookback_days` before it
+pub async fn find_followups(cfg: &Config, db: &Db, fetch_id: i64, articles: &[NewsArticle]) -> Result<Vec<Followup>> {
+    let published = db.published_source_articles(fetch_id, cfg.followups.lookback_days)?;
+    if published.is_empty() || articles.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (matches, threshold) = match cfg.followups.method {
+        FollowupMethod::Fingerprint => {
+            let new: Vec<HashSet<u64>> = articles.iter().map(fingerprint).collect();
+            let old: Vec<HashSet<u64>> = published.iter().map(fingerprint).collect();
+            (closest(&new, &old, containment), cfg.followups.fingerprint_threshold)
+        }
+        FollowupMethod::Embedding => {
+            let new = embed_articles(cfg, db, articles).await?;
+            let old = embed_articles(cfg, db, &published).await?;
+            (closest(&new, &old, |a, b| cosine_similarity(a, b)), cfg.followups.embedding_threshold)
+        }
+    };
+
+    let followups: Vec<Followup> = matches
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, m)| {
+            let (p, similarity) = m?;
+            let of = published[p].id?;
+            (similarity >= threshold).then_some(Followup { index, of, similarity })
+        })
+        .collect();
+
+    for f in &followups {
+        info!(
+            "Follow-up ({:.2}): \"{}\" restates \"{}\"",
+            f.similarity,
+            articles[f.index].og_title,
+            published.iter().find(|p| p.id == Some(f.of)).map(|p| p.og_title.as_str()).unwrap_or_default()
+        );
+    }
+    Ok(followups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_article;
+
+    #[test]
+    fn test_fingerprint_containment() {
+        let story = "Mohamed Salah has signed a new contract that keeps him at Anfield until the summer of 2028, the club announced on Friday.";
+        let published = fingerprint(&test_article(None, "Salah signs new deal", "/a", "example", story));
+        let restated = fingerprint(&test_article(None, "Salah signs new deal", "/a", "example", &format!("{story} Fans reacted with delight.")));
+        let fresh = fingerprint(&test_article(None, "Alisson out for a month", "/a", "example", "Alisson Becker will miss the next six games with a hamstring injury picked up in training."));
+
+        assert!(containment(&restated, &published) > 0.7);
+        assert_eq!(containment(&fresh, &published), 0.0);
+        assert_eq!(containment(&HashSet::new(), &published), 0.0);
+    }
+}
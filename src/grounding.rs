@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use async_openai::types::{ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage};
+use log::{info, warn};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::json;
+use tiktoken_rs::o200k_base;
+
+use crate::ai_summarizer::decode_first_n_tokens;
+use crate::config::{Config, Unsupported};
+use crate::llm::{chat_json, JsonCall};
+use crate::models::{Bullet, BulletVariant, NewsArticle, Summary};
+use crate::squad::Squad;
+
+// Capitalized words the summarizer uses freely without them being in the articles
+const STOP_NAMES: &[&str] = &[
+    "liverpool", "lfc", "reds", "the", "anfield", "premier", "league", "champions", "cup",
+    "fa", "carabao", "europa", "uefa", "fifa", "england", "english", "i", "we", "our",
+];
+
+/// Numbers and capitalized names in `text` that none of `sources` mention, counting a name as
+/// mentioned when the squad knows it by another one the sources use
+pub fn lexical_check(text: &str, sources: &[&str], squad: &Squad) -> Vec<String> {
+    let numbers = Regex::new(r"\d+(?:[.,]\d+)*").unwrap();
+    let words = Regex::new(r"\p{Lu}[\p{L}'’-]+").unwrap();
+    let haystack = sources.join("\n").to_lowercase();
+    let mut missing = vec![];
+
+    for m in numbers.find_iter(text) {
+        if !haystack.contains(m.as_str()) && !missing.iter().any(|s| s == m.as_str()) {
+            missing.push(m.as_str().to_string());
+        }
+    }
+
+    for m in words.find_iter(text) {
+        // the first word of a sentence is capitalized whatever it is
+        let before = text[..m.start()].trim_end();
+        if before.is_empty() || before.ends_with(['.', '!', '?', ':']) {
+            continue;
+        }
+        let word = m.as_str().trim_end_matches(['\'', '’', '-']).trim_end_matches("'s").trim_end_matches("’s");
+        let lower = word.to_lowercase();
+        if STOP_NAMES.contains(&lower.as_str()) || haystack.contains(&lower) || squad.known_alias(word, &haystack) {
+            continue;
+        }
+        if !missing.iter().any(|s| s == word) {
+            missing.push(word.to_string());
+        }
+    }
+
+    missing
+}
+
+#[derive(Debug, Deserialize)]
+struct GroundingVerdict {
+    index: usize,
+    supported: bool,
+    unsupported_claims: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GroundingResponse {
+    results: Vec<GroundingVerdict>,
+}
+
+/// Check each accepted, not yet checked bullet, in every variant it was written in, against the
+/// articles it cites (or all of `articles` when it cites none). Numbers and names are checked
+/// lexically, then the chat model judges the claims with the lexical misses as hints; if that
+/// call fails the lexical result stands. A bullet with an unsupported claim in any variant is
+/// flagged, or rejected when `grounding.action` is drop, whichever variant its readers get.
+pub async fn ground_summary(cfg: &Config, articles: &[NewsArticle], summary: &Summary) -> Result<Summary> {
+    let by_id: HashMap<i64, &NewsArticle> = articles.iter().filter_map(|a| a.id.map(|id| (id, a))).collect();
+    let sources_of = |b: &Bullet| -> Vec<&NewsArticle> {
+        let cited: Vec<&NewsArticle> = b.sources.iter().filter_map(|id| by_id.get(id).copied()).collect();
+        if cited.is_empty() { articles.iter().collect() } else { cited }
+    };
+
+    let mut summary = summary.clone();
+    let pending: Vec<usize> = summary.items.iter()
+        .enumerate()
+        .filter(|(_, b)| b.accepted == Some(true) && b.grounded.is_none())
+        .map(|(i, _)| i)
+        .collect();
+    if pending.is_empty() {
+        return Ok(summary);
+    }
+
+    // 1) Lexical check of every variant a recipient may get, noting loanees and departed
+    // players for the LLM check
+    let squad = Squad::load(cfg)?;
+    let mut verdicts: HashMap<usize, (bool, Vec<String>)> = HashMap::new();
+    for &i in &pending {
+        let sources = sources_of(&summary.items[i]);
+        let texts: Vec<&str> = sources.iter().flat_map(|a| [a.og_title.as_str(), a.text.as_str()]).collect();
+        let mut supported = true;
+        let mut notes: Vec<String> = vec![];
+        for (variant, text) in summary.items[i].variant_texts() {
+            let missing = lexical_check(text, &texts, &squad);
+            supported &= missing.is_empty();
+            let label = variant_label(variant);
+            for note in missing.iter().map(|m| format!("`{m}` is not in the sources{label}")).chain(squad.name_notes(text)) {
+                if !notes.contains(&note) {
+                    notes.push(note);
+                }
+            }
+        }
+        verdicts.insert(i, (supported, notes));
+    }
+
+    // 2) LLM check, with the lexical misses as hints
+    if cfg.grounding.llm_check {
+        match ai_ground(cfg, &summary.items, &pending, &sources_of, &verdicts).await {
+            Ok(llm_verdicts) => {
+                for v in llm_verdicts {
+                    if let Some(&i) = pending.get(v.index) {
+                        verdicts.insert(i, (v.supported, v.unsupported_claims));
+                    }
+                }
+            }
+            Err(e) => warn!("LLM grounding check failed, keeping the lexical results: {e:?}"),
+        }
+    }
+
+    // 3) Flag or drop
+    let mut unsupported = 0;
+    for (i, (supported, notes)) in verdicts {
+        let bullet = &mut summary.items[i];
+        bullet.grounded = Some(supported);
+        if supported {
+            continue;
+        }
+        unsupported += 1;
+        let notes = if notes.is_empty() { "no details given".to_string() } else { notes.join("; ") };
+        if cfg.grounding.action == Unsupported::Drop {
+            bullet.accepted = Some(false);
+            bullet.reason = Some(format!("Not supported by its sources: {notes}"));
+        }
+        bullet.grounding_notes = Some(notes);
+    }
+    info!("Grounding found {} of {} bullets unsupported by their sources", unsupported, pending.len());
+
+    Ok(summary)
+}
+
+/// Names the variant a lexical miss was found in, the standard text goes unnamed
+fn variant_label(variant: BulletVariant) -> &'static str {
+    match variant {
+        BulletVariant::Standard => "",
+        BulletVariant::Headline => " (headline)",
+        BulletVariant::Detailed => " (detailed version)",
+    }
+}
+
+async fn ai_ground<'a>(
+    cfg: &Config,
+    items: &[Bullet],
+    pending: &[usize],
+    sources_of: &dyn Fn(&Bullet) -> Vec<&'a NewsArticle>,
+    lexical: &HashMap<usize, (bool, Vec<String>)>,
+) -> Result<Vec<GroundingVerdict>> {
+    let bpe = o200k_base().unwrap();
+
+    // every cited article once, labelled by position
+    let mut shown: Vec<&NewsArticle> = vec![];
+    let mut bullet_lines = vec![];
+    for (n, &i) in pending.iter().enumerate() {
+        let mut labels = vec![];
+        for a in sources_of(&items[i]) {
+            let pos = match shown.iter().position(|s| std::ptr::eq(*s, a)) {
+                Some(pos) => pos,
+                None => {
+                    shown.push(a);
+                    shown.len() - 1
+                }
+            };
+            labels.push(format!("[{pos}]"));
+        }
+        let mut line = format!("{}. {} (sources: {})", n, items[i].text, labels.join(", "));
+        for (variant, text) in items[i].variant_texts().skip(1) {
+            line.push_str(&format!("\n   {}: {}", if variant == BulletVariant::Headline { "Headline" } else { "Detailed" }, text));
+        }
+        if let Some((_, notes)) = lexical.get(&i).filter(|(_, notes)| !notes.is_empty()) {
+            line.push_str(&format!("\n   Hint: {}", notes.join("; ")));
+        }
+        bullet_lines.push(line);
+    }
+
+    let articles_text = shown.iter()
+        .enumerate()
+        .map(|(pos, a)| format!(
+            "[{}] {}\n\n{}",
+            pos,
+            a.og_title,
+            decode_first_n_tokens(&bpe, &a.text, cfg.grounding.max_source_tokens)
+        ))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let system_prompt = r#"
+You are a fact checker for a Liverpool FC news digest.
+
+You are given numbered source articles and numbered bullet points, each listing the articles it was written from.
+
+A bullet may also come in a headline and a detailed version, listed under it. Readers get one of the versions, so judge them together: a claim in any version counts as a claim of the bullet.
+
+For each bullet, decide whether its sources support it:
+  - supported: true  → every factual claim (names, numbers, fees, dates, scores, quotes) is stated in or follows directly from its sources
+  - supported: false → it states something its sources don't say or contradict
+
+Tone, opinion and emojis don't need support. A hint lists names or numbers that a simple text search couldn't find in the sources; they may still be supported if written differently. It also lists players named in the bullet who are on loan or have left the club: a bullet that presents them as current Liverpool players is not supported.
+
+For every bullet also list unsupported_claims: the specific claims the sources don't support, or an empty list.
+
+Respond only with a JSON object listing every bullet index with its verdict.
+"#;
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "results": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "index": { "type": "integer" },
+                        "supported": { "type": "boolean" },
+                        "unsupported_claims": { "type": "array", "items": { "type": "string" } }
+                    },
+                    "required": ["index", "supported", "unsupported_claims"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["results"],
+        "additionalProperties": false
+    });
+
+    let user_prompt = format!("SOURCE ARTICLES:\n{}\n\nBULLETS:\n{}", articles_text, bullet_lines.join("\n"));
+
+    let parsed: GroundingResponse = chat_json(cfg, JsonCall {
+        step: "grounding",
+        schema_name: "grounding_check",
+        schema,
+        messages: vec![
+            ChatCompletionRequestSystemMessage::from(system_prompt).into(),
+            ChatCompletionRequestUserMessage::from(user_prompt).into(),
+        ],
+        max_completion_tokens: 3000,
+    }).await?;
+
+    Ok(parsed.results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_article;
+
+    #[test]
+    fn test_lexical_check() {
+        let sources = ["Liverpool have agreed a £45m fee with Bournemouth for Milos Kerkez, 21."];
+
+        // everything checkable is in the source
+        let squad = Squad::default();
+        assert!(lexical_check("Reds agree £45m deal for Kerkez 🔴", &sources, &squad).is_empty());
+
+        // a wrong fee and an unknown name are both reported
+        assert_eq!(
+            lexical_check("Liverpool agree £50m deal for Kerkez and Frimpong", &sources, &squad),
+            vec!["50".to_string(), "Frimpong".to_string()]
+        );
+
+        // the first word of a sentence is not treated as a name
+        assert!(lexical_check("Huge news. Kerkez is coming.", &sources, &squad).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ground_summary_checks_every_variant() {
+        let cfg: Config = serde_yaml::from_str(
+            "model: gpt-5-mini\ngrounding: {enabled: true, llm_check: false, action: drop}\nsquad: {path: /nonexistent/squad.yaml}",
+        ).unwrap();
+        let articles = vec![test_article(Some(1), "Kerkez deal agreed", "/news/1", "thisisanfield",
+            "Liverpool have agreed a £45m fee with Bournemouth for Milos Kerkez, 21.")];
+        let bullet = |detail: &str| Bullet {
+            text: "Reds agree £45m deal for Kerkez".to_string(),
+            headline: Some("Kerkez deal agreed".to_string()),
+            detail: Some(detail.to_string()),
+            accepted: Some(true),
+            sources: vec![1],
+            ..Default::default()
+        };
+        let summary = Summary {
+            items: vec![
+                bullet("Liverpool have agreed a £45m fee with Bournemouth for Kerkez."),
+                bullet("Liverpool have agreed a £50m fee with Bournemouth for Kerkez."),
+            ],
+            ..Default::default()
+        };
+        let grounded = ground_summary(&cfg, &articles, &summary).await.unwrap();
+
+        assert_eq!(grounded.items[0].grounded, Some(true));
+        // a wrong fee only in the detailed version drops the whole bullet
+        assert_eq!(grounded.items[1].grounded, Some(false));
+        assert_eq!(grounded.items[1].accepted, Some(false));
+        assert_eq!(grounded.items[1].grounding_notes.as_deref(), Some("`50` is not in the sources (detailed version)"));
+    }
+}
@@ -0,0 +1,273 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow};
+use async_openai::{
+    Client, config::OpenAIConfig, error::OpenAIError, types::{
+        ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage, ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs, ReasoningEffort, ResponseFormat, ResponseFormatJsonSchema, ServiceTier
+    }
+};
+use serde::de::DeserializeOwned;
+use log::{debug, info, warn};
+
+use crate::config::Config;
+use crate::costs::call_cost;
+
+const MAX_BACKOFF_SECS: u64 = 300; // however many retries are configured
+
+/// One attempt at a chat completion, successful or not
+#[derive(Debug, Clone)]
+pub struct LlmCall {
+    pub step: String,
+    pub model: String,
+    pub flex: bool,
+    pub attempt: u32, // 1-based, counted across the whole fallback chain
+    pub succeeded: bool,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>, // includes reasoning tokens
+    pub reasoning_tokens: Option<u32>,
+    pub cost_usd: Option<f64>,
+}
+
+// Attempts made since the last drain. The app drains it after each step and stores the
+// calls against the current fetch, so the AI functions don't need a DB handle.
+static CALL_LOG: Mutex<Vec<LlmCall>> = Mutex::new(Vec::new());
+
+pub fn drain_call_log() -> Vec<LlmCall> {
+    CALL_LOG.lock().map(|mut log| std::mem::take(&mut *log)).unwrap_or_default()
+}
+
+pub fn record(call: LlmCall) {
+    if let Ok(mut log) = CALL_LOG.lock() {
+        log.push(call);
+    }
+}
+
+/// A chat completion whose reply must match `schema`
+pub struct JsonCall<'a> {
+    pub step: &'a str,
+    pub schema_name: &'a str,
+    pub schema: serde_json::Value,
+    pub messages: Vec<ChatCompletionRequestMessage>,
+    pub max_completion_tokens: u32,
+}
+
+enum Failure {
+    /// Worth trying the same model again
+    Retryable(String),
+    /// The reply didn't parse; ask again with the parse error
+    BadJson { content: String, error: String },
+    /// This model won't work, move down the chain
+    Fatal(String),
+}
+
+fn classify(err: OpenAIError) -> Failure {
+    match err {
+        OpenAIError::Reqwest(e) => Failure::Retryable(format!("http error: {e}")),
+        OpenAIError::JSONDeserialize(e) => Failure::Retryable(format!("malformed API response: {e}")),
+        OpenAIError::ApiError(e) => {
+            let rate_limited = e.code.as_deref() == Some("rate_limit_exceeded")
+                || e.r#type.as_deref().is_some_and(|t| t.contains("rate_limit") || t == "tokens" || t == "requests");
+            let server_error = e.r#type.as_deref() == Some("server_error")
+                // 5xx bodies aren't guaranteed to be JSON, so they arrive without a type or code
+                || (e.r#type.is_none() && e.code.is_none());
+            if rate_limited || server_error {
+                Failure::Retryable(e.to_string())
+            } else {
+                Failure::Fatal(e.to_string())
+            }
+        }
+        other => Failure::Fatal(other.to_string()),
+    }
+}
+
+/// The message of `err` and whether it is worth trying the same model again
+pub fn retryable(err: OpenAIError) -> (String, bool) {
+    match classify(err) {
+        Failure::Retryable(e) => (e, true),
+        Failure::Fatal(e) | Failure::BadJson { error: e, .. } => (e, false),
+    }
+}
+
+/// A client whose retries are ours alone, the client's own backoff would outlive our timeout
+pub fn openai_client(cfg: &Config) -> Result<Client<OpenAIConfig>> {
+    let no_backoff = backoff::ExponentialBackoff {
+        max_elapsed_time: Some(Duration::ZERO),
+        ..Default::default()
+    };
+    Ok(Client::with_config(OpenAIConfig::default().with_api_key(cfg.openai_key()?)).with_backoff(no_backoff))
+}
+
+/// How long to wait before the `retry`th retry (1-based) of a call
+pub fn backoff_delay(cfg: &Config, retry: u32) -> Duration {
+    Duration::from_secs(cfg.llm.backoff_secs.saturating_mul(2u64.saturating_pow(retry - 1)).min(MAX_BACKOFF_SECS))
+}
+
+/// Run a JSON-schema chat completion with the configured retry policy.
+///
+/// Every model in the chain (`model` on Flex, then `llm.fallback`) gets `llm.max_retries`
+/// retries with exponential backoff on timeouts, rate limits and server errors. A reply that
+/// doesn't parse as `T` is sent back to the model with the parse error. Every attempt is
+/// recorded in the call log.
+pub async fn chat_json<T: DeserializeOwned>(cfg: &Config, call: JsonCall<'_>) -> Result<T> {
+    chat_json_validated(cfg, call, |_: &T| Ok(())).await
+}
+
+/// Like [`chat_json`], but a reply that parses and fails `validate` is also sent back to the model
+pub async fn chat_json_validated<T: DeserializeOwned>(
+    cfg: &Config,
+    call: JsonCall<'_>,
+    validate: impl Fn(&T) -> std::result::Result<(), String>,
+) -> Result<T> {
+    let client = openai_client(cfg)?;
+
+    let timeout = Duration::from_secs(cfg.llm.timeout_secs);
+    let mut attempt = 0;
+    let mut last_error = String::new();
+
+    for (model, flex) in cfg.llm.chain(&cfg.model) {
+        let mut messages = call.messages.clone();
+
+        for retry in 0..=cfg.llm.max_retries {
+            attempt += 1;
+            if retry > 0 {
+                let delay = backoff_delay(cfg, retry);
+                debug!("Waiting {:?} before retry {} of {} on {}", delay, retry, call.step, model);
+                tokio::time::sleep(delay).await;
+            }
+
+            let mut args = CreateChatCompletionRequestArgs::default();
+            args.model(&model)
+                .messages(messages.clone())
+                .response_format(ResponseFormat::JsonSchema {
+                    json_schema: ResponseFormatJsonSchema {
+                        description: None,
+                        name: call.schema_name.to_string(),
+                        schema: Some(call.schema.clone()),
+                        strict: Some(true),
+                    },
+                })
+                .max_completion_tokens(call.max_completion_tokens)
+                .reasoning_effort(ReasoningEffort::Low);
+            if flex {
+                args.service_tier(ServiceTier::Flex);
+            }
+            let request = args.build().context("Failed to build OpenAI request")?;
+
+            debug!("{} attempt {} on {} (flex: {}) with {:?} timeout", call.step, attempt, model, flex, timeout);
+            let start_time = Instant::now();
+            let result = tokio::time::timeout(timeout, client.chat().create(request)).await;
+            let latency_ms = start_time.elapsed().as_millis() as u64;
+
+            // a reply that fails to parse is still billed, so usage is kept for every outcome
+            let usage = result.as_ref().ok().and_then(|r| r.as_ref().ok()).and_then(|r| r.usage.clone());
+            let prompt_tokens = usage.as_ref().map(|u| u.prompt_tokens);
+            let completion_tokens = usage.as_ref().map(|u| u.completion_tokens);
+            let reasoning_tokens = usage.as_ref()
+                .and_then(|u| u.completion_tokens_details.as_ref())
+                .and_then(|d| d.reasoning_tokens);
+            let cost_usd = usage.as_ref()
+                .and_then(|u| call_cost(cfg, &model, flex, u.prompt_tokens, u.completion_tokens));
+
+            let failure = match result {
+                Err(_) => Failure::Retryable(format!("timed out after {} seconds", cfg.llm.timeout_secs)),
+                Ok(Err(e)) => classify(e),
+                Ok(Ok(response)) => {
+                    debug!("Here is the raw response: {:#?}", response);
+                    match response.choices.into_iter().find_map(|c| c.message.content) {
+                        None => Failure::Retryable("no content in response".to_string()),
+                        Some(content) => match serde_json::from_str::<T>(&content)
+                            .map_err(|e| e.to_string())
+                            .and_then(|value| validate(&value).map(|_| value))
+                        {
+                            Ok(value) => {
+                                record(LlmCall {
+                                    step: call.step.to_string(),
+                                    model: model.clone(),
+                                    flex,
+                                    attempt,
+                                    succeeded: true,
+                                    error: None,
+                                    latency_ms,
+                                    prompt_tokens,
+                                    completion_tokens,
+                                    reasoning_tokens,
+                                    cost_usd,
+                                });
+                                if attempt > 1 {
+                                    info!("{} succeeded on attempt {} with {} (flex: {})", call.step, attempt, model, flex);
+                                }
+                                return Ok(value);
+                            }
+                            Err(error) => Failure::BadJson { content, error },
+                        },
+                    }
+                }
+            };
+
+            let (error, fatal) = match failure {
+                Failure::Retryable(e) => (e, false),
+                Failure::Fatal(e) => (e, true),
+                Failure::BadJson { content, error } => {
+                    // re-ask on the next attempt with the model's own reply and what was wrong with it
+                    messages.push(ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage::from(content)));
+                    messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage::from(format!(
+                        "That reply did not match the required JSON schema ({error}). Reply again with only valid JSON."
+                    ))));
+                    (format!("invalid JSON: {error}"), false)
+                }
+            };
+
+            warn!("{} attempt {} on {} (flex: {}) failed: {}", call.step, attempt, model, flex, error);
+            record(LlmCall {
+                step: call.step.to_string(),
+                model: model.clone(),
+                flex,
+                attempt,
+                succeeded: false,
+                error: Some(error.clone()),
+                latency_ms,
+                prompt_tokens,
+                completion_tokens,
+                reasoning_tokens,
+                cost_usd,
+            });
+            last_error = error;
+
+            if fatal {
+                break;
+            }
+        }
+    }
+
+    Err(anyhow!("{} failed after {} attempts, last error: {}", call.step, attempt, last_error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::error::ApiError;
+
+    fn api_error(r#type: Option<&str>, code: Option<&str>) -> OpenAIError {
+        OpenAIError::ApiError(ApiError {
+            message: "boom".to_string(),
+            r#type: r#type.map(str::to_string),
+            param: None,
+            code: code.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn test_classify() {
+        // rate limits and server errors are retried on the same model
+        assert!(matches!(classify(api_error(Some("tokens"), Some("rate_limit_exceeded"))), Failure::Retryable(_)));
+        assert!(matches!(classify(api_error(Some("server_error"), None)), Failure::Retryable(_)));
+        assert!(matches!(classify(api_error(None, None)), Failure::Retryable(_)));
+
+        // anything else moves down the fallback chain
+        assert!(matches!(classify(api_error(Some("insufficient_quota"), Some("insufficient_quota"))), Failure::Fatal(_)));
+        assert!(matches!(classify(api_error(Some("invalid_request_error"), Some("model_not_found"))), Failure::Fatal(_)));
+    }
+}
@@ -3,16 +3,40 @@ mod config;
 mod db;
 mod models;
 mod extractor;
+mod llm;
+mod costs;
 mod ai_summarizer;
+mod content_budget;
 mod ai_deduplicator;
+mod embeddings;
+mod embedding_deduplicator;
+mod entities;
+mod followups;
+mod extractive;
 mod utils;
+mod ranking;
+mod grounding;
 mod email;
 mod telegram;
 mod calendar;
+mod match_day;
+mod transfers;
+mod availability;
 mod logger;
+mod reports;
+mod rollup;
+mod ask;
+mod eval;
+mod regenerate;
+mod runs;
+mod squad;
+mod stories;
+
+use std::path::PathBuf;
 
 use anyhow::Result;
-use clap::Parser;
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(name = "lfc")]
@@ -22,6 +46,10 @@ struct Cli {
     #[arg(long)]
     no_ai: bool,
 
+    /// Send an extractive digest of the articles' own sentences instead of the AI summary
+    #[arg(long)]
+    offline: bool,
+
     /// Skip email notifications
     #[arg(long)]
     no_email: bool,
@@ -29,10 +57,222 @@ struct Cli {
     /// Skip telegram notifications
     #[arg(long)]
     no_telegram: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show rejected bullets side by side with the bullets they duplicate
+    Rejected {
+        /// Fetch to report on (defaults to the latest summarized fetch)
+        #[arg(long)]
+        fetch_id: Option<i64>,
+    },
+    /// Show LLM token usage and cost
+    Costs {
+        /// Group by day or month
+        #[arg(long, value_enum, default_value_t = reports::Period::Day)]
+        by: reports::Period,
+    },
+    /// Send a recap of the daily digests of the past week or month
+    Rollup {
+        #[arg(value_enum)]
+        period: rollup::RollupPeriod,
+        /// First day to cover (defaults to a week ago, or the first of last month)
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Last day to cover (defaults to yesterday, or the end of last month)
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// List tracked transfer rumours with their history and sources
+    Transfers {
+        /// Include done and collapsed deals
+        #[arg(long)]
+        all: bool,
+    },
+    /// List injured, doubtful and suspended players
+    Availability {
+        /// Include players tracked as available
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show how the sentiment of the summaries changes over time
+    Mood {
+        /// Only the last N days (defaults to all history)
+        #[arg(long)]
+        days: Option<u32>,
+        /// Also write the series, with per-category scores, to this CSV file
+        #[arg(long)]
+        csv: Option<PathBuf>,
+        /// Also draw the series as a PNG chart at this path
+        #[arg(long)]
+        png: Option<PathBuf>,
+    },
+    /// Show who the articles and digests mention most, and who is trending up or down
+    Mentions {
+        /// Length of the window, compared with the one before it
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+        /// Only players, clubs, competitions or staff
+        #[arg(long, value_enum)]
+        kind: Option<entities::EntityKind>,
+        /// Most rows to list
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Tag every stored article and bullet again first, e.g. after updating the squad
+        #[arg(long)]
+        retag: bool,
+    },
+    /// Full-text search of stored articles and digest bullets
+    Search {
+        /// Words, "exact phrases", prefix* and AND/OR/NOT
+        query: String,
+        /// Published on or after this day
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Published on or before this day
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Only articles from sources whose name contains this
+        #[arg(long)]
+        source: Option<String>,
+        /// Only articles whose byline contains this
+        #[arg(long)]
+        author: Option<String>,
+        /// Most results of each kind
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Answer a question from the stored articles and digests, citing its sources
+    Ask {
+        /// e.g. "what did Slot say about Gravenberch this month?"
+        question: String,
+    },
+    /// Run the Telegram bot, answering /ask <question> in the configured chats
+    Bot,
+    /// Summarize, deduplicate and deliver a stored fetch again
+    Regenerate {
+        /// Fetch to regenerate
+        #[arg(long, conflicts_with = "date", required_unless_present = "date")]
+        fetch_id: Option<i64>,
+        /// Regenerate the last fetch of this day (UTC)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// Keep the old summary as a superseded version, or replace it
+        #[arg(long, value_enum, default_value_t = regenerate::RegenerateMode::Version)]
+        mode: regenerate::RegenerateMode,
+        /// Send the digest again even if the old one was sent
+        #[arg(long)]
+        resend: bool,
+    },
+    /// List recent story threads, or print one's timeline of articles and digest bullets
+    Story {
+        /// Story to print (lists the recent ones without)
+        id: Option<i64>,
+        /// List the stories updated in the last N days
+        #[arg(long, default_value_t = 30)]
+        days: u32,
+        /// Build every story again from the stored articles and bullets first
+        #[arg(long)]
+        rebuild: bool,
+    },
+    /// Show or update the squad, staff and loanee list the prompts and grounding use
+    Squad {
+        #[command(subcommand)]
+        action: Option<SquadCommand>,
+    },
+    /// Compare models or prompt versions on stored fetches or fixtures
+    Eval {
+        /// Stored fetch to run on (repeatable)
+        #[arg(long = "fetch")]
+        fetches: Vec<i64>,
+        /// Directory of fixture files (*.yaml) to run on, or to write --freeze to
+        #[arg(long)]
+        fixtures: Option<PathBuf>,
+        /// Compare these models with the configured prompts, instead of `eval.variants`
+        #[arg(long = "model")]
+        models: Vec<String>,
+        /// Only run these of `eval.variants`
+        #[arg(long = "variant")]
+        variants: Vec<String>,
+        /// Write the full report to this file and print only the totals
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Write this stored fetch to --fixtures as a fixture to label, instead of evaluating
+        #[arg(long)]
+        freeze: Option<i64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SquadCommand {
+    /// List the squad file (the default)
+    Show,
+    /// Add a member or change one, e.g. after a signing, sale or loan
+    Set {
+        /// Full name, e.g. "Dominik Szoboszlai"
+        name: String,
+        /// Required for a new member
+        #[arg(long, value_enum)]
+        status: Option<squad::Status>,
+        /// Position for players, job for staff
+        #[arg(long)]
+        role: Option<String>,
+        /// Shirt number
+        #[arg(long)]
+        number: Option<u8>,
+        /// What the press calls them, if not the last word of the name
+        #[arg(long)]
+        short: Option<String>,
+        /// Another name they go by (repeatable)
+        #[arg(long = "nickname")]
+        nicknames: Vec<String>,
+        /// A wrong spelling to correct in bullets (repeatable)
+        #[arg(long = "misspelling")]
+        misspellings: Vec<String>,
+        /// e.g. "on loan at Bournemouth until June" or "joined Real Madrid"
+        #[arg(long)]
+        note: Option<String>,
+    },
+    /// Take a member off the list altogether
+    Remove {
+        name: String,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    app::run_scraper(cli.no_ai, cli.no_email, cli.no_telegram).await
+    match cli.command {
+        None => app::run_scraper(cli.no_ai, cli.offline, cli.no_email, cli.no_telegram).await,
+        Some(Command::Rejected { fetch_id }) => reports::print_rejected_bullets(fetch_id),
+        Some(Command::Costs { by }) => reports::print_costs(by),
+        Some(Command::Rollup { period, from, to }) => rollup::run_rollup(period, from, to, cli.no_email, cli.no_telegram).await,
+        Some(Command::Transfers { all }) => reports::print_transfers(all),
+        Some(Command::Availability { all }) => reports::print_availability(all),
+        Some(Command::Mood { days, csv, png }) => reports::print_mood(days, csv.as_deref(), png.as_deref()),
+        Some(Command::Mentions { days, kind, limit, retag }) => reports::print_mentions(days, kind, limit, retag),
+        Some(Command::Search { query, from, to, source, author, limit }) => {
+            reports::print_search(&query, &models::SearchFilters { from, to, source, author }, limit)
+        }
+        Some(Command::Ask { question }) => ask::run_ask(&question).await,
+        Some(Command::Bot) => ask::run_bot().await,
+        Some(Command::Regenerate { fetch_id, date, mode, resend }) => {
+            regenerate::run_regenerate(fetch_id, date, mode, resend, cli.no_email, cli.no_telegram).await
+        }
+        Some(Command::Story { id, days, rebuild }) => stories::run_story(id, days, rebuild).await,
+        Some(Command::Squad { action }) => match action.unwrap_or(SquadCommand::Show) {
+            SquadCommand::Show => squad::print_squad(),
+            SquadCommand::Set { name, status, role, number, short, nicknames, misspellings, note } => {
+                squad::set_member(&name, squad::MemberUpdate { status, role, number, short, nicknames, misspellings, note })
+            }
+            SquadCommand::Remove { name } => squad::remove_member(&name),
+        },
+        Some(Command::Eval { fetches, fixtures, models, variants, out, freeze }) => {
+            eval::run_eval(&fetches, fixtures.as_deref(), &models, &variants, out.as_deref(), freeze).await
+        }
+    }
 }
@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use async_openai::types::{ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage};
+use log::{debug, info};
+use serde::Deserialize;
+use serde_json::json;
+use tiktoken_rs::o200k_base;
+
+use crate::availability::current_availability;
+use crate::ai_summarizer::decode_first_n_tokens;
+use crate::calendar::{Fixture, MatchMode};
+use crate::config::Config;
+use crate::db::Db;
+use crate::llm::{chat_json, JsonCall};
+use crate::models::{MatchPreview, MatchReport, MatchSection, NewsArticle, PlayerRating};
+
+// title words that mark an article as being about the game itself
+const PREVIEW_WORDS: &[&str] = &["preview", "team news", "line-up", "lineup", "predicted", "injury", "fitness", "press conference"];
+const REPORT_WORDS: &[&str] = &["report", "ratings", "reaction", "verdict", "talking points", "player ratings", "what we learned"];
+
+/// Build the preview (match day) or report (the day after) for `mode` from today's articles
+/// and, for context, archived articles mentioning the opponent
+pub async fn build_match_section(cfg: &Config, db: &Db, mode: &MatchMode, articles: &[NewsArticle]) -> Result<MatchSection> {
+    match mode {
+        MatchMode::PreMatch(fixture) => {
+            let archive = db.load_articles_mentioning(&fixture.opponent, cfg.match_day.archive_days, cfg.match_day.max_articles)?;
+            let picked = pick_articles(cfg, fixture, PREVIEW_WORDS, articles, &archive);
+            info!("Writing the {} preview from {} articles", fixture.title(), picked.len());
+            let mut preview = preview(cfg, fixture, &picked).await?;
+            if cfg.availability.enabled {
+                // the tracker remembers injuries that today's articles don't mention
+                preview.injuries = current_availability(cfg, db, true)?.iter().map(|p| p.describe()).collect();
+            }
+            Ok(MatchSection::Preview(preview))
+        }
+        MatchMode::PostMatch(fixture) => {
+            // reports published late on match day were scraped by yesterday's runs
+            let archive = db.load_articles_mentioning(&fixture.opponent, 2, cfg.match_day.max_articles)?;
+            let picked = pick_articles(cfg, fixture, REPORT_WORDS, articles, &archive);
+            info!("Writing the {} report from {} articles", fixture.title(), picked.len());
+            Ok(MatchSection::Report(report(cfg, fixture, &picked).await?))
+        }
+    }
+}
+
+/// Today's articles about the game first, then archived ones, without repeats
+fn pick_articles<'a>(
+    cfg: &Config,
+    fixture: &Fixture,
+    words: &[&str],
+    today: &'a [NewsArticle],
+    archive: &'a [NewsArticle],
+) -> Vec<&'a NewsArticle> {
+    let opponent = fixture.opponent.to_lowercase();
+    let about_game = |a: &NewsArticle| {
+        let title = a.og_title.to_lowercase();
+        title.contains(&opponent) || words.iter().any(|w| title.contains(w))
+    };
+
+    let mut seen: HashSet<&str> = HashSet::new();
+    today.iter()
+        .filter(|a| about_game(a))
+        .chain(archive.iter())
+        .filter(|a| seen.insert(a.url.as_str()))
+        .take(cfg.match_day.max_articles)
+        .collect()
+}
+
+fn articles_block(cfg: &Config, articles: &[&NewsArticle]) -> String {
+    let bpe = o200k_base().unwrap();
+    articles.iter()
+        .map(|a| format!(
+            "{} ({}, {})\n\n{}",
+            a.og_title,
+            a.source,
+            a.published_time.format("%Y-%m-%d"),
+            decode_first_n_tokens(&bpe, &a.text, cfg.match_day.max_article_tokens)
+        ))
+        .collect::<Vec<_>>()
+        .join("\n\n---\n\n")
+}
+
+#[derive(Debug, Deserialize)]
+struct RawPreview {
+    team_news: Vec<String>,
+    injuries: Vec<String>,
+    opponent_form: String,
+}
+
+async fn preview(cfg: &Config, fixture: &Fixture, articles: &[&NewsArticle]) -> Result<MatchPreview> {
+    let system_prompt = format!(r#"
+You are a Liverpool (LFC) fan writing the match-day preview for {title}, kicking off at {kickoff} (UTC).
+
+From the articles provided, write:
+  - team_news: short bullet points on the expected line-up, selection calls and what the manager said
+  - injuries: one short bullet point per player who is out or doubtful, with the reason and expected return if known
+  - opponent_form: one or two sentences on {opponent}'s recent results and form
+
+Only use what the articles say; leave a list empty rather than guess. Use casual language and the odd emoji, and go easy on the em dashes.
+"#, title = fixture.title(), kickoff = fixture.date.format("%H:%M"), opponent = fixture.opponent);
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "team_news": { "type": "array", "items": { "type": "string" } },
+            "injuries": { "type": "array", "items": { "type": "string" } },
+            "opponent_form": { "type": "string" }
+        },
+        "required": ["team_news", "injuries", "opponent_form"],
+        "additionalProperties": false
+    });
+
+    let raw: RawPreview = chat_json(cfg, JsonCall {
+        step: "match_preview",
+        schema_name: "match_preview",
+        schema,
+        messages: vec![
+            ChatCompletionRequestSystemMessage::from(system_prompt).into(),
+            ChatCompletionRequestUserMessage::from(articles_block(cfg, articles)).into(),
+        ],
+        max_completion_tokens: 3000,
+    }).await?;
+    debug!("Match preview: {:#?}", raw);
+
+    Ok(MatchPreview {
+        fixture: fixture.title(),
+        kickoff: fixture.date,
+        team_news: raw.team_news,
+        injuries: raw.injuries,
+        opponent_form: raw.opponent_form,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct RawReport {
+    score: String,
+    scorers: Vec<String>,
+    ratings: Vec<PlayerRating>,
+    reactions: Vec<String>,
+    verdict: String,
+}
+
+async fn report(cfg: &Config, fixture: &Fixture, articles: &[&NewsArticle]) -> Result<MatchReport> {
+    let system_prompt = format!(r#"
+You are a Liverpool (LFC) fan writing the post-match report for yesterday's {title}.
+
+From the match reports, ratings and reaction pieces provided, write:
+  - score: the final score in the form "Home 2-1 Away"
+  - scorers: one entry per goal, like "Salah 23'", marking own goals and penalties
+  - ratings: Liverpool players who played, with a rating out of 10 (average the articles' ratings when there are several) and a few words on their game
+  - reactions: short bullet points with what the manager, players and pundits said
+  - verdict: one sentence on what the result means for us
+
+Only use what the articles say; leave a list empty rather than guess, and write "unknown" as the score if no article gives it. Use casual language and the odd emoji, and go easy on the em dashes.
+"#, title = fixture.title());
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "score": { "type": "string" },
+            "scorers": { "type": "array", "items": { "type": "string" } },
+            "ratings": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "player": { "type": "string" },
+                        "rating": { "type": "number" },
+                        "note": { "type": "string" }
+                    },
+                    "required": ["player", "rating", "note"],
+                    "additionalProperties": false
+                }
+            },
+            "reactions": { "type": "array", "items": { "type": "string" } },
+            "verdict": { "type": "string" }
+        },
+        "required": ["score", "scorers", "ratings", "reactions", "verdict"],
+        "additionalProperties": false
+    });
+
+    let raw: RawReport = chat_json(cfg, JsonCall {
+        step: "match_report",
+        schema_name: "match_report",
+        schema,
+        messages: vec![
+            ChatCompletionRequestSystemMessage::from(system_prompt).into(),
+            ChatCompletionRequestUserMessage::from(articles_block(cfg, articles)).into(),
+        ],
+        max_completion_tokens: 3000,
+    }).await?;
+    debug!("Match report: {:#?}", raw);
+
+    Ok(MatchReport {
+        fixture: fixture.title(),
+        score: raw.score,
+        scorers: raw.scorers,
+        ratings: raw.ratings,
+        reactions: raw.reactions,
+        verdict: raw.verdict,
+    })
+}
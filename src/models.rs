@@ -1,23 +1,315 @@
-use chrono::{NaiveDate, Utc};
+use chrono::{NaiveDate, NaiveDateTime, Utc};
 use chrono::DateTime;
 use url::Url;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Summary {
     pub mood: String,
+    #[serde(default)]
+    pub sentiment: Option<f32>, // -1.0 (grim) to 1.0 (elated)
+    #[serde(default)]
+    pub category_sentiment: Vec<CategorySentiment>,
+    #[serde(default)]
+    pub match_section: Option<MatchSection>, // preview on match day, report the day after
+    #[serde(default)]
+    pub transfer_changes: Vec<TransferChange>, // rumours whose status moved this run
+    #[serde(default)]
+    pub availability_changes: Vec<AvailabilityChange>, // players ruled out, back in training, …
     pub items: Vec<Bullet>,
     pub date: NaiveDate,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorySentiment {
+    pub category: String,
+    pub score: f32,
+}
+
+/// The match-day block rendered above the bullets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum MatchSection {
+    Preview(MatchPreview),
+    Report(MatchReport),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchPreview {
+    pub fixture: String, // "Liverpool vs Opponent"
+    pub kickoff: NaiveDateTime,
+    pub team_news: Vec<String>,
+    pub injuries: Vec<String>,
+    pub opponent_form: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchReport {
+    pub fixture: String,
+    pub score: String, // "Liverpool 2-1 Opponent"
+    pub scorers: Vec<String>,
+    pub ratings: Vec<PlayerRating>,
+    pub reactions: Vec<String>,
+    pub verdict: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerRating {
+    pub player: String,
+    pub rating: f32,
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferStatus {
+    Rumour,
+    Talks,
+    Agreed,
+    Done,
+    Collapsed,
+}
+
+impl TransferStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransferStatus::Rumour => "rumour",
+            TransferStatus::Talks => "talks",
+            TransferStatus::Agreed => "agreed",
+            TransferStatus::Done => "done",
+            TransferStatus::Collapsed => "collapsed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [Self::Rumour, Self::Talks, Self::Agreed, Self::Done, Self::Collapsed]
+            .into_iter()
+            .find(|status| status.as_str() == s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferDirection {
+    In,
+    Out,
+}
+
+impl TransferDirection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransferDirection::In => "in",
+            TransferDirection::Out => "out",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferRumour {
+    pub id: i64,
+    pub player: String,
+    pub from_club: Option<String>,
+    pub to_club: Option<String>,
+    pub direction: TransferDirection,
+    pub fee: Option<String>,
+    pub status: TransferStatus,
+    pub first_seen: NaiveDateTime,
+    pub last_updated: NaiveDateTime,
+}
+
+impl TransferRumour {
+    /// "Bournemouth → Liverpool", with "?" for a club that wasn't reported
+    pub fn route(&self) -> String {
+        match self.direction {
+            TransferDirection::In => format!("{} → Liverpool", self.from_club.as_deref().unwrap_or("?")),
+            TransferDirection::Out => format!("Liverpool → {}", self.to_club.as_deref().unwrap_or("?")),
+        }
+    }
+}
+
+/// One report about a rumour, as stored in its history
+#[derive(Debug, Clone)]
+pub struct TransferUpdate {
+    pub status: TransferStatus,
+    pub fee: Option<String>,
+    pub source: Option<String>,
+    pub url: Option<String>,
+    pub reported_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferChange {
+    pub rumour: TransferRumour,
+    pub previous: Option<TransferStatus>, // None for a rumour seen for the first time
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AvailabilityStatus {
+    Available,
+    Doubtful,
+    Out,
+    Suspended,
+}
+
+impl AvailabilityStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AvailabilityStatus::Available => "available",
+            AvailabilityStatus::Doubtful => "doubtful",
+            AvailabilityStatus::Out => "out",
+            AvailabilityStatus::Suspended => "suspended",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [Self::Available, Self::Doubtful, Self::Out, Self::Suspended]
+            .into_iter()
+            .find(|status| status.as_str() == s)
+    }
+}
+
+/// A player's latest known fitness or suspension status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerAvailability {
+    pub id: i64,
+    pub player: String,
+    pub status: AvailabilityStatus,
+    pub issue: Option<String>,
+    pub expected_return: Option<String>,
+    pub last_updated: NaiveDateTime,
+}
+
+impl PlayerAvailability {
+    /// "Alisson: out (hamstring), back after the international break"
+    pub fn describe(&self) -> String {
+        let mut line = format!("{}: {}", self.player, self.status.as_str());
+        if let Some(issue) = &self.issue {
+            line.push_str(&format!(" ({})", issue));
+        }
+        if let Some(expected) = &self.expected_return {
+            line.push_str(&format!(", back {}", expected));
+        }
+        line
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailabilityChange {
+    pub player: PlayerAvailability,
+    pub previous: Option<AvailabilityStatus>, // None for a player not tracked before
+    pub sources: Vec<String>,
+}
+
+/// One summary's sentiment, for the mood history
+#[derive(Debug, Clone)]
+pub struct MoodPoint {
+    pub fetch_id: i64,
+    pub generated_at: NaiveDateTime,
+    pub sentiment: f32,
+    pub categories: Vec<CategorySentiment>,
+}
+
+/// How long a rendered bullet is: a headline, the standard bullet or a detailed paragraph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BulletVariant {
+    Headline,
+    #[default]
+    Standard,
+    Detailed,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Bullet {
+    #[serde(default)]
+    pub id: Option<i64>, // row id once the bullet has been stored
     pub text: String,
     pub accepted: Option<bool>,
+    #[serde(default)]
+    pub embedding: Option<Vec<f32>>,
+    #[serde(default)]
+    pub reason: Option<String>, // why the deduplicator accepted or rejected it
+    #[serde(default)]
+    pub duplicate_of: Option<DuplicateOf>,
+    #[serde(default)]
+    pub published_at: Option<NaiveDateTime>, // when its summary was generated, for previous bullets
+    #[serde(default)]
+    pub category: Option<String>, // one of the configured category names
+    #[serde(default)]
+    pub importance: Option<u8>, // 1 (filler) to 10 (huge news)
+    #[serde(default)]
+    pub deferred: bool, // accepted, but left out of the digest by the caps
+    #[serde(default)]
+    pub carryovers: u32,
+    #[serde(default)]
+    pub sources: Vec<i64>, // ids of the articles the bullet was written from
+    #[serde(default)]
+    pub headline: Option<String>, // headline-length variant of `text`
+    #[serde(default)]
+    pub detail: Option<String>, // paragraph-length variant of `text`
+    #[serde(default)]
+    pub grounded: Option<bool>, // None = not checked against its sources
+    #[serde(default)]
+    pub grounding_notes: Option<String>,
+    #[serde(default)]
+    pub update_on: Option<String>, // title of the earlier reported story this bullet follows up
+}
+
+impl Bullet {
+    /// The text of `variant`, falling back to the standard text for bullets written without it
+    pub fn variant_text(&self, variant: BulletVariant) -> &str {
+        let text = match variant {
+            BulletVariant::Headline => self.headline.as_deref(),
+            BulletVariant::Standard => None,
+            BulletVariant::Detailed => self.detail.as_deref(),
+        };
+        text.filter(|t| !t.is_empty()).unwrap_or(&self.text)
+    }
+
+    /// Each variant the bullet was written in with its text, the standard one first
+    pub fn variant_texts(&self) -> impl Iterator<Item = (BulletVariant, &str)> {
+        [
+            (BulletVariant::Standard, Some(self.text.as_str())),
+            (BulletVariant::Headline, self.headline.as_deref()),
+            (BulletVariant::Detailed, self.detail.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(variant, text)| Some((variant, text.filter(|t| !t.is_empty())?)))
+    }
+
+    /// Days since this bullet was published, 0 for today's candidates
+    pub fn age_days(&self, now: NaiveDateTime) -> f32 {
+        self.published_at
+            .map(|t| (now - t).num_minutes().max(0) as f32 / (24.0 * 60.0))
+            .unwrap_or(0.0)
+    }
+}
+
+/// The bullet a rejected candidate repeats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicateOf {
+    /// An already stored bullet, by row id
+    Previous(i64),
+    /// Another candidate of the same summary, by position in `Summary.items`
+    Candidate(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectedBullet {
+    pub id: i64,
+    pub fetch_id: i64,
+    pub text: String,
+    pub reason: Option<String>,
+    pub matched_id: Option<i64>,
+    pub matched_fetch_id: Option<i64>,
+    pub matched_text: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct NewsArticle {
+    pub id: Option<i64>, // row id once the article has been stored
     pub url: Url, // from the 'url' crate
     pub og_title: String,
     pub published_time: DateTime<Utc>,
@@ -26,3 +318,87 @@ pub struct NewsArticle {
     pub text: String,
     pub source: String,
 }
+
+/// An article at https://example.com`path`, published at noon on 2026-10-18, for tests
+#[cfg(test)]
+pub fn test_article(id: Option<i64>, title: &str, path: &str, source: &str, text: &str) -> NewsArticle {
+    use chrono::TimeZone;
+    NewsArticle {
+        id,
+        url: format!("https://example.com{path}").parse().unwrap(),
+        og_title: title.to_string(),
+        published_time: Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap(),
+        og_image: "https://example.com/a.jpg".parse().unwrap(),
+        author: String::new(),
+        text: text.to_string(),
+        source: source.to_string(),
+    }
+}
+
+/// Narrows `lfc search`; every filter is optional
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub source: Option<String>, // substring of the source name
+    pub author: Option<String>, // substring of the byline
+}
+
+#[derive(Debug, Clone)]
+pub struct ArticleHit {
+    pub id: i64,
+    pub title: String,
+    pub source: String,
+    pub author: Option<String>,
+    pub url: String,
+    pub published: Option<NaiveDate>,
+    pub snippet: String, // matched words in [brackets]
+}
+
+#[derive(Debug, Clone)]
+pub struct BulletHit {
+    pub id: i64,
+    pub sent_at: Option<NaiveDateTime>,
+    pub snippet: String,
+}
+
+/// A story thread, with how many articles and sent bullets it has
+#[derive(Debug, Clone)]
+pub struct Story {
+    pub id: i64,
+    pub title: String,
+    pub first_seen: NaiveDateTime,
+    pub last_updated: NaiveDateTime,
+    pub articles: i64,
+    pub bullets: i64,
+}
+
+/// An article or digest bullet on a story's timeline
+#[derive(Debug, Clone)]
+pub struct StoryEntry {
+    pub at: NaiveDateTime,
+    pub text: String, // the article's title, or the bullet
+    pub source: Option<String>, // the article's site, None for bullets
+    pub url: Option<String>,
+}
+
+/// How often an entity was named in two equal windows, the latest and the one before
+#[derive(Debug, Clone)]
+pub struct MentionRow {
+    pub name: String,
+    pub kind: String,
+    pub articles: i64,
+    pub previous_articles: i64,
+    pub bullets: i64, // sent digest bullets in the latest window
+}
+
+#[derive(Debug, Clone)]
+pub struct CostRow {
+    pub period: String,
+    pub model: String,
+    pub calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub reasoning_tokens: i64,
+    pub cost_usd: Option<f64>,
+}
@@ -0,0 +1,84 @@
+use log::info;
+
+use crate::config::DigestConfig;
+use crate::models::{Bullet, Summary};
+
+/// Importance used for ordering; bullets without a score sort as middling
+pub fn importance_of(bullet: &Bullet) -> u8 {
+    bullet.importance.unwrap_or(5)
+}
+
+/// Defer accepted bullets that don't fit the digest caps: anything below `min_importance`,
+/// then everything past the `max_bullets` most important. Deferred bullets stay accepted
+/// so the next run picks them up as carryover candidates.
+pub fn apply_digest_caps(summary: &Summary, caps: &DigestConfig) -> Summary {
+    let mut summary = summary.clone();
+
+    let mut accepted: Vec<usize> = summary
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| b.accepted == Some(true))
+        .map(|(i, _)| i)
+        .collect();
+
+    if let Some(min) = caps.min_importance {
+        accepted.retain(|&i| {
+            let keep = importance_of(&summary.items[i]) >= min;
+            if !keep {
+                summary.items[i].deferred = true;
+            }
+            keep
+        });
+    }
+
+    if let Some(max) = caps.max_bullets {
+        // stable sort keeps the summarizer's order among equally important bullets
+        accepted.sort_by_key(|&i| std::cmp::Reverse(importance_of(&summary.items[i])));
+        for &i in accepted.iter().skip(max) {
+            summary.items[i].deferred = true;
+        }
+    }
+
+    let deferred = summary.items.iter().filter(|b| b.deferred).count();
+    if deferred > 0 {
+        info!("Deferred {} bullets to the next run because of the digest caps", deferred);
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_digest_caps() {
+        let bullet = |text: &str, importance: u8, accepted: bool| Bullet {
+            text: text.to_string(),
+            accepted: Some(accepted),
+            importance: Some(importance),
+            ..Default::default()
+        };
+        let summary = Summary {
+            mood: String::new(),
+            items: vec![
+                bullet("filler", 2, true),
+                bullet("big", 9, true),
+                bullet("rejected", 10, false),
+                bullet("medium", 6, true),
+                bullet("small", 4, true),
+            ],
+            date: chrono::Utc::now().date_naive(),
+            ..Default::default()
+        };
+        let caps = DigestConfig { max_bullets: Some(2), min_importance: Some(3), max_carryovers: 2 };
+
+        let capped = apply_digest_caps(&summary, &caps);
+        let deferred: Vec<&str> = capped.items.iter().filter(|b| b.deferred).map(|b| b.text.as_str()).collect();
+        assert_eq!(deferred, vec!["filler", "small"]);
+
+        // rejected bullets are never deferred, whatever their score
+        assert!(!capped.items[2].deferred);
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use log::info;
+
+use crate::app::{execute_run, missing_delivery_secrets};
+use crate::config::Config;
+use crate::db::Db;
+use crate::logger::init_logger;
+use crate::runs::{RunOptions, Stage};
+
+/// What happens to the summary a regenerated fetch already has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegenerateMode {
+    /// Keep the old summary and its bullets as a superseded version
+    Version,
+    /// Delete the old summary and its bullets
+    Replace,
+}
+
+/// Send the new digest unless the old one already went out and no resend was asked for
+fn should_deliver(previously_sent: Option<bool>, resend: bool) -> bool {
+    resend || previously_sent != Some(true)
+}
+
+/// Summarize, deduplicate and deliver a stored fetch again
+pub async fn run_regenerate(
+    fetch_id: Option<i64>,
+    date: Option<NaiveDate>,
+    mode: RegenerateMode,
+    resend: bool,
+    no_email: bool,
+    no_telegram: bool,
+) -> Result<()> {
+    init_logger()?;
+    let mut cfg = Config::get_user_config()?;
+    let db = Db::open(&cfg)?;
+
+    let fetch_id = match (fetch_id, date) {
+        (Some(id), _) => id,
+        (None, Some(day)) => db.latest_fetch_on(day)?
+            .ok_or_else(|| anyhow!("No fetch with articles on {day}"))?,
+        (None, None) => return Err(anyhow!("Pass --fetch-id or --date")),
+    };
+    let articles = db.count_fetch_articles(fetch_id)?;
+    if articles == 0 {
+        return Err(anyhow!("Fetch {fetch_id} has no stored articles"));
+    }
+    let fetched_at = db.fetch_time(fetch_id)?
+        .ok_or_else(|| anyhow!("Fetch {fetch_id} does not exist"))?;
+
+    let previous = db.summary_state(fetch_id)?;
+    let deliver = should_deliver(previous.map(|(_, sent)| sent), resend);
+    if deliver && let Some(problem) = missing_delivery_secrets(&cfg, no_email, no_telegram) {
+        return Err(anyhow!(problem));
+    }
+
+    // carried-over bullets belong to whichever run comes next, so only the latest fetch takes them
+    let latest = !db.has_later_summary(fetch_id)?;
+    info!(
+        "Regenerating fetch {} ({} articles, fetched {}), {} the old summary",
+        fetch_id,
+        articles,
+        fetched_at,
+        match mode {
+            RegenerateMode::Version => "versioning",
+            RegenerateMode::Replace => "replacing",
+        }
+    );
+
+    let options = RunOptions {
+        no_ai: false,
+        offline: false,
+        no_email,
+        no_telegram,
+        carryovers: latest,
+        // the trackers have moved on since, merging old reports again would only add noise
+        trackers: false,
+        previous: Some(mode),
+        generated_at: Some(previous.and_then(|(t, _)| t).unwrap_or(fetched_at)),
+        deliver,
+    };
+    // a run over the stored articles, so it starts after the scrape and resumes like any other
+    let run_id = db.create_run(Some(fetch_id), Some(Stage::Scrape), &options)?;
+    drop(db);
+    execute_run(&mut cfg, run_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_deliver() {
+        assert!(should_deliver(None, false));
+        assert!(should_deliver(Some(false), false));
+        assert!(!should_deliver(Some(true), false));
+        assert!(should_deliver(Some(true), true));
+    }
+}
@@ -0,0 +1,418 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+
+use crate::availability::current_availability;
+use crate::config::Config;
+use crate::db::Db;
+use crate::entities::{retag_all, EntityKind};
+use crate::models::{MoodPoint, SearchFilters};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Period {
+    Day,
+    Month,
+}
+
+const COLUMN_WIDTH: usize = 58;
+
+/// Print each rejected bullet of a fetch next to the bullet it was judged to repeat
+pub fn print_rejected_bullets(fetch_id: Option<i64>) -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let db = Db::open(&cfg)?;
+
+    let rejected = db.fetch_rejected_bullets(fetch_id)?;
+    if rejected.is_empty() {
+        println!("No rejected bullets.");
+        return Ok(());
+    }
+
+    println!("{} rejected bullets in fetch {}\n", rejected.len(), rejected[0].fetch_id);
+
+    for r in &rejected {
+        let left_header = format!("REJECTED #{}", r.id);
+        let right_header = match (r.matched_id, r.matched_fetch_id) {
+            (Some(id), Some(f)) if f == r.fetch_id => format!("SAME DAY #{}", id),
+            (Some(id), Some(f)) => format!("MATCHED #{} (fetch {})", id, f),
+            _ => "NO MATCH RECORDED".to_string(),
+        };
+        let right_text = r.matched_text.as_deref().unwrap_or("");
+
+        println!("{}", side_by_side(&left_header, &right_header));
+        println!("{}", side_by_side(&r.text, right_text));
+        println!("reason: {}", r.reason.as_deref().unwrap_or("(none recorded)"));
+        println!("{}", "-".repeat(COLUMN_WIDTH * 2 + 3));
+    }
+
+    Ok(())
+}
+
+/// Print token usage and cost per day or month and model, with the monthly budget status
+pub fn print_costs(by: Period) -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let db = Db::open(&cfg)?;
+
+    let format = match by {
+        Period::Day => "%Y-%m-%d",
+        Period::Month => "%Y-%m",
+    };
+    let rows = db.cost_ledger(format)?;
+    if rows.is_empty() {
+        println!("No LLM calls recorded yet.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<10}  {:<24} {:>6} {:>12} {:>12} {:>12} {:>10}",
+        "period", "model", "calls", "prompt", "completion", "reasoning", "cost"
+    );
+    let mut total = 0.0;
+    for r in &rows {
+        let cost = match r.cost_usd {
+            Some(c) => {
+                total += c;
+                format!("${:.4}", c)
+            }
+            None => "n/a".to_string(),
+        };
+        println!(
+            "{:<10}  {:<24} {:>6} {:>12} {:>12} {:>12} {:>10}",
+            r.period, r.model, r.calls, r.prompt_tokens, r.completion_tokens, r.reasoning_tokens, cost
+        );
+    }
+    println!("\nTotal: ${:.4}", total);
+
+    if let Some(budget) = cfg.costs.monthly_budget {
+        println!("This month: ${:.4} of ${:.2} budget", db.month_to_date_cost()?, budget);
+    }
+
+    Ok(())
+}
+
+/// Print the entities named in the most articles over the last `days`, with the change from the
+/// `days` before and the sent bullets naming them, then the biggest risers and fallers.
+/// `retag` first tags every stored article and bullet again with the current dictionary
+pub fn print_mentions(days: u32, kind: Option<EntityKind>, limit: usize, retag: bool) -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let mut db = Db::open(&cfg)?;
+
+    if retag {
+        let (articles, bullets) = retag_all(&cfg, &mut db)?;
+        println!("Tagged {} articles and {} bullets again.\n", articles, bullets);
+    }
+
+    let rows = db.mention_counts(days, kind.map(|k| k.as_str()))?;
+    if rows.is_empty() {
+        println!("No mentions in the last {} days.", days);
+        return Ok(());
+    }
+
+    println!("{:<28} {:<12} {:>8} {:>8} {:>8} {:>8}", "name", "kind", "articles", "before", "change", "bullets");
+    for r in rows.iter().take(limit) {
+        println!(
+            "{:<28} {:<12} {:>8} {:>8} {:>8} {:>8}",
+            r.name, r.kind, r.articles, r.previous_articles, trend(r.articles, r.previous_articles), r.bullets
+        );
+    }
+
+    let mut movers: Vec<_> = rows.iter().filter(|r| r.articles != r.previous_articles).collect();
+    movers.sort_by_key(|r| std::cmp::Reverse(r.articles - r.previous_articles));
+    let risers: Vec<String> = movers.iter().filter(|r| r.articles > r.previous_articles).take(5)
+        .map(|r| format!("{} ({})", r.name, trend(r.articles, r.previous_articles)))
+        .collect();
+    let fallers: Vec<String> = movers.iter().rev().filter(|r| r.articles < r.previous_articles).take(5)
+        .map(|r| format!("{} ({})", r.name, trend(r.articles, r.previous_articles)))
+        .collect();
+    if !risers.is_empty() {
+        println!("\nTrending up: {}", risers.join(", "));
+    }
+    if !fallers.is_empty() {
+        println!("Trending down: {}", fallers.join(", "));
+    }
+    Ok(())
+}
+
+/// "+3", "-2", "=" or "new"
+fn trend(latest: i64, previous: i64) -> String {
+    match latest - previous {
+        _ if previous == 0 && latest > 0 => "new".to_string(),
+        0 => "=".to_string(),
+        d => format!("{d:+}"),
+    }
+}
+
+/// Print the tracked transfer rumours, open ones only unless `all`, each with its history
+pub fn print_transfers(all: bool) -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let db = Db::open(&cfg)?;
+
+    let rumours = db.list_transfer_rumours(all)?;
+    if rumours.is_empty() {
+        println!("No {}transfer rumours tracked.", if all { "" } else { "open " });
+        return Ok(());
+    }
+
+    for rumour in rumours {
+        let history = db.transfer_history(rumour.id)?;
+        let mut sources: Vec<String> = vec![];
+        for update in &history {
+            if let Some(source) = &update.source && !sources.contains(source) {
+                sources.push(source.clone());
+            }
+        }
+
+        let mut headline = format!("#{} {} ({}): {}", rumour.id, rumour.player, rumour.route(), rumour.status.as_str());
+        if let Some(fee) = &rumour.fee {
+            headline.push_str(&format!(", {}", fee));
+        }
+        println!("{}", headline);
+        if !sources.is_empty() {
+            println!("   sources: {}", sources.join(", "));
+        }
+        println!("   first seen {}, updated {}", rumour.first_seen.format("%Y-%m-%d"), rumour.last_updated.format("%Y-%m-%d"));
+        for update in &history {
+            println!(
+                "   {}  {:<9} {:<12} {}",
+                update.reported_at.format("%Y-%m-%d"),
+                update.status.as_str(),
+                update.fee.as_deref().unwrap_or("-"),
+                update.url.as_deref().unwrap_or("")
+            );
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Print who is out, doubtful or suspended, or every tracked player with `all`
+pub fn print_availability(all: bool) -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let db = Db::open(&cfg)?;
+
+    let players = current_availability(&cfg, &db, !all)?;
+    if players.is_empty() {
+        println!("No {}players tracked.", if all { "" } else { "unavailable " });
+        return Ok(());
+    }
+
+    for p in players {
+        println!("{}  {}", p.last_updated.format("%Y-%m-%d"), p.describe());
+    }
+    Ok(())
+}
+
+/// Print the articles, and unless filtered by source or author the digest bullets, matching
+/// a full-text query, best first
+pub fn print_search(query: &str, filters: &SearchFilters, limit: usize) -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let db = Db::open(&cfg)?;
+
+    let articles = db.search_article_hits(query, filters, limit)?;
+    // bullets have no source or author of their own
+    let bullets = if filters.source.is_none() && filters.author.is_none() {
+        db.search_bullet_hits(query, filters.from, filters.to, limit)?
+    } else {
+        vec![]
+    };
+    if articles.is_empty() && bullets.is_empty() {
+        println!("No matches.");
+        return Ok(());
+    }
+
+    for hit in &articles {
+        let date = hit.published.map(|d| d.to_string()).unwrap_or_else(|| "????-??-??".to_string());
+        let byline = match hit.author.as_deref() {
+            Some(author) if !author.is_empty() => format!("{}, {}", hit.source, author),
+            _ => hit.source.clone(),
+        };
+        println!("{}  #{}  {} ({})", date, hit.id, hit.title, byline);
+        println!("  {}", hit.snippet.split_whitespace().collect::<Vec<_>>().join(" "));
+        println!("  {}\n", hit.url);
+    }
+
+    if !bullets.is_empty() {
+        println!("Digest bullets:\n");
+        for hit in &bullets {
+            let date = hit.sent_at.map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default();
+            println!("{}  #{}  {}", date, hit.id, hit.snippet);
+        }
+    }
+    Ok(())
+}
+
+/// Print the sentiment of each summary with a sparkline, optionally writing it as CSV or a PNG chart
+pub fn print_mood(days: Option<u32>, csv: Option<&Path>, png: Option<&Path>) -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let db = Db::open(&cfg)?;
+
+    let points = db.mood_history(days)?;
+    if points.is_empty() {
+        println!("No sentiment recorded yet.");
+        return Ok(());
+    }
+
+    for p in &points {
+        let categories = p.categories
+            .iter()
+            .map(|c| format!("{} {:+.2}", c.category, c.score))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("{}  {:+.2}  {}", p.generated_at.format("%Y-%m-%d %H:%M"), p.sentiment, categories);
+    }
+
+    let scores: Vec<f32> = points.iter().map(|p| p.sentiment).collect();
+    let average = scores.iter().sum::<f32>() / scores.len() as f32;
+    println!("\n{}", sparkline(&scores));
+    println!("{} summaries, average {:+.2}", scores.len(), average);
+
+    if let Some(path) = csv {
+        write_mood_csv(&cfg, &points, path)?;
+        println!("Wrote {}", path.display());
+    }
+    if let Some(path) = png {
+        write_mood_png(&scores, path)?;
+        println!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// One block character per score, scaled from -1.0 (lowest) to 1.0 (highest)
+fn sparkline(scores: &[f32]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    scores
+        .iter()
+        .map(|s| {
+            let level = ((s.clamp(-1.0, 1.0) + 1.0) / 2.0 * (BLOCKS.len() - 1) as f32).round();
+            BLOCKS[level as usize]
+        })
+        .collect()
+}
+
+fn write_mood_csv(cfg: &Config, points: &[MoodPoint], path: &Path) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+
+    let names: Vec<&str> = cfg.categories.iter().map(|c| c.name.as_str()).collect();
+    writeln!(out, "fetch_id,generated_at,sentiment,{}", names.join(","))?;
+    for p in points {
+        let per_category: Vec<String> = names
+            .iter()
+            .map(|n| {
+                p.categories.iter()
+                    .find(|c| c.category == *n)
+                    .map(|c| c.score.to_string())
+                    .unwrap_or_default()
+            })
+            .collect();
+        writeln!(out, "{},{},{},{}", p.fetch_id, p.generated_at, p.sentiment, per_category.join(","))?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// A plain line chart: the sentiment in red over a grey zero line and faint ±0.5 lines
+fn write_mood_png(scores: &[f32], path: &Path) -> Result<()> {
+    const WIDTH: u32 = 800;
+    const HEIGHT: u32 = 300;
+    const MARGIN: u32 = 20;
+
+    let mut pixels = vec![255u8; (WIDTH * HEIGHT * 3) as usize];
+    let mut set = |x: i64, y: i64, rgb: [u8; 3]| {
+        if (0..WIDTH as i64).contains(&x) && (0..HEIGHT as i64).contains(&y) {
+            let i = ((y as u32 * WIDTH + x as u32) * 3) as usize;
+            pixels[i..i + 3].copy_from_slice(&rgb);
+        }
+    };
+
+    let plot_height = (HEIGHT - 2 * MARGIN) as f32;
+    let y_of = |score: f32| (MARGIN as f32 + (1.0 - score.clamp(-1.0, 1.0)) / 2.0 * plot_height).round() as i64;
+    let x_of = |i: usize| {
+        let step = (WIDTH - 2 * MARGIN) as f32 / (scores.len().max(2) - 1) as f32;
+        (MARGIN as f32 + i as f32 * step).round() as i64
+    };
+
+    for (score, rgb) in [(0.5, [230, 230, 230]), (-0.5, [230, 230, 230]), (0.0, [160, 160, 160])] {
+        for x in MARGIN..WIDTH - MARGIN {
+            set(x as i64, y_of(score), rgb);
+        }
+    }
+
+    let red = [200, 16, 46];
+    let points: Vec<(i64, i64)> = scores.iter().enumerate().map(|(i, s)| (x_of(i), y_of(*s))).collect();
+    for pair in points.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+        for t in 0..=steps {
+            let x = x0 + (x1 - x0) * t / steps;
+            let y = y0 + (y1 - y0) * t / steps;
+            for dy in -1..=1 {
+                set(x, y + dy, red);
+            }
+        }
+    }
+    for (x, y) in points {
+        for dx in -2..=2 {
+            for dy in -2..=2 {
+                set(x + dx, y + dy, red);
+            }
+        }
+    }
+
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(())
+}
+
+fn side_by_side(left: &str, right: &str) -> String {
+    let left = wrap(left, COLUMN_WIDTH);
+    let right = wrap(right, COLUMN_WIDTH);
+    let rows = left.len().max(right.len());
+
+    (0..rows)
+        .map(|i| {
+            let l = left.get(i).map(String::as_str).unwrap_or("");
+            let r = right.get(i).map(String::as_str).unwrap_or("");
+            format!("{:<width$} | {}", l, r, width = COLUMN_WIDTH).trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[-1.0, 0.0, 1.0]), "▁▅█");
+        // out-of-range scores are clamped
+        assert_eq!(sparkline(&[-3.0, 2.0]), "▁█");
+        assert_eq!(sparkline(&[]), "");
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use clap::ValueEnum;
+use log::{debug, error, info};
+
+use crate::ai_summarizer::summarize_period;
+use crate::config::{Config, RollupConfig};
+use crate::costs::apply_budget;
+use crate::db::Db;
+use crate::email::send_email;
+use crate::llm::drain_call_log;
+use crate::logger::init_logger;
+use crate::models::BulletVariant;
+use crate::telegram::send_telegram_message;
+use crate::utils::format_summary_plain_text;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RollupPeriod {
+    Weekly,
+    Monthly,
+}
+
+impl RollupPeriod {
+    /// Stored as the summary's `kind`
+    fn kind(self) -> &'static str {
+        match self {
+            RollupPeriod::Weekly => "weekly",
+            RollupPeriod::Monthly => "monthly",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            RollupPeriod::Weekly => "week",
+            RollupPeriod::Monthly => "month",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            RollupPeriod::Weekly => "Week",
+            RollupPeriod::Monthly => "Month",
+        }
+    }
+
+    fn config(self, cfg: &Config) -> &RollupConfig {
+        match self {
+            RollupPeriod::Weekly => &cfg.rollups.weekly,
+            RollupPeriod::Monthly => &cfg.rollups.monthly,
+        }
+    }
+
+    /// The seven days up to yesterday, or the previous calendar month
+    fn default_range(self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            RollupPeriod::Weekly => (today - Duration::days(7), today - Duration::days(1)),
+            RollupPeriod::Monthly => {
+                let end = today.with_day(1).unwrap() - Duration::days(1);
+                (end.with_day(1).unwrap(), end)
+            }
+        }
+    }
+}
+
+/// Summarize the daily digests sent between two dates (inclusive) into a recap, store it as its
+/// own summary and send it to the period's recipients
+pub async fn run_rollup(
+    period: RollupPeriod,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    no_email: bool,
+    no_telegram: bool,
+) -> Result<()> {
+    init_logger()?;
+    let mut cfg = Config::get_user_config()?;
+    let mut db = Db::open(&cfg)?;
+
+    let (default_start, default_end) = period.default_range(Local::now().date_naive());
+    let (start, end) = (from.unwrap_or(default_start), to.unwrap_or(default_end));
+    if start > end {
+        return Err(anyhow!("--from ({start}) must not be after --to ({end})"));
+    }
+
+    let bullets = db.fetch_sent_bullets_between(start, end)?;
+    if bullets.is_empty() {
+        info!("No bullets were sent between {} and {}, nothing to recap.", start, end);
+        return Ok(());
+    }
+    let moods = db.fetch_moods_between(start, end)?;
+    let articles = db.load_articles_between(start, end)?;
+    info!("Recapping {} bullets and {} articles from {} to {}", bullets.len(), articles.len(), start, end);
+
+    if !apply_budget(&mut cfg, &db)? {
+        return Ok(());
+    }
+
+    let rollup_cfg = period.config(&cfg).clone();
+    let fetch_id = db.create_fetch()?;
+    let summary = summarize_period(&cfg, period.label(), (start, end), &bullets, &moods, &articles, rollup_cfg.max_bullets).await;
+    db.insert_llm_calls(fetch_id, &drain_call_log())?;
+    let mut summary = summary?;
+
+    // nothing to deduplicate against, every recap bullet goes out
+    for b in &mut summary.items {
+        b.accepted = Some(true);
+        b.reason = Some(format!("{} recap", period.kind()));
+    }
+    db.insert_rollup(fetch_id, &summary, period.kind(), start, end)?;
+    debug!("Stored the {} recap as fetch {}", period.kind(), fetch_id);
+
+    let render = |recipient: &String, channel_variant: BulletVariant| {
+        let text = format!(
+            "{} in review: {} to {}\n\n{}",
+            period.title(),
+            start.format("%b %-d"),
+            end.format("%b %-d"),
+            format_summary_plain_text(&summary, &cfg, None, cfg.variant_for(recipient, channel_variant))
+        );
+        (recipient.clone(), text)
+    };
+    let subject = rollup_cfg.subject.clone().unwrap_or_else(|| format!("LFC {} in review", period.label()));
+
+    if no_email {
+        info!("--no-email flag set, skipping email notifications");
+    } else {
+        let messages: Vec<(String, String)> = rollup_cfg.emails.iter().map(|r| render(r, cfg.channels.email)).collect();
+        match send_email(&cfg, &subject, &messages).await {
+            Ok(_) => info!("Recap email(s) sent to {} recipients.", messages.len()),
+            Err(e) => error!("Recap email(s) failed: {e:?}"),
+        }
+    }
+
+    if no_telegram {
+        info!("--no-telegram flag set, skipping telegram notifications");
+    } else {
+        let messages: Vec<(String, String)> = rollup_cfg.telegram_chat_ids.iter().map(|r| render(r, cfg.channels.telegram)).collect();
+        match send_telegram_message(&cfg, &messages).await {
+            Ok(_) => info!("Recap telegram(s) sent to {} chats.", messages.len()),
+            Err(e) => error!("Recap telegram(s) failed: {e:?}"),
+        }
+    }
+
+    db.mark_summary_sent(fetch_id)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_range() {
+        let sunday = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        assert_eq!(
+            RollupPeriod::Weekly.default_range(sunday),
+            (NaiveDate::from_ymd_opt(2026, 10, 11).unwrap(), NaiveDate::from_ymd_opt(2026, 10, 17).unwrap())
+        );
+
+        let new_year = NaiveDate::from_ymd_opt(2027, 1, 1).unwrap();
+        assert_eq!(
+            RollupPeriod::Monthly.default_range(new_year),
+            (NaiveDate::from_ymd_opt(2026, 12, 1).unwrap(), NaiveDate::from_ymd_opt(2026, 12, 31).unwrap())
+        );
+    }
+}
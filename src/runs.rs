@@ -0,0 +1,150 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::MatchMode;
+use crate::models::Summary;
+use crate::regenerate::RegenerateMode;
+
+/// A run that keeps failing is given up on after this many attempts
+pub const MAX_RUN_ATTEMPTS: i64 = 3;
+
+/// A run still marked running that hasn't started or finished a stage for this long is taken
+/// to have crashed, well beyond what the slowest stage takes
+pub const STALE_RUN_MINUTES: u32 = 120;
+
+/// The steps of a run, in order. A run resumes after the last one that finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    /// Find article URLs that aren't stored yet
+    Discover,
+    /// Fetch and store those articles
+    Scrape,
+    /// Write candidate bullets from the fetch's articles
+    Summarize,
+    /// Judge the candidates against published bullets, check them and apply the caps
+    Dedup,
+    /// Merge the fetch's transfer and availability reports into the trackers
+    Track,
+    /// Store the summary
+    Persist,
+    /// Send the digest
+    Deliver,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 7] = [
+        Stage::Discover,
+        Stage::Scrape,
+        Stage::Summarize,
+        Stage::Dedup,
+        Stage::Track,
+        Stage::Persist,
+        Stage::Deliver,
+    ];
+
+    /// Stored in `runs.stage` and `run_stages.stage`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Discover => "discover",
+            Stage::Scrape => "scrape",
+            Stage::Summarize => "summarize",
+            Stage::Dedup => "dedup",
+            Stage::Track => "track",
+            Stage::Persist => "persist",
+            Stage::Deliver => "deliver",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Stage> {
+        Stage::ALL.into_iter().find(|stage| stage.as_str() == s)
+    }
+
+    /// The stages still to run once `finished` has, all of them if none has
+    pub fn after(finished: Option<Stage>) -> impl Iterator<Item = Stage> {
+        Stage::ALL.into_iter().filter(move |stage| Some(*stage) > finished)
+    }
+}
+
+/// How a run treats its fetch, stored with the run so a resumed run behaves the same
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunOptions {
+    pub no_ai: bool,
+    /// Build an extractive digest instead of asking the LLM; set too when a run falls back to one
+    #[serde(default)]
+    pub offline: bool,
+    pub no_email: bool,
+    pub no_telegram: bool,
+    /// Merge in the bullets earlier runs held back; only right for the latest fetch
+    pub carryovers: bool,
+    /// Run the transfer and availability trackers
+    pub trackers: bool,
+    /// What to do with a summary the fetch already has
+    pub previous: Option<RegenerateMode>,
+    /// Date the new summary as of this time instead of now
+    pub generated_at: Option<NaiveDateTime>,
+    /// Send the digest; it is marked sent either way
+    pub deliver: bool,
+}
+
+impl RunOptions {
+    /// A scheduled run over newly discovered articles
+    pub fn daily(no_ai: bool, offline: bool, no_email: bool, no_telegram: bool) -> Self {
+        RunOptions {
+            no_ai,
+            offline,
+            no_email,
+            no_telegram,
+            carryovers: true,
+            trackers: true,
+            previous: None,
+            generated_at: None,
+            deliver: true,
+        }
+    }
+}
+
+/// What the finished stages handed on to the next one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunState {
+    /// Discovered article URLs
+    #[serde(default)]
+    pub urls: Vec<String>,
+    #[serde(default)]
+    pub match_mode: Option<MatchMode>,
+    /// The candidates after summarize, the digest after dedup and persist
+    #[serde(default)]
+    pub summary: Option<Summary>,
+    /// Channels the digest already went out on, so a retried deliver only resends the others
+    #[serde(default)]
+    pub email_sent: bool,
+    #[serde(default)]
+    pub telegram_sent: bool,
+}
+
+/// A row of `runs`
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub id: i64,
+    pub fetch_id: Option<i64>,
+    /// Last stage that finished
+    pub stage: Option<Stage>,
+    pub attempts: i64,
+    pub options: RunOptions,
+    pub state: RunState,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_after() {
+        assert_eq!(Stage::after(None).collect::<Vec<_>>(), Stage::ALL.to_vec());
+        assert_eq!(Stage::after(Some(Stage::Scrape)).next(), Some(Stage::Summarize));
+        assert_eq!(Stage::after(Some(Stage::Dedup)).next(), Some(Stage::Track)); // runs saved before it existed
+        assert_eq!(Stage::after(Some(Stage::Deliver)).count(), 0);
+        assert_eq!(Stage::parse("dedup"), Some(Stage::Dedup));
+        assert_eq!(Stage::parse("rollup"), None);
+    }
+}
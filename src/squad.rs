@@ -0,0 +1,342 @@
+use std::fs;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{Local, NaiveDate};
+use clap::ValueEnum;
+use log::info;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::models::Summary;
+
+/// Where someone stands with the club
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// In the first-team squad
+    Player,
+    /// Manager, coaches and other staff
+    Staff,
+    /// Out on loan at another club
+    Loanee,
+    /// Left the club
+    Departed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Member {
+    pub name: String,
+    pub status: Status,
+    /// Position for players, job for staff
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number: Option<u8>,
+    /// What the press calls them, defaults to the last word of the name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nicknames: Vec<String>,
+    /// Wrong spellings of `short` that are corrected in bullets
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub misspellings: Vec<String>,
+    /// e.g. where a loanee or departed player went
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Member {
+    pub fn short_name(&self) -> &str {
+        self.short.as_deref().unwrap_or_else(|| self.name.split_whitespace().last().unwrap_or(&self.name))
+    }
+
+    /// Every name they go by, misspellings included
+    pub fn aliases(&self) -> impl Iterator<Item = &str> {
+        [self.name.as_str(), self.short_name()]
+            .into_iter()
+            .chain(self.nicknames.iter().map(String::as_str))
+            .chain(self.misspellings.iter().map(String::as_str))
+    }
+
+    fn describe(&self) -> String {
+        let mut line = match self.number {
+            Some(n) => format!("#{n} {}", self.name),
+            None => self.name.clone(),
+        };
+        if let Some(role) = &self.role {
+            line.push_str(&format!(", {role}"));
+        }
+        if !self.nicknames.is_empty() {
+            line.push_str(&format!(" (also {})", self.nicknames.join(", ")));
+        }
+        if let Some(note) = &self.note {
+            line.push_str(&format!(": {note}"));
+        }
+        line
+    }
+}
+
+/// What to change on a member with `lfc squad set`; unset fields are left alone
+#[derive(Debug, Default)]
+pub struct MemberUpdate {
+    pub status: Option<Status>,
+    pub role: Option<String>,
+    pub number: Option<u8>,
+    pub short: Option<String>,
+    pub nicknames: Vec<String>,
+    pub misspellings: Vec<String>,
+    pub note: Option<String>,
+}
+
+/// The club's current squad, staff, loanees and recent departures
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Squad {
+    #[serde(default)]
+    pub updated: Option<NaiveDate>,
+    #[serde(default)]
+    pub members: Vec<Member>,
+}
+
+fn mentions(text: &str, alias: &str) -> bool {
+    Regex::new(&format!(r"(?i)\b{}\b", regex::escape(alias))).is_ok_and(|re| re.is_match(text))
+}
+
+impl Squad {
+    /// The squad file, or an empty squad if there is none yet
+    pub fn load(cfg: &Config) -> Result<Squad> {
+        let path = cfg.squad.path();
+        if !path.exists() {
+            return Ok(Squad::default());
+        }
+        let raw = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_yaml::from_str(&raw).with_context(|| format!("Invalid squad file {}", path.display()))
+    }
+
+    fn save(&self, cfg: &Config) -> Result<()> {
+        let path = cfg.squad.path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_yaml::to_string(self)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The member `name` refers to, by full name, short name, nickname or misspelling
+    pub fn find(&self, name: &str) -> Option<&Member> {
+        self.position(name).map(|i| &self.members[i])
+    }
+
+    /// Index of the member `name` refers to, a full name match winning over an alias of another
+    fn position(&self, name: &str) -> Option<usize> {
+        let name = name.to_lowercase();
+        self.members
+            .iter()
+            .position(|m| m.name.to_lowercase() == name)
+            .or_else(|| self.members.iter().position(|m| m.aliases().any(|a| a.to_lowercase() == name)))
+    }
+
+    fn with_status(&self, status: Status) -> Vec<String> {
+        self.members.iter().filter(|m| m.status == status).map(|m| format!("- {}", m.describe())).collect()
+    }
+
+    /// The squad as prompt context, or None while it is empty
+    pub fn prompt_context(&self) -> Option<String> {
+        if self.members.is_empty() {
+            return None;
+        }
+        let mut out = format!(
+            "CLUB KNOWLEDGE (as of {}). Trust it over what you remember when naming players and staff, and spell names as written here.",
+            self.updated.map(|d| d.to_string()).unwrap_or_else(|| "unknown".to_string())
+        );
+        for (heading, status) in [
+            ("First-team squad", Status::Player),
+            ("Staff", Status::Staff),
+            ("Out on loan", Status::Loanee),
+            ("Left the club, no longer Liverpool players", Status::Departed),
+        ] {
+            let lines = self.with_status(status);
+            if !lines.is_empty() {
+                out.push_str(&format!("\n\n{heading}:\n{}", lines.join("\n")));
+            }
+        }
+        Some(out)
+    }
+
+    /// Replace known misspellings in the bullets with the member's short name, returning how many
+    /// were replaced
+    pub fn fix_spellings(&self, summary: &mut Summary) -> usize {
+        let mut fixed = 0;
+        for m in &self.members {
+            for wrong in &m.misspellings {
+                let Ok(re) = Regex::new(&format!(r"(?i)\b{}\b", regex::escape(wrong))) else { continue };
+                for b in &mut summary.items {
+                    for text in [Some(&mut b.text), b.headline.as_mut(), b.detail.as_mut()].into_iter().flatten() {
+                        if re.is_match(text) {
+                            info!("Correcting \"{}\" to \"{}\" in a bullet", wrong, m.short_name());
+                            *text = re.replace_all(text, m.short_name()).into_owned();
+                            fixed += 1;
+                        }
+                    }
+                }
+            }
+        }
+        fixed
+    }
+
+    /// Whether `word` is a name of someone whose other names appear in `haystack` (lowercase)
+    pub fn known_alias(&self, word: &str, haystack: &str) -> bool {
+        self.find(word).is_some_and(|m| m.aliases().any(|a| haystack.contains(&a.to_lowercase())))
+    }
+
+    /// Notes on the loanees and departed players `text` names, for checking it says nothing
+    /// that makes them sound like current squad members
+    pub fn name_notes(&self, text: &str) -> Vec<String> {
+        self.members
+            .iter()
+            .filter(|m| matches!(m.status, Status::Loanee | Status::Departed))
+            .filter(|m| m.aliases().any(|a| mentions(text, a)))
+            .map(|m| {
+                let status = if m.status == Status::Loanee { "is out on loan" } else { "has left the club" };
+                match &m.note {
+                    Some(note) => format!("{} {} ({})", m.name, status, note),
+                    None => format!("{} {}", m.name, status),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Print the squad file grouped by status
+pub fn print_squad() -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let squad = Squad::load(&cfg)?;
+    if squad.members.is_empty() {
+        println!("No squad at {}. Add members with `lfc squad set`.", cfg.squad.path().display());
+        return Ok(());
+    }
+
+    if let Some(day) = squad.updated {
+        println!("Updated {day}");
+    }
+    for (heading, status) in [
+        ("Squad", Status::Player),
+        ("Staff", Status::Staff),
+        ("On loan", Status::Loanee),
+        ("Departed", Status::Departed),
+    ] {
+        let lines = squad.with_status(status);
+        if !lines.is_empty() {
+            println!("\n{heading}:\n{}", lines.join("\n"));
+        }
+    }
+    Ok(())
+}
+
+/// Add a member or change one, e.g. when a player signs, leaves or goes out on loan
+pub fn set_member(name: &str, update: MemberUpdate) -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let mut squad = Squad::load(&cfg)?;
+
+    let member = match squad.position(name) {
+        Some(i) => &mut squad.members[i],
+        None => {
+            let status = update.status.ok_or_else(|| anyhow!("{name} is new, pass --status"))?;
+            squad.members.push(Member {
+                name: name.to_string(),
+                status,
+                role: None,
+                number: None,
+                short: None,
+                nicknames: vec![],
+                misspellings: vec![],
+                note: None,
+            });
+            squad.members.last_mut().unwrap()
+        }
+    };
+    if let Some(status) = update.status {
+        member.status = status;
+        // a departed player's shirt number goes to someone else
+        if status == Status::Departed && update.number.is_none() {
+            member.number = None;
+        }
+    }
+    member.role = update.role.or(member.role.take());
+    member.number = update.number.or(member.number);
+    member.short = update.short.or(member.short.take());
+    member.note = update.note.or(member.note.take());
+    for n in update.nicknames {
+        if !member.nicknames.contains(&n) {
+            member.nicknames.push(n);
+        }
+    }
+    for n in update.misspellings {
+        if !member.misspellings.contains(&n) {
+            member.misspellings.push(n);
+        }
+    }
+    println!("{}", member.describe());
+
+    squad.updated = Some(Local::now().date_naive());
+    squad.save(&cfg)
+}
+
+/// Take a member off the squad file altogether
+pub fn remove_member(name: &str) -> Result<()> {
+    let cfg = Config::get_user_config()?;
+    let mut squad = Squad::load(&cfg)?;
+    let i = squad.position(name).ok_or_else(|| anyhow!("{name} is not in {}", cfg.squad.path().display()))?;
+    squad.members.remove(i);
+    squad.updated = Some(Local::now().date_naive());
+    squad.save(&cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Bullet;
+
+    fn squad() -> Squad {
+        serde_yaml::from_str(r#"
+updated: 2025-09-02
+members:
+  - name: Dominik Szoboszlai
+    status: player
+    role: midfielder
+    number: 8
+    nicknames: [Szobo]
+    misspellings: [Szoboslai]
+  - name: Virgil van Dijk
+    status: player
+    short: Van Dijk
+  - name: Darwin Núñez
+    status: departed
+    note: joined Al-Hilal
+"#).unwrap()
+    }
+
+    #[test]
+    fn test_find_and_notes() {
+        let squad = squad();
+        assert_eq!(squad.find("szobo").map(|m| m.number), Some(Some(8)));
+        assert_eq!(squad.find("Van Dijk").map(|m| m.name.as_str()), Some("Virgil van Dijk"));
+        assert!(squad.find("Salah").is_none());
+        assert_eq!(squad.position("dominik szoboszlai"), Some(0));
+
+        assert!(squad.known_alias("Szobo", "dominik szoboszlai scored"));
+        assert!(!squad.known_alias("Szobo", "mac allister scored"));
+
+        assert_eq!(squad.name_notes("Núñez starts up front"), vec!["Darwin Núñez has left the club (joined Al-Hilal)".to_string()]);
+        assert!(squad.name_notes("Szoboszlai starts in midfield").is_empty());
+    }
+
+    #[test]
+    fn test_fix_spellings() {
+        let mut summary = Summary {
+            items: vec![Bullet { text: "Szoboslai bags a stunner 🚀".to_string(), ..Default::default() }],
+            ..Default::default()
+        };
+        assert_eq!(squad().fix_spellings(&mut summary), 1);
+        assert_eq!(summary.items[0].text, "Szoboszlai bags a stunner 🚀");
+    }
+}
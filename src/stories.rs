@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
+use log::{debug, info};
+
+use crate::config::{Config, StoryMethod};
+use crate::content_budget::{similarity, title_words};
+use crate::db::Db;
+use crate::embeddings::cosine_similarity;
+use crate::entities::Dictionary;
+use crate::followups::embed_articles;
+use crate::logger::init_logger;
+use crate::models::{Bullet, NewsArticle, Summary};
+use crate::squad::Squad;
+
+const LEAD_CHARS: usize = 500; // of the text, searched for entities along with the title
+const TITLE_WEIGHT: f32 = 0.7; // of the lexical score, the rest going to shared entities
+
+/// What an article is matched to a story by
+struct Features {
+    words: HashSet<String>,
+    entities: HashSet<String>,
+    embedding: Option<Vec<f32>>,
+}
+
+impl Features {
+    fn of(dictionary: &Dictionary, article: &NewsArticle, embedding: Option<Vec<f32>>) -> Features {
+        let lead: String = article.text.chars().take(LEAD_CHARS).collect();
+        Features {
+            words: title_words(&article.og_title),
+            entities: dictionary.tag(&format!("{}\n{}", article.og_title, lead)).into_iter().map(|t| t.name).collect(),
+            embedding,
+        }
+    }
+
+    /// Title-word and entity overlap, averaged with the embeddings' cosine similarity when
+    /// both have one
+    fn score(&self, other: &Features) -> f32 {
+        let lexical = TITLE_WEIGHT * similarity(&self.words, &other.words)
+            + (1.0 - TITLE_WEIGHT) * similarity(&self.entities, &other.entities);
+        match (&self.embedding, &other.embedding) {
+            (Some(a), Some(b)) => (lexical + cosine_similarity(a, b)) / 2.0,
+            _ => lexical,
+        }
+    }
+}
+
+/// A story and the articles it has so far
+struct Thread {
+    id: i64,
+    last_updated: NaiveDateTime,
+    members: Vec<Features>,
+}
+
+/// The thread, still open at `at`, whose closest article scores at least `threshold` against
+/// `item`, taking the best one
+fn best_thread(threads: &[Thread], item: &Features, at: NaiveDateTime, window: Duration, threshold: f32) -> Option<usize> {
+    threads
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.last_updated >= at - window)
+        .filter_map(|(i, t)| {
+            let best = t.members.iter().map(|m| item.score(m)).fold(0.0, f32::max);
+            (best >= threshold).then_some((i, best))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+/// Put each of `articles` on the open story it fits best, or start a new one, oldest article first
+async fn place_articles(cfg: &Config, db: &mut Db, threads: &mut Vec<Thread>, articles: &[NewsArticle]) -> Result<usize> {
+    let dictionary = Dictionary::new(cfg, &Squad::load(cfg)?);
+    let embeddings = match cfg.stories.method {
+        StoryMethod::Embedding => embed_articles(cfg, db, articles).await?.into_iter().map(Some).collect(),
+        StoryMethod::Lexical => vec![None; articles.len()],
+    };
+    let (window, threshold) = (Duration::days(cfg.stories.window_days as i64), match cfg.stories.method {
+        StoryMethod::Lexical => cfg.stories.threshold,
+        StoryMethod::Embedding => cfg.stories.embedding_threshold,
+    });
+
+    let mut order: Vec<usize> = (0..articles.len()).collect();
+    order.sort_by_key(|&i| articles[i].published_time);
+    let mut started = 0;
+    for i in order {
+        let (article, at) = (&articles[i], articles[i].published_time.naive_utc());
+        let Some(article_id) = article.id else { continue };
+        let features = Features::of(&dictionary, article, embeddings[i].clone());
+        let t = match best_thread(threads, &features, at, window, threshold) {
+            Some(t) => t,
+            None => {
+                let id = db.create_story(&article.og_title, at)?;
+                threads.push(Thread { id, last_updated: at, members: vec![] });
+                started += 1;
+                threads.len() - 1
+            }
+        };
+        db.set_article_story(article_id, threads[t].id, at)?;
+        threads[t].last_updated = threads[t].last_updated.max(at);
+        threads[t].members.push(features);
+    }
+    Ok(started)
+}
+
+/// Put a fetch's articles that have no story yet on the stories still open when they were
+/// published, starting new ones for the rest
+pub async fn assign_articles(cfg: &Config, db: &mut Db, articles: &[NewsArticle]) -> Result<()> {
+    let Some(earliest) = articles.iter().map(|a| a.published_time.naive_utc()).min() else { return Ok(()) };
+    let known = db.story_articles_since(earliest - Duration::days(cfg.stories.window_days as i64))?;
+
+    // a resumed run keeps the stories it already gave out
+    let placed: HashSet<i64> = known.iter().filter_map(|(_, _, a)| a.id).collect();
+    let new: Vec<NewsArticle> = articles.iter().filter(|a| a.id.is_some_and(|id| !placed.contains(&id))).cloned().collect();
+    if new.is_empty() {
+        return Ok(());
+    }
+
+    let dictionary = Dictionary::new(cfg, &Squad::load(cfg)?);
+    let known_articles: Vec<NewsArticle> = known.iter().map(|(_, _, a)| a.clone()).collect();
+    let embeddings = match cfg.stories.method {
+        StoryMethod::Embedding => embed_articles(cfg, db, &known_articles).await?.into_iter().map(Some).collect(),
+        StoryMethod::Lexical => vec![None; known.len()],
+    };
+    let mut threads: Vec<Thread> = vec![];
+    for ((story, last_updated, article), embedding) in known.into_iter().zip(embeddings) {
+        let features = Features::of(&dictionary, &article, embedding);
+        match threads.iter_mut().find(|t| t.id == story) {
+            Some(t) => t.members.push(features),
+            None => threads.push(Thread { id: story, last_updated, members: vec![features] }),
+        }
+    }
+
+    let started = place_articles(cfg, db, &mut threads, &new).await?;
+    info!("Placed {} articles on stories, {} of them starting new ones", new.len(), started);
+    Ok(())
+}
+
+/// The story most of a bullet's sources are on
+fn source_story(db: &Db, bullet: &Bullet) -> Result<Option<i64>> {
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for story in db.article_story_ids(&bullet.sources)? {
+        *counts.entry(story).or_default() += 1;
+    }
+    // ties go to the newer story
+    Ok(counts.into_iter().max_by_key(|&(story, n)| (n, story)).map(|(story, _)| story))
+}
+
+/// Put a bullet on its sources' story, or a story of its own when they have none
+fn place_bullet(db: &Db, bullet_id: i64, bullet: &Bullet, at: NaiveDateTime) -> Result<i64> {
+    let story = match source_story(db, bullet)? {
+        Some(story) => story,
+        None => db.create_story(bullet.headline.as_deref().filter(|h| !h.is_empty()).unwrap_or(&bullet.text), at)?,
+    };
+    db.set_bullet_story(bullet_id, story)?;
+    Ok(story)
+}
+
+/// Put a stored summary's accepted bullets, `ids` matching its items, on stories, labelling the
+/// ones whose story an earlier sent digest already reported
+pub fn assign_bullets(cfg: &Config, db: &Db, fetch_id: i64, ids: &[i64], summary: &mut Summary) -> Result<()> {
+    let now = Utc::now().naive_utc();
+    for (&id, b) in ids.iter().zip(summary.items.iter_mut()) {
+        if b.accepted != Some(true) {
+            continue;
+        }
+        let story = place_bullet(db, id, b, now)?;
+        if cfg.stories.label_updates && db.story_reported_before(story, fetch_id)? {
+            if let Some(title) = db.list_stories(None, Some(story))?.into_iter().next().map(|s| s.title) {
+                db.set_bullet_update_on(id, &title)?;
+                b.update_on = Some(title);
+            }
+            debug!("Bullet {} is an update on story {}", id, story);
+        }
+    }
+    Ok(())
+}
+
+/// Forget the story threads and build them again from every stored article and accepted bullet
+async fn rebuild(cfg: &Config, db: &mut Db) -> Result<()> {
+    db.clear_stories()?;
+    let articles = db.load_all_articles()?;
+    let mut threads = vec![];
+    let started = place_articles(cfg, db, &mut threads, &articles).await?;
+
+    let bullets = db.load_accepted_bullets()?;
+    for b in &bullets {
+        if let Some(id) = b.id {
+            place_bullet(db, id, b, b.published_at.unwrap_or_else(|| Utc::now().naive_utc()))?;
+        }
+    }
+    println!("Placed {} articles on {} stories, and {} bullets on them.\n", articles.len(), started, bullets.len());
+    Ok(())
+}
+
+/// Print a story's timeline of articles and sent bullets, or without `id` the stories updated in
+/// the last `days`. `rebuild` first builds every story again
+pub async fn run_story(id: Option<i64>, days: u32, rebuild_first: bool) -> Result<()> {
+    init_logger()?;
+    let cfg = Config::get_user_config()?;
+    let mut db = Db::open(&cfg)?;
+    if rebuild_first {
+        rebuild(&cfg, &mut db).await?;
+    }
+
+    let Some(id) = id else {
+        let stories = db.list_stories(Some(days), None)?;
+        if stories.is_empty() {
+            println!("No stories updated in the last {} days.", days);
+            return Ok(());
+        }
+        println!("{:>6}  {:<10}  {:<10}  {:>8} {:>8}  title", "id", "first seen", "updated", "articles", "bullets");
+        for s in stories {
+            println!(
+                "{:>6}  {:<10}  {:<10}  {:>8} {:>8}  {}",
+                s.id, s.first_seen.date(), s.last_updated.date(), s.articles, s.bullets, s.title
+            );
+        }
+        return Ok(());
+    };
+
+    let story = db.list_stories(None, Some(id))?.into_iter().next().ok_or_else(|| anyhow!("No story {id}"))?;
+    println!("#{} {} ({} to {})\n", story.id, story.title, story.first_seen.date(), story.last_updated.date());
+    for e in db.story_timeline(id)? {
+        match (&e.source, &e.url) {
+            (Some(source), Some(url)) => println!("{}  {} ({})\n                  {}", e.at.format("%Y-%m-%d %H:%M"), e.text, source, url),
+            _ => println!("{}  digest: {}", e.at.format("%Y-%m-%d %H:%M"), e.text),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(title: &str, entities: &[&str]) -> Features {
+        Features {
+            words: title_words(title),
+            entities: entities.iter().map(|e| e.to_string()).collect(),
+            embedding: None,
+        }
+    }
+
+    #[test]
+    fn test_best_thread() {
+        let at = NaiveDateTime::parse_from_str("2025-08-20 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let threads = vec![
+            Thread {
+                id: 1,
+                last_updated: at - Duration::days(2),
+                members: vec![features("Liverpool open talks with Konaté over new contract", &["Ibrahima Konaté"])],
+            },
+            Thread {
+                id: 2,
+                last_updated: at - Duration::days(1),
+                members: vec![features("Salah scores twice as Liverpool beat Forest", &["Mohamed Salah", "Nottingham Forest"])],
+            },
+            Thread {
+                id: 3,
+                last_updated: at - Duration::days(30),
+                members: vec![features("Isak contract stand-off drags on", &["Alexander Isak"])],
+            },
+        ];
+        let window = Duration::days(14);
+
+        // same saga, other words
+        let update = features("Konaté contract update: Liverpool offer rejected", &["Ibrahima Konaté"]);
+        assert_eq!(best_thread(&threads, &update, at, window, 0.4), Some(0));
+
+        // same player, different story
+        let other = features("Salah contract talks stall", &["Mohamed Salah"]);
+        assert_eq!(best_thread(&threads, &other, at, window, 0.4), None);
+
+        // a story quiet for longer than the window is closed
+        let late = features("Isak contract stand-off drags on", &["Alexander Isak"]);
+        assert_eq!(best_thread(&threads, &late, at, window, 0.4), None);
+    }
+}
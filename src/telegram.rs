@@ -2,18 +2,94 @@ use anyhow::Result;
 use teloxide::{prelude::*, types::ChatId};
 use crate::config::Config;
 
-pub async fn send_telegram_message(cfg: &Config, text: &str) -> Result<()> {
-    let Some(recipients) = &cfg.telegram_chat_ids else { return Ok(()); };
-    if recipients.is_empty() { return Ok(()); }
-    
+const MAX_MESSAGE_CHARS: usize = 4096; // Telegram rejects longer messages
+
+/// `text` in pieces Telegram accepts, split between bullets and sections where it can, then
+/// between lines, and only a line longer than a whole message mid-line
+fn split_message(text: &str) -> Vec<String> {
+    let mut chunks: Vec<String> = vec![];
+    let mut current = String::new();
+    let mut push = |piece: &str, separator: &str, current: &mut String| {
+        if !current.is_empty() && current.chars().count() + separator.len() + piece.chars().count() > MAX_MESSAGE_CHARS {
+            chunks.push(std::mem::take(current));
+        }
+        if !current.is_empty() {
+            current.push_str(separator);
+        }
+        current.push_str(piece);
+    };
+
+    for block in text.split("\n\n") {
+        if block.chars().count() <= MAX_MESSAGE_CHARS {
+            push(block, "\n\n", &mut current);
+            continue;
+        }
+        for line in block.lines() {
+            let chars: Vec<char> = line.chars().collect();
+            for piece in chars.chunks(MAX_MESSAGE_CHARS) {
+                push(&piece.iter().collect::<String>(), "\n", &mut current);
+            }
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Send each (chat ID, text) pair as its own message, or as several when it is too long for one
+pub async fn send_telegram_message(cfg: &Config, messages: &[(String, String)]) -> Result<()> {
+    if messages.is_empty() { return Ok(()); }
+
     let Some(telegram_bot_token) = &cfg.telegram_bot_token else { return Ok(()); };
 
     let bot = Bot::new(telegram_bot_token);
 
-    for recipient in recipients {
+    for (recipient, text) in messages {
         let chat_id: i64 = recipient.parse()?;
-        bot.send_message(ChatId(chat_id), text).await?;
+        for chunk in split_message(text) {
+            bot.send_message(ChatId(chat_id), chunk).await?;
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Bullet, BulletVariant, Summary};
+    use crate::utils::format_summary_plain_text;
+
+    #[test]
+    fn test_split_message() {
+        let cfg: Config = serde_yaml::from_str("model: gpt-5-mini").unwrap();
+        let detail = "Liverpool were made to work for it at Anfield, where a stubborn low block held firm until the hour. ".repeat(6);
+        let summary = Summary {
+            mood: "A busy day for the Reds.".to_string(),
+            items: (0..20)
+                .map(|i| Bullet {
+                    text: format!("Bullet {i}"),
+                    detail: Some(format!("Story {i}: {detail}")),
+                    accepted: Some(true),
+                    category: Some("club".to_string()),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let text = format_summary_plain_text(&summary, &cfg, None, BulletVariant::Detailed);
+        assert!(text.chars().count() > MAX_MESSAGE_CHARS);
+
+        let chunks = split_message(&text);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.chars().count() <= MAX_MESSAGE_CHARS));
+        // bullets are kept whole and nothing is lost
+        assert!(chunks.iter().all(|c| c.starts_with("- Story") || c.starts_with(&summary.mood)));
+        assert_eq!(chunks.join("\n\n"), text);
+
+        // a single line longer than a message is cut
+        let long = "x".repeat(MAX_MESSAGE_CHARS + 10);
+        assert_eq!(split_message(&long).iter().map(|c| c.chars().count()).collect::<Vec<_>>(), vec![MAX_MESSAGE_CHARS, 10]);
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use async_openai::types::{ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage};
+use chrono::Utc;
+use log::{debug, info};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::content_budget::truncate_content;
+use crate::config::Config;
+use crate::db::Db;
+use crate::llm::{chat_json, JsonCall};
+use crate::models::{NewsArticle, TransferChange, TransferDirection, TransferRumour, TransferStatus};
+
+/// A transfer story as one fetch's articles report it
+#[derive(Debug, Deserialize)]
+pub struct TransferReport {
+    pub player: String,
+    pub from_club: Option<String>,
+    pub to_club: Option<String>,
+    pub direction: TransferDirection,
+    pub fee: Option<String>,
+    pub status: TransferStatus,
+    pub sources: Vec<usize>, // indices into the fetch's articles
+}
+
+#[derive(Debug, Deserialize)]
+struct TransferResponse {
+    transfers: Vec<TransferReport>,
+}
+
+/// Pull every Liverpool transfer story out of the articles, one record per player and move
+pub async fn extract_transfers(cfg: &Config, articles: &[NewsArticle]) -> Result<Vec<TransferReport>> {
+    let system_prompt = r#"
+You track Liverpool FC men's team transfers. The articles are numbered [n].
+
+List every transfer story involving Liverpool the articles report, once per player and move:
+  - player: full name as the articles give it
+  - from_club / to_club: the clubs involved, null if not reported
+  - direction: "in" if the player would join Liverpool, "out" if he would leave
+  - fee: the reported fee as written (e.g. "£45m", "undisclosed"), or null
+  - status: the furthest stage the articles report
+      rumour    → linked, interest, shortlisted
+      talks     → bids made, negotiations, personal terms being discussed
+      agreed    → fee or terms agreed, medical booked, but not official
+      done      → officially announced or completed
+      collapsed → talks ended, bid rejected for good, player joined someone else
+  - sources: the numbers of the articles reporting it
+
+Ignore the women's and academy teams unless a player is joining the first team, and ignore stories that only mention a past transfer.
+"#;
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "transfers": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "player": { "type": "string" },
+                        "from_club": { "type": ["string", "null"] },
+                        "to_club": { "type": ["string", "null"] },
+                        "direction": { "type": "string", "enum": ["in", "out"] },
+                        "fee": { "type": ["string", "null"] },
+                        "status": { "type": "string", "enum": ["rumour", "talks", "agreed", "done", "collapsed"] },
+                        "sources": { "type": "array", "items": { "type": "integer" } }
+                    },
+                    "required": ["player", "from_club", "to_club", "direction", "fee", "status", "sources"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["transfers"],
+        "additionalProperties": false
+    });
+
+    let parsed: TransferResponse = chat_json(cfg, JsonCall {
+        step: "transfers",
+        schema_name: "transfer_reports",
+        schema,
+        messages: vec![
+            ChatCompletionRequestSystemMessage::from(system_prompt).into(),
+            ChatCompletionRequestUserMessage::from(truncate_content(cfg, "transfers", articles, &HashSet::new())?).into(),
+        ],
+        max_completion_tokens: 3000,
+    }).await?;
+
+    debug!("Extracted transfer reports: {:#?}", parsed.transfers);
+    Ok(parsed.transfers)
+}
+
+fn normalize(name: &str) -> String {
+    name.to_lowercase().replace(['.', '\''], "").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// "Kerkez" and "Milos Kerkez" are the same player, "Kerkez" and "Kovacs" aren't
+pub fn same_player(a: &str, b: &str) -> bool {
+    let (a, b) = (normalize(a), normalize(b));
+    a == b || a.ends_with(&format!(" {b}")) || b.ends_with(&format!(" {a}"))
+}
+
+/// "Bournemouth" and "AFC Bournemouth" are the same club; an unknown club matches any
+fn same_club(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a.map(normalize), b.map(normalize)) {
+        (Some(a), Some(b)) => a.contains(&b) || b.contains(&a),
+        _ => true,
+    }
+}
+
+/// Whether a report of `to` should replace a tracked `from`. Deals only move forward, since
+/// outlets lag behind each other, except that any deal can collapse and a collapsed one revive.
+pub fn advances(from: TransferStatus, to: TransferStatus) -> bool {
+    match (from, to) {
+        (a, b) if a == b => false,
+        (TransferStatus::Collapsed, _) | (_, TransferStatus::Collapsed) => from != TransferStatus::Done,
+        (a, b) => b > a,
+    }
+}
+
+/// Merge one fetch's reports into the tracked rumours, recording every report in the
+/// history, and return the rumours whose status changed (new ones included)
+pub fn merge_transfers(cfg: &Config, db: &Db, fetch_id: i64, articles: &[NewsArticle], reports: &[TransferReport]) -> Result<Vec<TransferChange>> {
+    let mut known = db.recent_transfer_rumours(cfg.transfers.memory_days)?;
+    let mut before: HashMap<i64, Option<TransferStatus>> = HashMap::new();
+    let mut sources: HashMap<i64, Vec<String>> = HashMap::new();
+    let now = Utc::now().naive_utc();
+
+    for report in reports {
+        // the club on the other side of the deal is what tells two rumours apart
+        let counterparty = |r: &TransferRumour| match r.direction {
+            TransferDirection::In => r.from_club.clone(),
+            TransferDirection::Out => r.to_club.clone(),
+        };
+        let reported_counterparty = match report.direction {
+            TransferDirection::In => report.from_club.as_deref(),
+            TransferDirection::Out => report.to_club.as_deref(),
+        };
+
+        let existing = known.iter().position(|r| {
+            r.direction == report.direction
+                && same_player(&r.player, &report.player)
+                && same_club(counterparty(r).as_deref(), reported_counterparty)
+        });
+
+        let index = match existing {
+            Some(i) => {
+                let rumour = &mut known[i];
+                before.entry(rumour.id).or_insert(Some(rumour.status));
+                if advances(rumour.status, report.status) {
+                    rumour.status = report.status;
+                }
+                rumour.fee = report.fee.clone().or(rumour.fee.take());
+                rumour.from_club = report.from_club.clone().or(rumour.from_club.take());
+                rumour.to_club = report.to_club.clone().or(rumour.to_club.take());
+                db.upsert_transfer_rumour(rumour)?;
+                i
+            }
+            None => {
+                let mut rumour = TransferRumour {
+                    id: 0,
+                    player: report.player.clone(),
+                    from_club: report.from_club.clone(),
+                    to_club: report.to_club.clone(),
+                    direction: report.direction,
+                    fee: report.fee.clone(),
+                    status: report.status,
+                    first_seen: now,
+                    last_updated: now,
+                };
+                rumour.id = db.upsert_transfer_rumour(&rumour)?;
+                before.insert(rumour.id, None);
+                known.push(rumour);
+                known.len() - 1
+            }
+        };
+
+        let id = known[index].id;
+        for article in report.sources.iter().filter_map(|&n| articles.get(n)) {
+            db.insert_transfer_update(id, fetch_id, report.status, report.fee.as_deref(), &article.source, article.url.as_str())?;
+            let names = sources.entry(id).or_default();
+            if !names.contains(&article.source) {
+                names.push(article.source.clone());
+            }
+        }
+    }
+
+    let changes: Vec<TransferChange> = known
+        .into_iter()
+        .filter_map(|rumour| {
+            let previous = *before.get(&rumour.id)?;
+            (previous != Some(rumour.status)).then(|| TransferChange {
+                sources: sources.remove(&rumour.id).unwrap_or_default(),
+                previous,
+                rumour,
+            })
+        })
+        .collect();
+
+    info!("{} transfer reports, {} status changes", reports.len(), changes.len());
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_player() {
+        assert!(same_player("Kerkez", "Milos Kerkez"));
+        assert!(same_player("Virgil van Dijk", "virgil van dijk"));
+        assert!(same_player("N. Williams", "n williams"));
+        assert!(!same_player("Kerkez", "Kovacs"));
+        // a surname that only ends another name isn't enough
+        assert!(!same_player("Son", "Robertson"));
+    }
+
+    #[test]
+    fn test_advances() {
+        use TransferStatus::*;
+        assert!(advances(Rumour, Talks));
+        assert!(advances(Talks, Done));
+        // a lagging outlet doesn't move a deal back
+        assert!(!advances(Agreed, Rumour));
+        assert!(advances(Agreed, Collapsed));
+        assert!(advances(Collapsed, Talks));
+        assert!(!advances(Done, Collapsed));
+        assert!(!advances(Talks, Talks));
+    }
+}
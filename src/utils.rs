@@ -1,22 +1,167 @@
-use crate::models::Summary;
+use crate::config::Config;
+use crate::models::{AvailabilityChange, Bullet, BulletVariant, MatchSection, Summary, TransferChange};
+use crate::ranking::importance_of;
 use regex::Regex;
+use std::cmp::Reverse;
 
-pub fn format_summary_plain_text(summary: &Summary) -> String {
+/// Render the accepted, non-deferred bullets under a header per category. Bullets are sorted
+/// by importance, and sections by their most important bullet (ties keep taxonomy order).
+/// `sections` limits the output to those category names, the tracker blocks going with their
+/// configured `section`; bullets without a known category go under "Other" unless sections
+/// are limited. `variant` picks each bullet's length.
+pub fn format_summary_plain_text(summary: &Summary, cfg: &Config, sections: Option<&[String]>, variant: BulletVariant) -> String {
+    let categories = &cfg.categories;
     let mut output = String::new();
+    let wanted = |name: &str| sections.is_none_or(|s| s.iter().any(|n| n == name));
 
     // Mood sentence
     output.push_str(&format!("{}\n\n", summary.mood));
 
-    // Bullet points
-    for bullet in &summary.items {
-        if bullet.accepted == Some(true) {
-            output.push_str(&format!("- {}\n\n", bullet.text));
+    if let Some(section) = &summary.match_section {
+        output.push_str(&format!("{}\n\n", format_match_section(section)));
+    }
+
+    if !summary.transfer_changes.is_empty() && wanted(&cfg.transfers.section) {
+        output.push_str("🔄 Transfer tracker\n\n");
+        for change in &summary.transfer_changes {
+            output.push_str(&format!("- {}\n\n", format_transfer_change(change)));
+        }
+    }
+
+    if !summary.availability_changes.is_empty() && wanted(&cfg.availability.section) {
+        output.push_str("🏥 Availability\n\n");
+        for change in &summary.availability_changes {
+            output.push_str(&format!("- {}\n\n", format_availability_change(change)));
+        }
+    }
+
+    let mut accepted: Vec<&Bullet> = summary.items.iter()
+        .filter(|b| b.accepted == Some(true) && !b.deferred)
+        .collect();
+    accepted.sort_by_key(|b| Reverse(importance_of(b)));
+
+    let mut grouped: Vec<(String, Vec<&Bullet>)> = categories.iter()
+        .filter(|c| wanted(&c.name))
+        .map(|c| {
+            let bullets = accepted.iter().copied()
+                .filter(|b| b.category.as_deref() == Some(c.name.as_str()))
+                .collect();
+            (c.title(), bullets)
+        })
+        .collect();
+
+    if sections.is_none() {
+        let other = accepted.iter().copied()
+            .filter(|b| !categories.iter().any(|c| b.category.as_deref() == Some(c.name.as_str())))
+            .collect();
+        grouped.push(("Other".to_string(), other));
+    }
+
+    grouped.retain(|(_, bullets)| !bullets.is_empty());
+    grouped.sort_by_key(|(_, bullets)| Reverse(importance_of(bullets[0])));
+
+    for (title, bullets) in grouped {
+        output.push_str(&format!("{}\n\n", title));
+        for bullet in bullets {
+            // only flagged, not dropped, bullets reach here unsupported
+            let marker = if bullet.grounded == Some(false) { " (unverified ⚠️)" } else { "" };
+            let update = bullet.update_on.as_ref().map(|t| format!("Update on \"{t}\": ")).unwrap_or_default();
+            output.push_str(&format!("- {}{}{}\n\n", update, bullet.variant_text(variant), marker));
+        }
+    }
+
+    output.trim().to_string()
+}
+
+/// "Kerkez (Bournemouth → Liverpool): rumour → talks, £45m (This Is Anfield)"
+pub fn format_transfer_change(change: &TransferChange) -> String {
+    let r = &change.rumour;
+    let status = match change.previous {
+        Some(previous) => format!("{} → {}", previous.as_str(), r.status.as_str()),
+        None => format!("new, {}", r.status.as_str()),
+    };
+    let mut line = format!("{} ({}): {}", r.player, r.route(), status);
+    if let Some(fee) = &r.fee {
+        line.push_str(&format!(", {}", fee));
+    }
+    if !change.sources.is_empty() {
+        line.push_str(&format!(" ({})", change.sources.join(", ")));
+    }
+    line
+}
+
+/// "Alisson: out → available (This Is Anfield)"
+pub fn format_availability_change(change: &AvailabilityChange) -> String {
+    let p = &change.player;
+    let mut line = match change.previous {
+        Some(previous) => format!("{}: {} → {}", p.player, previous.as_str(), p.status.as_str()),
+        None => format!("{}: {}", p.player, p.status.as_str()),
+    };
+    if let Some(issue) = &p.issue {
+        line.push_str(&format!(" ({})", issue));
+    }
+    if let Some(expected) = &p.expected_return {
+        line.push_str(&format!(", back {}", expected));
+    }
+    if !change.sources.is_empty() {
+        line.push_str(&format!(" ({})", change.sources.join(", ")));
+    }
+    line
+}
+
+/// The match-day preview or post-match report as a block of plain text
+pub fn format_match_section(section: &MatchSection) -> String {
+    fn list(output: &mut String, title: &str, items: &[String]) {
+        if !items.is_empty() {
+            output.push_str(&format!("{}\n\n", title));
+            for item in items {
+                output.push_str(&format!("- {}\n", item));
+            }
+            output.push('\n');
+        }
+    }
+
+    let mut output = String::new();
+    match section {
+        MatchSection::Preview(p) => {
+            output.push_str(&format!("⚽ Match day: {}, kick-off {} UTC\n\n", p.fixture, p.kickoff.format("%H:%M")));
+            list(&mut output, "Team news", &p.team_news);
+            list(&mut output, "Injuries", &p.injuries);
+            if !p.opponent_form.is_empty() {
+                output.push_str(&format!("Opponent form\n\n{}\n", p.opponent_form));
+            }
+        }
+        MatchSection::Report(r) => {
+            output.push_str(&format!("📋 Match report: {}\n\n", r.score));
+            if !r.scorers.is_empty() {
+                output.push_str(&format!("Scorers: {}\n\n", r.scorers.join(", ")));
+            }
+            let ratings: Vec<String> = r.ratings.iter()
+                .map(|p| format!("{} {}: {}", p.player, p.rating, p.note))
+                .collect();
+            list(&mut output, "Ratings", &ratings);
+            list(&mut output, "Reactions", &r.reactions);
+            if !r.verdict.is_empty() {
+                output.push_str(&format!("{}\n", r.verdict));
+            }
         }
     }
 
     output.trim().to_string()
 }
 
+/// (recipient, text) pairs with each recipient's own section and variant choice applied,
+/// `channel_variant` for recipients without one
+pub fn render_for_recipients(cfg: &Config, summary: &Summary, recipients: &[String], channel_variant: BulletVariant) -> Vec<(String, String)> {
+    recipients
+        .iter()
+        .map(|r| {
+            let text = format_summary_plain_text(summary, cfg, cfg.sections_for(r), cfg.variant_for(r, channel_variant));
+            (r.clone(), text)
+        })
+        .collect()
+}
+
 /// Remove HTML tags from text content to clean up stray tags that make it through scraping
 /// 
 /// This function is specifically designed to clean up HTML content that sometimes appears
@@ -90,6 +235,7 @@ pub fn clean_html_tags(text: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::{AvailabilityStatus, MatchReport, PlayerAvailability, PlayerRating};
 
     #[test]
     fn test_clean_html_tags() {
@@ -128,4 +274,105 @@ mod tests {
         let expected = "Some content";
         assert_eq!(clean_html_tags(empty_tags), expected);
     }
+
+    #[test]
+    fn test_format_availability_change() {
+        let change = AvailabilityChange {
+            player: PlayerAvailability {
+                id: 1,
+                player: "Alisson".to_string(),
+                status: AvailabilityStatus::Out,
+                issue: Some("hamstring".to_string()),
+                expected_return: Some("after the international break".to_string()),
+                last_updated: chrono::Utc::now().naive_utc(),
+            },
+            previous: Some(AvailabilityStatus::Doubtful),
+            sources: vec!["This Is Anfield".to_string()],
+        };
+        assert_eq!(
+            format_availability_change(&change),
+            "Alisson: doubtful → out (hamstring), back after the international break (This Is Anfield)"
+        );
+    }
+
+    #[test]
+    fn test_format_match_report() {
+        let report = MatchSection::Report(MatchReport {
+            fixture: "Liverpool vs Everton".to_string(),
+            score: "Liverpool 2-0 Everton".to_string(),
+            scorers: vec!["Salah 12'".to_string(), "Gakpo 70'".to_string()],
+            ratings: vec![PlayerRating { player: "Salah".to_string(), rating: 8.5, note: "ran the show".to_string() }],
+            reactions: vec![],
+            verdict: "Derby day done right.".to_string(),
+        });
+        assert_eq!(
+            format_match_section(&report),
+            "📋 Match report: Liverpool 2-0 Everton\n\nScorers: Salah 12', Gakpo 70'\n\nRatings\n\n- Salah 8.5: ran the show\n\nDerby day done right."
+        );
+    }
+
+    #[test]
+    fn test_format_summary_sections() {
+        let cfg: Config = serde_yaml::from_str("model: gpt-5-mini").unwrap();
+        let bullet = |text: &str, category: Option<&str>, accepted: bool| Bullet {
+            text: text.to_string(),
+            accepted: Some(accepted),
+            category: category.map(str::to_string),
+            ..Default::default()
+        };
+        let summary = Summary {
+            mood: "The mood is positive.".to_string(),
+            items: vec![
+                bullet("Win at Anfield", Some("match"), true),
+                bullet("New signing", Some("transfers"), true),
+                bullet("Rejected rumour", Some("transfers"), false),
+                bullet("Uncategorized", None, true),
+            ],
+            date: chrono::Utc::now().date_naive(),
+            ..Default::default()
+        };
+
+        // all sections, in taxonomy order, with uncategorized bullets last
+        let text = format_summary_plain_text(&summary, &cfg, None, BulletVariant::Standard);
+        assert_eq!(
+            text,
+            "The mood is positive.\n\nTransfers\n\n- New signing\n\nMatch\n\n- Win at Anfield\n\nOther\n\n- Uncategorized"
+        );
+
+        // a recipient's section choice drops everything else
+        let sections = vec!["match".to_string()];
+        let text = format_summary_plain_text(&summary, &cfg, Some(&sections), BulletVariant::Standard);
+        assert_eq!(text, "The mood is positive.\n\nMatch\n\n- Win at Anfield");
+    }
+
+    #[test]
+    fn test_format_summary_variants() {
+        let cfg: Config = serde_yaml::from_str(r#"
+model: gpt-5-mini
+recipients:
+  "123":
+    variant: detailed
+"#).unwrap();
+        let summary = Summary {
+            mood: "The mood is positive.".to_string(),
+            items: vec![
+                Bullet {
+                    text: "Salah signs a new two-year deal 🔴".to_string(),
+                    headline: Some("Salah signs new deal".to_string()),
+                    detail: Some("Salah has signed a new two-year deal, ending months of talk.".to_string()),
+                    accepted: Some(true),
+                    category: Some("transfers".to_string()),
+                    ..Default::default()
+                },
+                // carried over from before variants existed
+                Bullet { text: "Old bullet".to_string(), accepted: Some(true), category: Some("club".to_string()), ..Default::default() },
+            ],
+            ..Default::default()
+        };
+
+        let messages = render_for_recipients(&cfg, &summary, &["123".to_string(), "456".to_string()], BulletVariant::Headline);
+        assert!(messages[0].1.contains("- Salah has signed a new two-year deal, ending months of talk."));
+        assert!(messages[1].1.contains("- Salah signs new deal\n"));
+        assert!(messages[1].1.contains("- Old bullet"));
+    }
 }